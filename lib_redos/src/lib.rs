@@ -2,17 +2,393 @@
 
 /// 线程 ID 使用 `isize`，可以用负数表示错误
 pub type ThreadID = isize;
+/// 进程 ID 使用 `isize`，可以用负数表示错误
+pub type ProcessID = isize;
 pub type MutexID = usize;
+/// 信号量在进程中的编号
+pub type SemID = usize;
+
+// 系统调用出错时，返回以下错误码的相反数
+
+/// 文件或目录不存在
+pub const ENOENT: isize = 2;
+/// 进程不存在
+pub const ESRCH: isize = 3;
+/// 阻塞的系统调用被打断（线程被终止）
+pub const EINTR: isize = 4;
+/// 读写出错
+pub const EIO: isize = 5;
+/// 不是合法的可执行文件
+pub const ENOEXEC: isize = 8;
+/// 文件描述符不存在
+pub const EBADF: isize = 9;
+/// 没有符合条件的子进程
+pub const ECHILD: isize = 10;
+/// 资源暂时不可用，需要重试
+pub const EAGAIN: isize = 11;
+/// 内存不足
+pub const ENOMEM: isize = 12;
+/// 非法的用户地址
+pub const EFAULT: isize = 14;
+/// 资源正在被使用
+pub const EBUSY: isize = 16;
+/// 文件已经存在
+pub const EEXIST: isize = 17;
+/// 路径中的某一项不是目录
+pub const ENOTDIR: isize = 20;
+/// 是一个目录
+pub const EISDIR: isize = 21;
+/// 参数不合法
+pub const EINVAL: isize = 22;
+/// 打开的文件描述符过多
+pub const EMFILE: isize = 24;
+/// 文件不支持移动读写位置
+pub const ESPIPE: isize = 29;
+/// 管道的读端已经全部关闭
+pub const EPIPE: isize = 32;
+/// 缓冲区太小
+pub const ERANGE: isize = 34;
+/// 目录不为空
+pub const ENOTEMPTY: isize = 39;
 
 pub const SYS_SLEEP: usize = 3;
 pub const SYS_JOIN: usize = 4;
+/// Linux 中的 62 号已被 [`SYS_CREATE_THREAD`] 占用
+pub const SYS_LSEEK: usize = 8;
+/// Linux 中的 17 号已被 [`SYS_MUTEX_UNLOCK`] 占用
+pub const SYS_GETCWD: usize = 9;
 
 pub const SYS_MUTEX_CREATE: usize = 14;
 pub const SYS_MUTEX_DESTROY: usize = 15;
 pub const SYS_MUTEX_LOCK: usize = 16;
 pub const SYS_MUTEX_UNLOCK: usize = 17;
 
+/// 第二个参数为初始的资源数
+pub const SYS_SEM_CREATE: usize = 18;
+pub const SYS_SEM_WAIT: usize = 19;
+pub const SYS_SEM_POST: usize = 20;
+pub const SYS_SEM_DESTROY: usize = 21;
+
+pub const SYS_DUP: usize = 23;
+/// 与 Linux 的 dup3 相同，第三个参数可以为 [`O_CLOEXEC`]；新旧描述符相同时直接返回
+pub const SYS_DUP2: usize = 24;
+
+pub const SYS_MKDIR: usize = 34;
+pub const SYS_UNLINK: usize = 35;
+pub const SYS_RENAME: usize = 38;
+pub const SYS_CHDIR: usize = 49;
+
+pub const SYS_OPEN: usize = 56;
+pub const SYS_CLOSE: usize = 57;
+pub const SYS_PIPE: usize = 59;
+pub const SYS_GETDENTS: usize = 61;
 pub const SYS_CREATE_THREAD: usize = 62;
 pub const SYS_READ: usize = 63;
 pub const SYS_WRITE: usize = 64;
+pub const SYS_STAT: usize = 79;
+pub const SYS_FSTAT: usize = 80;
 pub const SYS_EXIT: usize = 93;
+pub const SYS_EXIT_GROUP: usize = 94;
+/// 与 Linux 不同，参数直接为休眠的纳秒数
+pub const SYS_NANOSLEEP: usize = 101;
+pub const SYS_CLOCK_GETTIME: usize = 113;
+pub const SYS_BRK: usize = 214;
+pub const SYS_MUNMAP: usize = 215;
+pub const SYS_FORK: usize = 220;
+pub const SYS_EXEC: usize = 221;
+pub const SYS_MMAP: usize = 222;
+pub const SYS_MPROTECT: usize = 226;
+pub const SYS_MSYNC: usize = 227;
+pub const SYS_SCHED_YIELD: usize = 124;
+pub const SYS_KILL: usize = 129;
+pub const SYS_SIGACTION: usize = 134;
+pub const SYS_SIGPROCMASK: usize = 135;
+pub const SYS_SIGRETURN: usize = 139;
+pub const SYS_SETPRIORITY: usize = 140;
+pub const SYS_GETPRIORITY: usize = 141;
+/// 不支持时区，第二个参数被忽略
+pub const SYS_GETTIMEOFDAY: usize = 169;
+pub const SYS_WAITPID: usize = 260;
+
+/// waitpid 的选项：没有退出的子进程时立即返回 0
+pub const WNOHANG: usize = 1;
+
+/// setpriority / getpriority 的对象：进程（进程 ID 为 0 时表示当前进程）
+pub const PRIO_PROCESS: usize = 0;
+/// 最小的 nice 值，优先级最高
+pub const NICE_MIN: isize = -20;
+/// 最大的 nice 值，优先级最低
+pub const NICE_MAX: isize = 19;
+
+// mmap / mprotect 的权限
+
+/// 不可访问
+pub const PROT_NONE: usize = 0;
+/// 可读
+pub const PROT_READ: usize = 1;
+/// 可写
+pub const PROT_WRITE: usize = 2;
+/// 可执行
+pub const PROT_EXEC: usize = 4;
+
+// mmap 的映射方式
+
+/// 与其他进程共享修改
+pub const MAP_SHARED: usize = 0x01;
+/// 修改仅对当前进程可见
+pub const MAP_PRIVATE: usize = 0x02;
+/// 必须映射到给定的地址，覆盖原有的映射
+pub const MAP_FIXED: usize = 0x10;
+/// 匿名映射，不对应任何文件
+pub const MAP_ANONYMOUS: usize = 0x20;
+
+// open 的选项
+
+/// 只读
+pub const O_RDONLY: usize = 0;
+/// 只写
+pub const O_WRONLY: usize = 1;
+/// 读写
+pub const O_RDWR: usize = 2;
+/// 读写权限所占的位
+pub const O_ACCMODE: usize = 3;
+/// 文件不存在时创建
+pub const O_CREAT: usize = 0x40;
+/// 与 `O_CREAT` 一起使用，文件已经存在时失败
+pub const O_EXCL: usize = 0x80;
+/// 打开时将文件清空
+pub const O_TRUNC: usize = 0x200;
+/// 每次写入前将读写位置移到文件末尾
+pub const O_APPEND: usize = 0x400;
+/// 执行 exec 时关闭该文件描述符
+pub const O_CLOEXEC: usize = 0x80000;
+
+// lseek 的基准位置
+
+/// 从文件开头计算
+pub const SEEK_SET: usize = 0;
+/// 从当前位置计算
+pub const SEEK_CUR: usize = 1;
+/// 从文件末尾计算
+pub const SEEK_END: usize = 2;
+
+// 文件类型，保存在 `Stat::mode` 和 `Dirent::mode` 的高位中
+
+/// 文件类型所占的位
+pub const S_IFMT: u32 = 0o170000;
+/// 管道
+pub const S_IFIFO: u32 = 0o010000;
+/// 字符设备
+pub const S_IFCHR: u32 = 0o020000;
+/// 目录
+pub const S_IFDIR: u32 = 0o040000;
+/// 块设备
+pub const S_IFBLK: u32 = 0o060000;
+/// 普通文件
+pub const S_IFREG: u32 = 0o100000;
+/// 符号链接
+pub const S_IFLNK: u32 = 0o120000;
+/// 套接字
+pub const S_IFSOCK: u32 = 0o140000;
+
+/// stat / fstat 返回的文件信息
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Stat {
+    /// 所在设备的编号
+    pub dev: u64,
+    /// inode 编号
+    pub ino: u64,
+    /// 文件类型和权限
+    pub mode: u32,
+    /// 硬链接数
+    pub nlink: u32,
+    /// 文件大小（字节）
+    pub size: u64,
+    /// 块大小
+    pub blksize: u64,
+    /// 占用的块数
+    pub blocks: u64,
+    /// 最后访问时间（秒）
+    pub atime: i64,
+    /// 最后修改时间（秒）
+    pub mtime: i64,
+    /// 最后改变状态的时间（秒）
+    pub ctime: i64,
+}
+
+impl Stat {
+    /// 是否为目录
+    pub fn is_dir(&self) -> bool {
+        self.mode & S_IFMT == S_IFDIR
+    }
+}
+
+/// 目录项名字的最大长度，包括结尾的 `\0`
+pub const DIRENT_NAME_LENGTH: usize = 256;
+
+/// getdents 返回的目录项
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Dirent {
+    /// inode 编号
+    pub ino: u64,
+    /// 文件类型，与 `Stat::mode` 的高位相同
+    pub mode: u32,
+    /// 以 `\0` 结尾的名字
+    pub name: [u8; DIRENT_NAME_LENGTH],
+}
+
+impl Default for Dirent {
+    fn default() -> Self {
+        Self {
+            ino: 0,
+            mode: 0,
+            name: [0; DIRENT_NAME_LENGTH],
+        }
+    }
+}
+
+impl Dirent {
+    /// 目录项的名字
+    pub fn name(&self) -> &str {
+        let len = self
+            .name
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(DIRENT_NAME_LENGTH);
+        core::str::from_utf8(&self.name[..len]).unwrap_or("")
+    }
+}
+
+// clock_gettime 的时钟
+
+/// 墙上时间，自 1970-01-01 00:00:00 UTC 起
+pub const CLOCK_REALTIME: usize = 0;
+/// 单调时间，自系统启动起
+pub const CLOCK_MONOTONIC: usize = 1;
+
+/// 每秒的纳秒数
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// clock_gettime 返回的时间
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TimeSpec {
+    /// 秒
+    pub tv_sec: i64,
+    /// 不足一秒的纳秒数
+    pub tv_nsec: i64,
+}
+
+impl TimeSpec {
+    /// 从纳秒数转换
+    pub fn from_nanos(nanos: u64) -> Self {
+        Self {
+            tv_sec: (nanos / NANOS_PER_SEC) as i64,
+            tv_nsec: (nanos % NANOS_PER_SEC) as i64,
+        }
+    }
+
+    /// 转换为纳秒数
+    pub fn as_nanos(&self) -> u64 {
+        self.tv_sec as u64 * NANOS_PER_SEC + self.tv_nsec as u64
+    }
+}
+
+/// gettimeofday 返回的时间
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TimeVal {
+    /// 秒
+    pub tv_sec: i64,
+    /// 不足一秒的微秒数
+    pub tv_usec: i64,
+}
+
+impl TimeVal {
+    /// 从纳秒数转换，不足一微秒的部分舍去
+    pub fn from_nanos(nanos: u64) -> Self {
+        Self {
+            tv_sec: (nanos / NANOS_PER_SEC) as i64,
+            tv_usec: (nanos % NANOS_PER_SEC / 1000) as i64,
+        }
+    }
+}
+
+// 信号编号，与 Linux 相同
+
+/// 终端挂断
+pub const SIGHUP: usize = 1;
+/// 终端中断（Ctrl-C）
+pub const SIGINT: usize = 2;
+/// 终端退出
+pub const SIGQUIT: usize = 3;
+/// 非法指令
+pub const SIGILL: usize = 4;
+/// 断点
+pub const SIGTRAP: usize = 5;
+/// 调用 abort
+pub const SIGABRT: usize = 6;
+/// 总线错误（地址未对齐）
+pub const SIGBUS: usize = 7;
+/// 算术错误
+pub const SIGFPE: usize = 8;
+/// 强制终止，不能被捕获、忽略或屏蔽
+pub const SIGKILL: usize = 9;
+/// 用户自定义信号 1
+pub const SIGUSR1: usize = 10;
+/// 非法内存访问
+pub const SIGSEGV: usize = 11;
+/// 用户自定义信号 2
+pub const SIGUSR2: usize = 12;
+/// 向读端已关闭的管道写入
+pub const SIGPIPE: usize = 13;
+/// 定时器
+pub const SIGALRM: usize = 14;
+/// 请求终止
+pub const SIGTERM: usize = 15;
+/// 子进程退出
+pub const SIGCHLD: usize = 17;
+/// 继续执行被暂停的进程
+pub const SIGCONT: usize = 18;
+/// 暂停进程，不能被捕获、忽略或屏蔽
+pub const SIGSTOP: usize = 19;
+/// 终端暂停（Ctrl-Z）
+pub const SIGTSTP: usize = 20;
+/// 信号编号的上界，有效的信号编号为 1 到 `NSIG - 1`
+pub const NSIG: usize = 32;
+
+/// 信号集合，第 `n` 位表示 `n` 号信号
+pub type SigSet = u64;
+
+/// 默认的信号处理方式
+pub const SIG_DFL: usize = 0;
+/// 忽略信号
+pub const SIG_IGN: usize = 1;
+
+// sigprocmask 的操作
+
+/// 屏蔽集合中的信号
+pub const SIG_BLOCK: usize = 0;
+/// 解除屏蔽集合中的信号
+pub const SIG_UNBLOCK: usize = 1;
+/// 将屏蔽的信号设置为给定的集合
+pub const SIG_SETMASK: usize = 2;
+
+/// 被信号终止的进程的返回值为 `SIGNAL_EXIT_BASE + 信号编号`，与 shell 的约定相同
+pub const SIGNAL_EXIT_BASE: isize = 128;
+
+/// sigaction 使用的信号处理方式
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SigAction {
+    /// [`SIG_DFL`]、[`SIG_IGN`] 或处理函数的地址
+    ///
+    /// 处理函数的第一个参数为信号编号；由异常引起的信号（如 [`SIGSEGV`]），第二个参数为引起异常的地址
+    pub handler: usize,
+    /// 执行处理函数期间额外屏蔽的信号，当前信号总是会被屏蔽
+    pub mask: SigSet,
+    /// 处理函数返回时跳转的地址，需要在不改变栈指针的情况下调用 [`SYS_SIGRETURN`]
+    pub restorer: usize,
+}
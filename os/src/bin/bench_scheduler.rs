@@ -0,0 +1,86 @@
+//! 调度器的性能测试
+//!
+//! 在不同数量的线程下，测量各个调度算法选出下一个线程（每个时间片一次），
+//! 以及移除再加入一个线程（线程休眠再被唤醒）所用的时间
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate redos;
+
+use redos::memory;
+use redos::memory::addr::PhysicalAddress;
+use redos::process::scheduler::{Scheduler, SchedulerImpl};
+use redos::process::SchedulerKind;
+use redos::sbi::shutdown;
+use riscv::register::time;
+
+/// 参与测试的调度算法
+const KINDS: [SchedulerKind; 5] = [
+    SchedulerKind::Hrrn,
+    SchedulerKind::RoundRobin,
+    SchedulerKind::Stride,
+    SchedulerKind::Mlfq,
+    SchedulerKind::Cfs,
+];
+
+/// 调度器中的线程数
+const THREAD_COUNTS: [usize; 4] = [16, 64, 256, 1024];
+
+/// 每项测试重复的次数
+const ROUNDS: usize = 4096;
+
+/// Rust 的入口函数
+///
+/// 不开启中断，测试结束后关机
+#[no_mangle]
+pub extern "C" fn rust_main(_hart_id: usize, _dtb_pa: PhysicalAddress) -> ! {
+    memory::init();
+
+    println!("time per 1000 operations, in ticks of the `time` CSR");
+    println!(
+        "{:<10} {:>8} {:>12} {:>12}",
+        "scheduler", "threads", "get_next", "sleep/wake"
+    );
+    for &kind in KINDS.iter() {
+        for &count in THREAD_COUNTS.iter() {
+            let (pick, requeue) = measure(kind, count);
+            println!(
+                "{:<10} {:>8} {:>12} {:>12}",
+                kind.name(),
+                count,
+                pick,
+                requeue
+            );
+        }
+    }
+    shutdown()
+}
+
+/// 测量调度器中有 `count` 个线程时，每 1000 次 `get_next()` 和每 1000 次移除再加入线程所用的时间
+///
+/// 用整数代替线程，优先级各不相同
+fn measure(kind: SchedulerKind, count: usize) -> (usize, usize) {
+    let mut scheduler = SchedulerImpl::new(kind);
+    for thread in 0..count {
+        scheduler.add_thread(thread);
+        scheduler.set_priority(thread, 1 + thread % 40);
+    }
+
+    let start = time::read();
+    for _ in 0..ROUNDS {
+        scheduler.get_next();
+    }
+    let pick = (time::read() - start) * 1000 / ROUNDS;
+
+    let start = time::read();
+    for round in 0..ROUNDS {
+        // 依次让不同位置的线程休眠再被唤醒
+        let thread = round * 7919 % count;
+        scheduler.remove_thread(&thread);
+        scheduler.add_thread(thread);
+    }
+    let requeue = (time::read() - start) * 1000 / ROUNDS;
+
+    (pick, requeue)
+}
@@ -16,10 +16,10 @@ extern crate redos;
 
 use redos::memory;
 use redos::memory::addr::PhysicalAddress;
-use redos::process::process::Process;
+use redos::process::process::{set_init_process, Process};
 use redos::process::thread::{create_kernel_thread, create_user_process};
 use redos::process::PROCESSOR;
-use redos::{drivers, fs, interrupt};
+use redos::{drivers, fs, interrupt, smp};
 
 /// Rust 的入口函数
 ///
@@ -44,17 +44,17 @@ pub extern "C" fn rust_main(_hart_id: usize, dtb_pa: PhysicalAddress) -> ! {
             ));
         }
         processor.add_thread(create_user_process("hello_world"));
-        processor.add_thread(create_user_process("notebook"));
+        // init 进程负责启动 notebook，并回收孤儿进程
+        let init = create_user_process("init");
+        set_init_process(&init.process);
+        processor.add_thread(init);
     }
 
-    extern "C" {
-        fn __restore(context: usize);
-    }
-    // 获取第一个线程的 Context
-    let context = PROCESSOR.lock().prepare_next_thread();
-    // 启动第一个线程
-    unsafe { __restore(context as usize) };
-    unreachable!()
+    // 启动其他处理器核，它们从调度器中迁移线程执行
+    smp::init();
+
+    // 进入调度循环，启动第一个线程
+    redos::process::processor::run()
 }
 
 fn sample_process(id: usize) {
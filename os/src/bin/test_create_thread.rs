@@ -38,14 +38,8 @@ pub extern "C" fn rust_main(_hart_id: usize, dtb_pa: PhysicalAddress) -> ! {
         processor.add_thread(create_user_process("join"));
     }
 
-    extern "C" {
-        fn __restore(context: usize);
-    }
-    // 获取第一个线程的 Context
-    let context = PROCESSOR.lock().prepare_next_thread();
-    // 启动第一个线程
-    unsafe { __restore(context as usize) };
-    unreachable!()
+    // 进入调度循环，启动第一个线程
+    redos::process::processor::run()
 }
 
 fn sample_process(id: usize) {
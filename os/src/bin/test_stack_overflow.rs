@@ -0,0 +1,65 @@
+//! # 全局属性
+//! - `#![no_std]`
+//!   禁用标准库
+#![no_std]
+//!
+//! - `#![no_main]`
+//!   不使用 `main` 函数等全部 Rust-level 入口点来作为程序入口
+#![no_main]
+//! # 一些 unstable 的功能需要在 crate 层级声明后才可以使用
+//! - `#![feature(llvm_asm)]`
+//!   内嵌汇编
+#![feature(llvm_asm)]
+
+#[macro_use]
+extern crate redos;
+
+use redos::drivers;
+use redos::memory;
+use redos::memory::addr::PhysicalAddress;
+use redos::process::process::Process;
+use redos::process::thread::create_kernel_thread;
+use redos::process::PROCESSOR;
+
+/// Rust 的入口函数
+///
+/// 在 `_start` 为我们进行了一系列准备之后，这是第一个被调用的 Rust 函数
+///
+/// 内核线程无限递归，耗尽内核栈后访问保护区，预期输出 `panic ... kernel stack overflow at ...`
+#[no_mangle]
+pub extern "C" fn rust_main(_hart_id: usize, dtb_pa: PhysicalAddress) -> ! {
+    println!("Hello stack overflow!");
+    // 初始化各种模块
+    redos::interrupt::init();
+    memory::init();
+    drivers::init(dtb_pa);
+
+    let remap = memory::mapping::MemorySet::new_kernel().unwrap();
+    remap.activate();
+
+    PROCESSOR.lock().add_thread(create_kernel_thread(
+        Process::new_kernel().unwrap(),
+        overflow_process as usize,
+        None,
+    ));
+
+    // 进入调度循环，启动线程
+    redos::process::processor::run()
+}
+
+fn overflow_process() {
+    let depth = recurse(0);
+    // 正常情况下不会执行到这里
+    println!("test failed: recursed {} times without a fault", depth);
+}
+
+/// 每一层在栈上占用 1 KB，不会被优化为循环
+fn recurse(depth: usize) -> usize {
+    let mut buffer = [0u8; 1024];
+    unsafe { core::ptr::write_volatile(&mut buffer[0], depth as u8) };
+    if depth == usize::MAX {
+        return depth;
+    }
+    let result = recurse(depth + 1);
+    (unsafe { core::ptr::read_volatile(&buffer[0]) } as usize).wrapping_add(result)
+}
@@ -36,10 +36,6 @@ pub extern "C" fn rust_main(_hart_id: usize, dtb_pa: PhysicalAddress) -> ! {
     remap.activate();
 
     println!("kernel remapped");
-    extern "C" {
-        fn __restore(context: usize);
-    }
-
     {
         let mut processor = PROCESSOR.lock();
         // 创建一个内核进程
@@ -54,11 +50,8 @@ pub extern "C" fn rust_main(_hart_id: usize, dtb_pa: PhysicalAddress) -> ! {
         }
     }
 
-    // 获取第一个线程的 Context，具体原理后面讲解
-    let context = PROCESSOR.lock().prepare_next_thread();
-    // 启动第一个线程
-    unsafe { __restore(context as usize) };
-    unreachable!();
+    // 进入调度循环，启动第一个线程
+    redos::process::processor::run()
 }
 
 fn sample_process(id: usize) {
@@ -0,0 +1,25 @@
+//! 内核命令行参数
+//!
+//! 由设备树 `/chosen` 节点的 `bootargs` 提供（即 QEMU 的 `-append`），格式为空格分隔的 `key=value`。
+//! 目前支持：
+//! - `scheduler=hrrn|rr|stride|mlfq|cfs`：选择线程调度算法，优先于 cargo feature 的选择
+
+use crate::process::processor::set_scheduler;
+use crate::process::SchedulerKind;
+
+/// 解析并应用内核命令行参数，需要在创建任何线程之前调用
+pub fn parse(bootargs: &str) {
+    for option in bootargs.split_whitespace() {
+        let mut pair = option.splitn(2, '=');
+        match (pair.next(), pair.next()) {
+            (Some("scheduler"), Some(name)) => match SchedulerKind::from_name(name) {
+                Some(kind) => {
+                    set_scheduler(kind);
+                    println!("scheduler: {:?}", kind);
+                }
+                None => println!("unknown scheduler: {}", name),
+            },
+            _ => println!("unknown kernel option: {}", option),
+        }
+    }
+}
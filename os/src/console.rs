@@ -62,4 +62,16 @@ macro_rules! println {
     ($fmt: literal $(, $($arg: tt)+)?) => {
         $crate::console::print(format_args!(concat!($fmt, "\n") $(, $($arg)+)?));
     }
+}
+
+/// 打印调试信息，只在开启 `debug-log` feature 时输出
+///
+/// 系统调用路径上的提示信息会与多个处理器核上用户程序的输出交错在一起，因此默认不打印
+#[macro_export]
+macro_rules! debug_println {
+    ($fmt: literal $(, $($arg: tt)+)?) => {
+        if cfg!(feature = "debug-log") {
+            $crate::println!($fmt $(, $($arg)+)?);
+        }
+    }
 }
\ No newline at end of file
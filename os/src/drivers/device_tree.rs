@@ -1,8 +1,11 @@
 //! 设备树读取
 //!
-//! 递归遍历设备树并初始化
+//! 递归遍历设备树并初始化，同时读取 `/chosen` 节点中的内核命令行参数和 `/cpus` 节点中的时钟频率
 
 use super::bus::virtio_mmio::virtio_probe;
+use super::rtc::goldfish_rtc::goldfish_rtc_probe;
+use crate::cmdline;
+use crate::interrupt::timer;
 use crate::memory::addr::VirtualAddress;
 use core::slice;
 use device_tree::{DeviceTree, Node};
@@ -14,8 +17,10 @@ const DEVICE_TREE_MAGIC: u32 = 0xd00d_feed;
 fn walk(node: &Node) {
     // 检查设备的协议支持并初始化
     if let Ok(compatible) = node.prop_str("compatible") {
-        if compatible == "virtio,mmio" {
-            virtio_probe(node);
+        match compatible {
+            "virtio,mmio" => virtio_probe(node),
+            "google,goldfish-rtc" => goldfish_rtc_probe(node),
+            _ => {}
         }
     }
     // 遍历子树
@@ -40,6 +45,17 @@ pub fn init(dtb_va: VirtualAddress) {
         // 拷贝数据，加载并遍历
         let data = unsafe { slice::from_raw_parts(dtb_va.0 as *const u8, size as usize) };
         if let Ok(dt) = DeviceTree::load(data) {
+            if let Some(chosen) = dt.root.children.iter().find(|node| node.name == "chosen") {
+                if let Ok(bootargs) = chosen.prop_str("bootargs") {
+                    cmdline::parse(bootargs);
+                }
+            }
+            // 先读出时钟频率，实时时钟需要据此换算时间
+            if let Some(cpus) = dt.root.children.iter().find(|node| node.name == "cpus") {
+                if let Ok(frequency) = cpus.prop_u32("timebase-frequency") {
+                    timer::set_timebase_frequency(frequency as usize);
+                }
+            }
             walk(&dt.root);
         }
     }
@@ -0,0 +1,31 @@
+//! QEMU virt 中的 goldfish 实时时钟
+//!
+//! 读取 `TIME_LOW` 时设备会锁存当前时间的高 32 位，随后从 `TIME_HIGH` 读出，
+//! 得到自 1970 年起的纳秒数
+
+use crate::memory::addr::{PhysicalAddress, VirtualAddress};
+use core::ptr::read_volatile;
+use device_tree::{util::SliceRead, Node};
+
+/// 时间的低 32 位寄存器的偏移
+const TIME_LOW: usize = 0x00;
+/// 时间的高 32 位寄存器的偏移
+const TIME_HIGH: usize = 0x04;
+
+/// 从设备树节点找到 goldfish 实时时钟，读出墙上时间
+pub fn goldfish_rtc_probe(node: &Node) {
+    // reg 属性中包含了寄存器的位置
+    let reg = match node.prop_raw("reg") {
+        Some(reg) => reg,
+        _ => return,
+    };
+    let pa = PhysicalAddress(reg.as_slice().read_be_u64(0).unwrap() as usize);
+    let va = VirtualAddress::from(pa);
+    let nanos = unsafe {
+        let low = read_volatile((va.0 + TIME_LOW) as *const u32);
+        let high = read_volatile((va.0 + TIME_HIGH) as *const u32);
+        (high as u64) << 32 | low as u64
+    };
+    super::set_realtime(nanos);
+    println!("goldfish rtc at {:#x}", pa.0);
+}
@@ -0,0 +1,22 @@
+//! 实时时钟
+//!
+//! 启动时从实时时钟读出一次墙上时间，之后的墙上时间都由 `time` 寄存器推算，不再访问设备。
+//! 没有找到实时时钟时，墙上时间从 1970 年开始计算
+
+use crate::interrupt::timer::monotonic_nanos;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+pub mod goldfish_rtc;
+
+/// 墙上时间与单调时间之差（纳秒）
+static REALTIME_OFFSET: AtomicU64 = AtomicU64::new(0);
+
+/// 记录实时时钟读出的墙上时间（自 1970 年起的纳秒数）
+pub fn set_realtime(nanos: u64) {
+    REALTIME_OFFSET.store(nanos.saturating_sub(monotonic_nanos()), Ordering::Relaxed);
+}
+
+/// 墙上时间：自 1970-01-01 00:00:00 UTC 起的纳秒数
+pub fn realtime_nanos() -> u64 {
+    REALTIME_OFFSET.load(Ordering::Relaxed) + monotonic_nanos()
+}
@@ -9,7 +9,18 @@
     .section .text.entry
     .globl _start
 # 目前 _start 的功能：将预留的栈空间写入 $sp，然后跳转至 rust_main
+# 启动处理器核从 _start 进入 rust_main，其他处理器核由 SBI 从 _start_secondary 进入 secondary_main
 _start:
+    lui t2, %hi(rust_main)
+    addi t2, t2, %lo(rust_main)
+    j boot
+
+    .globl _start_secondary
+_start_secondary:
+    lui t2, %hi(secondary_main)
+    addi t2, t2, %lo(secondary_main)
+
+boot:
     # 通过线性映射关系计算 boot_page_table 的物理页号
     lui t0, %hi(boot_page_table)
     li t1, 0xffffffff00000000
@@ -22,14 +33,16 @@ _start:
     csrw satp, t0
     sfence.vma
 
-    # 加载栈的虚拟地址
+    # 内核中 tp 始终保存处理器核的编号
+    mv tp, a0
+    # 加载栈的虚拟地址，每个处理器核使用各自的启动栈：boot_stack_top - hart_id * 64K
     lui sp, %hi(boot_stack_top)
     addi sp, sp, %lo(boot_stack_top)
-    # 跳转至 rust_main
+    slli t0, a0, 16
+    sub sp, sp, t0
+    # 跳转至 rust_main 或 secondary_main
     # 这里同时伴随 hart 和 dtb_pa 两个指针的传入（是 OpenSBI 帮我们完成的）
-    lui t0, %hi(rust_main)
-    addi t0, t0, %lo(rust_main)
-    jr t0
+    jr t2
 # 触发访问非法地址中断
     ld x1, (x0)
 # 永不返回
@@ -41,8 +54,8 @@ spin:
     .section .bss.stack
     .global boot_stack
 boot_stack:
-    # 16K 启动栈大小
-    .space 4096 * 16
+    # 每个处理器核 64K 启动栈大小，最多 8 个处理器核（与 smp::MAX_HARTS 一致）
+    .space 4096 * 16 * 8
     .global boot_stack_top
 boot_stack_top:
     # 栈结尾
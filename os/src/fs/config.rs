@@ -2,3 +2,6 @@
 
 /// 块设备的 Cache 块个数
 pub const BLOCK_CACHE_CAPACITY: usize = 0x10;
+
+/// 管道缓冲区的大小
+pub const PIPE_BUFFER_SIZE: usize = 0x1000;
@@ -17,11 +17,18 @@ use spin::Mutex;
 
 mod config;
 mod inode_ext;
+mod open_file;
+mod page_cache;
+mod path;
+pub mod pipe;
 pub mod stdin;
 pub mod stdout;
 
 pub use config::*;
 pub use inode_ext::INodeExt;
+pub use open_file::{FileDescriptor, OpenFile};
+pub use page_cache::{PageCache, PAGE_CACHE};
+pub use path::{absolute_path, lookup, split_parent};
 pub use rcore_fs::{dev::block_cache::BlockCache, vfs::*};
 // pub use stdin::STDIN;
 pub use stdout::STDOUT;
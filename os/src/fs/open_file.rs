@@ -0,0 +1,142 @@
+//! 进程打开的文件 [`OpenFile`]
+
+use super::pipe::PipeWriter;
+use super::*;
+use crate::KResult;
+use alloc::string::String;
+use lib_redos::{O_ACCMODE, O_APPEND, O_RDONLY, O_WRONLY, SEEK_CUR, SEEK_END, SEEK_SET};
+
+/// 进程打开的文件，即文件描述符所指向的对象
+///
+/// fork 之后父子进程中的文件描述符指向同一个 [`OpenFile`]，因此也共享读写位置
+pub struct OpenFile {
+    /// 文件对应的 INode
+    pub inode: Arc<dyn INode>,
+    /// 打开文件时的选项
+    pub flags: usize,
+    /// 当前的读写位置，只对普通文件有意义
+    offset: Mutex<usize>,
+}
+
+/// 进程文件描述符表中的一项
+///
+/// 多个文件描述符可以指向同一个 [`OpenFile`]（dup 或 fork），但 close-on-exec 标志属于文件描述符自身
+#[derive(Clone)]
+pub struct FileDescriptor {
+    /// 指向的打开文件
+    pub file: Arc<OpenFile>,
+    /// exec 时是否关闭
+    pub close_on_exec: bool,
+}
+
+impl OpenFile {
+    /// 以 `flags` 打开 `inode`，读写位置从 0 开始
+    pub fn new(inode: Arc<dyn INode>, flags: usize) -> Arc<Self> {
+        Arc::new(Self {
+            inode,
+            flags,
+            offset: Mutex::new(0),
+        })
+    }
+
+    /// 是否可读
+    pub fn readable(&self) -> bool {
+        self.flags & O_ACCMODE != O_WRONLY
+    }
+
+    /// 是否可写
+    pub fn writable(&self) -> bool {
+        self.flags & O_ACCMODE != O_RDONLY
+    }
+
+    /// 是否为普通文件，普通文件的读写经过页面缓存，并使用读写位置
+    pub fn is_regular(&self) -> bool {
+        matches!(self.inode.metadata(), Ok(metadata) if metadata.type_ == FileType::File)
+    }
+
+    /// 从读写位置读取，并将读写位置后移
+    ///
+    /// 其他文件（如标准输入、管道）暂无数据时当前线程休眠，被要求终止时返回 `Err(FsError::Interrupted)`
+    pub fn read(&self, buffer: &mut [u8]) -> Result<usize> {
+        if self.is_regular() {
+            let mut offset = self.offset.lock();
+            let read = PAGE_CACHE
+                .lock()
+                .read(&self.inode, *offset, buffer)
+                .map_err(|_| FsError::DeviceError)?;
+            *offset += read;
+            Ok(read)
+        } else {
+            self.inode.read_at(0, buffer)
+        }
+    }
+
+    /// 向读写位置写入，并将读写位置后移；设置了 `O_APPEND` 时总是写入文件末尾
+    ///
+    /// 其他文件（如管道）暂时无法写入时当前线程休眠，被要求终止时返回 `Err(FsError::Interrupted)`
+    pub fn write(&self, buffer: &[u8]) -> Result<usize> {
+        if self.is_regular() {
+            let mut offset = self.offset.lock();
+            if self.flags & O_APPEND != 0 {
+                *offset = self.inode.metadata()?.size;
+            }
+            let written = PAGE_CACHE
+                .lock()
+                .write(&self.inode, *offset, buffer)
+                .map_err(|_| FsError::DeviceError)?;
+            *offset += written;
+            Ok(written)
+        } else {
+            self.inode.write_at(0, buffer)
+        }
+    }
+
+    /// 是否为读端已经全部关闭的管道写端
+    pub fn is_broken_pipe(&self) -> bool {
+        self.inode
+            .as_any_ref()
+            .downcast_ref::<PipeWriter>()
+            .map_or(false, PipeWriter::is_broken)
+    }
+
+    /// 读取目录中的下一项的名字，读写位置表示已经读取的项数；读完时返回 `None`
+    pub fn next_entry(&self) -> Option<String> {
+        let mut offset = self.offset.lock();
+        let name = self.inode.get_entry(*offset).ok()?;
+        *offset += 1;
+        Some(name)
+    }
+
+    /// 移动读写位置，返回新的读写位置
+    ///
+    /// `whence` 为 `SEEK_SET`、`SEEK_CUR` 或 `SEEK_END`，新的位置可以超过文件末尾，
+    /// 但不能为负数或超过 `isize::MAX`
+    pub fn seek(&self, delta: isize, whence: usize) -> KResult<usize> {
+        if !self.is_regular() {
+            return Err("file is not seekable");
+        }
+        let mut offset = self.offset.lock();
+        let base = match whence {
+            SEEK_SET => 0,
+            SEEK_CUR => *offset,
+            SEEK_END => self.size()?,
+            _ => return Err("invalid whence"),
+        };
+        let new_offset = match (base as isize).checked_add(delta) {
+            Some(new_offset) if new_offset >= 0 => new_offset,
+            Some(_) => return Err("negative file offset"),
+            None => return Err("file offset overflows"),
+        };
+        *offset = new_offset as usize;
+        Ok(*offset)
+    }
+
+    /// 文件的大小
+    fn size(&self) -> KResult<usize> {
+        Ok(self
+            .inode
+            .metadata()
+            .map_err(|_| "failed to get metadata")?
+            .size)
+    }
+}
@@ -0,0 +1,171 @@
+//! 普通文件的页面缓存 [`PageCache`]
+//!
+//! 文件映射和 read / write 系统调用都通过同一份缓存访问文件内容，因此两者看到的内容总是一致。
+//! 通过共享映射写入的内容只存在于缓存中，直到 munmap 或 msync 时才写回文件。
+
+use super::*;
+use crate::memory::{frame::FRAME_ALLOCATOR, frame_tracker::FrameTracker, PAGE_SIZE};
+use crate::KResult;
+use alloc::collections::BTreeMap;
+use core::cmp::min;
+use core::ops::Range;
+
+/// 缓存页面数的上限（4 MB），超过时释放没有被映射的页面
+///
+/// 被进程映射的页面不能释放，因此缓存的实际大小可能超过这个值
+const MAX_CACHED_PAGES: usize = 1024;
+
+lazy_static! {
+    /// 全局的页面缓存
+    pub static ref PAGE_CACHE: Mutex<PageCache> = Mutex::new(PageCache::default());
+}
+
+/// 页面缓存，以（inode 编号，文件内的页号）索引已读入的物理页面
+#[derive(Default)]
+pub struct PageCache {
+    pages: BTreeMap<(usize, usize), Arc<FrameTracker>>,
+}
+
+impl PageCache {
+    /// 获取文件第 `index` 页的缓存，尚未缓存时从文件中读取，超出文件末尾的部分为全零
+    ///
+    /// 缓存页面数超过 [`MAX_CACHED_PAGES`] 时，先释放没有被映射的页面
+    pub fn get(&mut self, inode: &Arc<dyn INode>, index: usize) -> KResult<Arc<FrameTracker>> {
+        let offset = index
+            .checked_mul(PAGE_SIZE)
+            .ok_or("file offset overflows")?;
+        let key = (inode_id(inode)?, index);
+        if let Some(frame) = self.pages.get(&key) {
+            return Ok(frame.clone());
+        }
+        if self.pages.len() >= MAX_CACHED_PAGES {
+            self.shrink();
+        }
+        let mut frame = FRAME_ALLOCATOR.lock().alloc()?;
+        (*frame).copy_from_slice(&[0u8; PAGE_SIZE]);
+        inode
+            .read_at(offset, &mut *frame)
+            .map_err(|_| "failed to read file")?;
+        let frame = Arc::new(frame);
+        self.pages.insert(key, frame.clone());
+        Ok(frame)
+    }
+
+    /// 经过缓存从文件 `offset` 处读取，返回读取的字节数
+    pub fn read(
+        &mut self,
+        inode: &Arc<dyn INode>,
+        offset: usize,
+        buffer: &mut [u8],
+    ) -> KResult<usize> {
+        let end = min(offset + buffer.len(), file_size(inode)?);
+        let mut position = offset;
+        while position < end {
+            let page_offset = position % PAGE_SIZE;
+            let len = min(PAGE_SIZE - page_offset, end - position);
+            let frame = self.get(inode, position / PAGE_SIZE)?;
+            buffer[position - offset..position - offset + len]
+                .copy_from_slice(&frame[page_offset..page_offset + len]);
+            position += len;
+        }
+        Ok(end.saturating_sub(offset))
+    }
+
+    /// 向文件 `offset` 处写入，同时更新已缓存的页面，返回写入的字节数
+    pub fn write(
+        &mut self,
+        inode: &Arc<dyn INode>,
+        offset: usize,
+        buffer: &[u8],
+    ) -> KResult<usize> {
+        let written = inode
+            .write_at(offset, buffer)
+            .map_err(|_| "failed to write file")?;
+        let id = inode_id(inode)?;
+        let end = offset + written;
+        let mut position = offset;
+        while position < end {
+            let page_offset = position % PAGE_SIZE;
+            let len = min(PAGE_SIZE - page_offset, end - position);
+            if let Some(frame) = self.pages.get(&(id, position / PAGE_SIZE)) {
+                // 页面可能同时被映射到用户进程中，直接修改其内容
+                frame.page_number().deref_kernel()[page_offset..page_offset + len]
+                    .copy_from_slice(&buffer[position - offset..position - offset + len]);
+            }
+            position += len;
+        }
+        Ok(written)
+    }
+
+    /// 将文件中页号位于 `pages` 内的已缓存页面写回文件，不会改变文件的大小
+    pub fn writeback(&mut self, inode: &Arc<dyn INode>, pages: Range<usize>) -> KResult<()> {
+        let id = inode_id(inode)?;
+        let size = file_size(inode)?;
+        for ((_, index), frame) in self.pages.range((id, pages.start)..(id, pages.end)) {
+            let offset = match index.checked_mul(PAGE_SIZE) {
+                Some(offset) if offset < size => offset,
+                _ => break,
+            };
+            let len = min(PAGE_SIZE, size - offset);
+            inode
+                .write_at(offset, &frame[..len])
+                .map_err(|_| "failed to write file")?;
+        }
+        Ok(())
+    }
+
+    /// 改变文件的大小，并将已缓存页面中超出新大小的部分清零，用于 `O_TRUNC`
+    pub fn resize(&mut self, inode: &Arc<dyn INode>, size: usize) -> KResult<()> {
+        inode.resize(size).map_err(|_| "failed to resize file")?;
+        let id = inode_id(inode)?;
+        for ((_, index), frame) in self.pages.range((id, size / PAGE_SIZE)..(id + 1, 0)) {
+            let start = size.saturating_sub(index * PAGE_SIZE);
+            for byte in frame.page_number().deref_kernel()[start..].iter_mut() {
+                *byte = 0;
+            }
+        }
+        Ok(())
+    }
+
+    /// 丢弃文件的所有缓存页面，用于文件被删除之后，避免 inode 编号被重新使用时读到旧的内容
+    pub fn invalidate(&mut self, inode: &Arc<dyn INode>) -> KResult<()> {
+        let id = inode_id(inode)?;
+        let keys: Vec<(usize, usize)> = self
+            .pages
+            .range((id, 0)..(id + 1, 0))
+            .map(|(key, _)| *key)
+            .collect();
+        for key in keys {
+            self.pages.remove(&key);
+        }
+        Ok(())
+    }
+
+    /// 释放没有被任何进程映射的缓存页面
+    ///
+    /// read / write 总是同时写入文件，共享映射的修改在解除映射时写回，所以这些页面都不需要写回
+    pub fn shrink(&mut self) {
+        let unused: Vec<(usize, usize)> = self
+            .pages
+            .iter()
+            .filter(|(_, frame)| Arc::strong_count(frame) == 1)
+            .map(|(key, _)| *key)
+            .collect();
+        for key in unused {
+            self.pages.remove(&key);
+        }
+    }
+}
+
+/// 用 inode 编号区分不同的文件
+fn inode_id(inode: &Arc<dyn INode>) -> KResult<usize> {
+    Ok(inode
+        .metadata()
+        .map_err(|_| "failed to get metadata")?
+        .inode)
+}
+
+/// 文件的大小
+fn file_size(inode: &Arc<dyn INode>) -> KResult<usize> {
+    Ok(inode.metadata().map_err(|_| "failed to get metadata")?.size)
+}
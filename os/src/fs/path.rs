@@ -0,0 +1,63 @@
+//! 路径解析
+
+use super::*;
+use alloc::string::String;
+
+/// 按路径查找文件，以 `/` 开头的绝对路径从根目录开始，否则从目录 `base` 开始
+///
+/// 路径中的 `.` 和 `..` 由文件系统的目录项处理，连续的 `/` 视为一个
+pub fn lookup(base: &Arc<dyn INode>, path: &str) -> Result<Arc<dyn INode>> {
+    let mut inode = if path.starts_with('/') {
+        ROOT_INODE.clone()
+    } else {
+        base.clone()
+    };
+    for name in path.split('/').filter(|name| !name.is_empty()) {
+        inode = inode.find(name)?;
+    }
+    Ok(inode)
+}
+
+/// 将路径分为所在目录和最后一项的名字，如 `/a/b/c` 分为 `/a/b/` 和 `c`
+pub fn split_parent(path: &str) -> (&str, &str) {
+    let path = path.trim_end_matches('/');
+    match path.rfind('/') {
+        Some(index) => (&path[..=index], &path[index + 1..]),
+        None => ("", path),
+    }
+}
+
+/// 求目录 `dir` 的绝对路径
+///
+/// 沿 `..` 逐级向上，在父目录中找到 inode 编号相同的目录项作为名字，直到根目录
+pub fn absolute_path(dir: &Arc<dyn INode>) -> Result<String> {
+    let mut names = Vec::new();
+    let mut current = dir.clone();
+    loop {
+        let id = current.metadata()?.inode;
+        let parent = current.find("..")?;
+        // 根目录的 `..` 指向自身
+        if parent.metadata()?.inode == id {
+            break;
+        }
+        let mut index = 0;
+        let name = loop {
+            let name = parent.get_entry(index)?;
+            if name != "." && name != ".." && parent.find(&name)?.metadata()?.inode == id {
+                break name;
+            }
+            index += 1;
+        };
+        names.push(name);
+        current = parent;
+    }
+    let mut path = String::new();
+    for name in names.iter().rev() {
+        path.push('/');
+        path.push_str(name);
+    }
+    if path.is_empty() {
+        path.push('/');
+    }
+    Ok(path)
+}
@@ -0,0 +1,177 @@
+//! 匿名管道 [`PipeReader`] 和 [`PipeWriter`]
+
+use super::*;
+use alloc::collections::VecDeque;
+
+/// 读端和写端共享的管道
+#[derive(Default)]
+struct Pipe {
+    /// 环形缓冲区，从后插入，从前弹出
+    buffer: Mutex<PipeBuffer>,
+    /// 条件变量用于使等待数据的读者休眠
+    reader_condvar: Condvar,
+    /// 条件变量用于使等待空间的写者休眠
+    writer_condvar: Condvar,
+}
+
+#[derive(Default)]
+struct PipeBuffer {
+    data: VecDeque<u8>,
+    /// 读端是否已经关闭
+    reader_closed: bool,
+    /// 写端是否已经关闭
+    writer_closed: bool,
+}
+
+/// 管道的读端，实现 [`INode`] 接口
+pub struct PipeReader(Arc<Pipe>);
+
+/// 管道的写端，实现 [`INode`] 接口
+pub struct PipeWriter(Arc<Pipe>);
+
+/// 创建一个管道，返回其读端和写端
+pub fn pipe() -> (Arc<PipeReader>, Arc<PipeWriter>) {
+    let pipe = Arc::new(Pipe::default());
+    (
+        Arc::new(PipeReader(pipe.clone())),
+        Arc::new(PipeWriter(pipe)),
+    )
+}
+
+impl INode for PipeReader {
+    /// 读取缓冲区中的数据
+    ///
+    /// 缓冲区为空时，若写端已经关闭则返回 0 表示文件结束，否则将当前线程休眠直到有数据；
+    /// 线程被要求终止时返回 `Interrupted`
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
+        if offset != 0 {
+            // 不支持 offset
+            return Err(FsError::NotSupported);
+        }
+        let wanted = !buf.is_empty();
+        loop {
+            self.0
+                .reader_condvar
+                .wait_while(|| {
+                    let buffer = self.0.buffer.lock();
+                    wanted && buffer.data.is_empty() && !buffer.writer_closed
+                })
+                .map_err(|_| FsError::Interrupted)?;
+            let mut buffer = self.0.buffer.lock();
+            if buffer.data.is_empty() {
+                if buffer.writer_closed || !wanted {
+                    return Ok(0);
+                }
+                // 数据已经被其他线程读走，继续等待
+                continue;
+            }
+            let len = buf.len().min(buffer.data.len());
+            for (byte, b) in buf.iter_mut().zip(buffer.data.drain(..len)) {
+                *byte = b;
+            }
+            drop(buffer);
+            // 腾出了空间，唤起等待写入的线程
+            self.0.writer_condvar.notify_all();
+            return Ok(len);
+        }
+    }
+
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize> {
+        Err(FsError::NotSupported)
+    }
+
+    fn poll(&self) -> Result<PollStatus> {
+        let buffer = self.0.buffer.lock();
+        Ok(PollStatus {
+            read: !buffer.data.is_empty() || buffer.writer_closed,
+            write: false,
+            error: false,
+        })
+    }
+
+    /// This is used to implement dynamics cast.
+    /// Simply return self in the implement of the function.
+    fn as_any_ref(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl INode for PipeWriter {
+    fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> Result<usize> {
+        Err(FsError::NotSupported)
+    }
+
+    /// 将数据写入缓冲区，返回写入的字节数
+    ///
+    /// 缓冲区已满时将当前线程休眠直到有空间，线程被要求终止时返回 `Interrupted`；
+    /// 读端已经关闭时应当先由 [`PipeWriter::is_broken`] 检查
+    fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize> {
+        if offset != 0 {
+            // 不支持 offset
+            return Err(FsError::NotSupported);
+        }
+        loop {
+            self.0
+                .writer_condvar
+                .wait_while(|| {
+                    let buffer = self.0.buffer.lock();
+                    !buf.is_empty()
+                        && !buffer.reader_closed
+                        && buffer.data.len() == PIPE_BUFFER_SIZE
+                })
+                .map_err(|_| FsError::Interrupted)?;
+            let mut buffer = self.0.buffer.lock();
+            if buffer.reader_closed {
+                return Err(FsError::NotSupported);
+            }
+            let len = buf.len().min(PIPE_BUFFER_SIZE - buffer.data.len());
+            if len == 0 && !buf.is_empty() {
+                // 空间已经被其他线程占用，继续等待
+                continue;
+            }
+            buffer.data.extend(buf[..len].iter());
+            drop(buffer);
+            // 有了新的数据，唤起等待读取的线程
+            self.0.reader_condvar.notify_all();
+            return Ok(len);
+        }
+    }
+
+    fn poll(&self) -> Result<PollStatus> {
+        let buffer = self.0.buffer.lock();
+        Ok(PollStatus {
+            read: false,
+            write: buffer.data.len() < PIPE_BUFFER_SIZE,
+            error: buffer.reader_closed,
+        })
+    }
+
+    /// This is used to implement dynamics cast.
+    /// Simply return self in the implement of the function.
+    fn as_any_ref(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl PipeWriter {
+    /// 读端是否已经全部关闭，此时写入没有意义
+    pub fn is_broken(&self) -> bool {
+        self.0.buffer.lock().reader_closed
+    }
+}
+
+/// 读端关闭时唤起等待写入的线程，让它们发现管道已经断开
+impl Drop for PipeReader {
+    fn drop(&mut self) {
+        self.0.buffer.lock().reader_closed = true;
+        self.0.writer_condvar.notify_all();
+    }
+}
+
+/// 写端关闭时唤起等待读取的线程，让它们读到文件结束
+impl Drop for PipeWriter {
+    fn drop(&mut self) {
+        self.0.buffer.lock().writer_closed = true;
+        self.0.reader_condvar.notify_all();
+    }
+}
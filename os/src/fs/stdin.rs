@@ -18,16 +18,22 @@ pub struct Stdin {
 
 impl INode for Stdin {
     /// Read bytes at `offset` into `buf`, return the number of bytes read.
+    ///
+    /// 缓冲区没有数据时，当前线程休眠直到有输入；线程被要求终止时返回 `Interrupted`
     fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
         if offset != 0 {
             // 不支持 offset
-            Err(FsError::NotSupported)
-        } else if self.buffer.lock().len() == 0 {
-            // 缓冲区没有数据，将当前线程休眠
-            self.condvar.wait();
-            Ok(0)
-        } else {
+            return Err(FsError::NotSupported);
+        }
+        loop {
+            self.condvar
+                .wait_while(|| self.buffer.lock().is_empty())
+                .map_err(|_| FsError::Interrupted)?;
             let mut stdin_buffer = self.buffer.lock();
+            // 被唤醒后数据可能已经被其他线程读走
+            if stdin_buffer.is_empty() {
+                continue;
+            }
             for (i, byte) in buf.iter_mut().enumerate() {
                 if let Some(b) = stdin_buffer.pop_front() {
                     *byte = b;
@@ -35,7 +41,7 @@ impl INode for Stdin {
                     return Ok(i);
                 }
             }
-            Ok(buf.len())
+            return Ok(buf.len());
         }
     }
 
@@ -2,106 +2,201 @@ use crate::fs::stdin::STDIN;
 use crate::interrupt::context::Context;
 use crate::interrupt::timer;
 use crate::kernel::syscall_handler;
-use crate::memory::addr::PhysicalAddress;
+use crate::memory::addr::{PhysicalAddress, VirtualAddress};
+use crate::memory::mapping::is_guard_page;
 use crate::process::alarm::ALARM;
-use crate::process::thread::ThreadState::Dead;
+use crate::process::process::foreground_process;
+use crate::process::processor::{exit_current_thread, schedule};
+use crate::process::signal::{force_signal, handle_signals, send_signal};
 use crate::process::PROCESSOR;
 use crate::sbi::console_getchar;
+use crate::smp::hart_id;
+use lib_redos::{SIGBUS, SIGILL, SIGINT, SIGSEGV};
 use riscv::register::scause::{Exception, Interrupt, Scause, Trap};
+use riscv::register::sstatus::SPP;
 use riscv::register::{sie, stvec};
 
 global_asm!(include_str!("./interrupt.asm"));
 
+/// 控制台中 Ctrl-C 对应的字符
+const CTRL_C: usize = 0x03;
+
 /// 初始化中断处理
 ///
 /// 把中断入口 `__interrupt` 写入 `stvec` 中，并且开启中断使能
 pub fn init() {
+    init_secondary();
     unsafe {
-        extern "C" {
-            /// `interrupt.asm` 中的中断入口
-            fn __interrupt();
-        }
-        // 使用 Direct 模式，将中断入口设置为 `__interrupt`
-        stvec::write(__interrupt as usize, stvec::TrapMode::Direct);
-
         // 开启外部中断使能
         sie::set_sext();
 
+        // 当前处理器核 S 态对应的 PLIC 上下文
+        let plic_context = 2 * hart_id() + 1;
         // 在 OpenSBI 中开启外部中断
-        *PhysicalAddress(0x0c00_2080).deref_kernel() = 1u32 << 10;
+        *PhysicalAddress(0x0c00_2000 + 0x80 * plic_context).deref_kernel() = 1u32 << 10;
         // 在 OpenSBI 中开启串口
         *PhysicalAddress(0x1000_0004).deref_kernel() = 0x0bu8;
         *PhysicalAddress(0x1000_0001).deref_kernel() = 0x01u8;
         // 其他一些外部中断相关魔数
         *PhysicalAddress(0x0C00_0028).deref_kernel() = 0x07u32;
-        *PhysicalAddress(0x0C20_1000).deref_kernel() = 0u32;
+        *PhysicalAddress(0x0C20_0000 + 0x1000 * plic_context).deref_kernel() = 0u32;
+    }
+}
+
+/// 初始化其他处理器核的中断处理
+///
+/// 写入中断入口，只开启处理器间中断（软件中断）
+pub fn init_secondary() {
+    unsafe {
+        extern "C" {
+            /// `interrupt.asm` 中的中断入口
+            fn __interrupt();
+        }
+        // 使用 Direct 模式，将中断入口设置为 `__interrupt`
+        stvec::write(__interrupt as usize, stvec::TrapMode::Direct);
+
+        // 开启软件中断使能，用于处理器间中断
+        sie::set_ssoft();
     }
 }
 
 /// 中断的处理入口
 ///
-/// `interrupt.asm` 首先保存寄存器至 Context，其作为参数和 scause 以及 stval 一并传入此函数
-/// 具体的中断类型需要根据 scause 来推断，然后分别处理
+/// `interrupt.asm` 首先保存寄存器至当前线程的内核栈顶的 Context，其作为参数和 scause 以及 stval 一并传入此函数
+/// 具体的中断类型需要根据 scause 来推断，然后分别处理。返回之前，会先处理当前线程收到的信号，
+/// 被要求终止的线程在这里结束
 #[no_mangle]
 pub fn handle_interrupt(context: &mut Context, scause: Scause, stval: usize) -> *mut Context {
-    // 首先检查线程是否已经结束（内核线程会自己设置标记来结束自己）
-    {
-        let mut processor = PROCESSOR.lock();
-        let current_thread = processor.current_thread();
-        if current_thread.as_ref().inner().state == Dead {
-            println!("thread {} exit", current_thread.id);
-            processor.kill_current_thread();
-            return processor.prepare_next_thread();
-        }
-    }
-    // 根据中断类型来处理，返回的 Context 必须位于放在内核栈顶
+    // 根据中断类型来处理，线程可能在处理过程中让出处理器核，再次被调度时从原处继续
     match scause.cause() {
         // 断点中断（ebreak）
         Trap::Exception(Exception::Breakpoint) => breakpoint(context),
         // 系统调用
         Trap::Exception(Exception::UserEnvCall) => syscall_handler(context),
         // 时钟中断
-        Trap::Interrupt(Interrupt::SupervisorTimer) => supervisor_timer(context),
+        Trap::Interrupt(Interrupt::SupervisorTimer) => supervisor_timer(),
         // 外部中断（键盘输入）
-        Trap::Interrupt(Interrupt::SupervisorExternal) => supervisor_external(context),
+        Trap::Interrupt(Interrupt::SupervisorExternal) => supervisor_external(),
+        // 处理器间中断
+        Trap::Interrupt(Interrupt::SupervisorSoft) => supervisor_soft(),
+        // 缺页异常
+        Trap::Exception(Exception::LoadPageFault)
+        | Trap::Exception(Exception::StorePageFault)
+        | Trap::Exception(Exception::InstructionPageFault) => page_fault(context, scause, stval),
         // 其他情况，无法处理
-        _ => fault("unimplemented interrupt type", scause, stval),
+        _ => fault(context, "unimplemented interrupt type", scause, stval),
+    }
+    // 空闲的处理器核在中断处理中唤醒了线程时立即切换，不等待时钟中断
+    let leave_idle = PROCESSOR.lock().should_leave_idle();
+    if leave_idle {
+        schedule();
     }
+    handle_signals(context);
+    // 线程被要求终止时（内核线程会自己设置标记来结束自己），不再返回
+    let current_thread = PROCESSOR.lock().current_thread();
+    let killed = current_thread.inner().killed;
+    if killed {
+        debug_println!("thread {} exit", current_thread.id);
+        drop(current_thread);
+        exit_current_thread();
+    }
+    context
 }
 
 /// 处理 ebreak 断点
 ///
 /// 继续执行，其中 `sepc` 增加 2 字节，以跳过当前这条 `ebreak` 指令
-fn breakpoint(context: &mut Context) -> *mut Context {
+fn breakpoint(context: &mut Context) {
     println!("Breakpoint at 0x{:x}", context.sepc);
     context.sepc += 2;
-    context
 }
 
 /// 处理时钟中断
-fn supervisor_timer(context: &mut Context) -> *mut Context {
-    if timer::tick_for_1sec() {
-        ALARM.lock().alarm();
-    }
-    PROCESSOR.lock().park_current_thread(context);
-    let cxt = PROCESSOR.lock().prepare_next_thread();
-    cxt
+///
+/// 唤醒休眠到期的线程，按照最近的休眠期限预约下一次时钟中断，然后重新调度
+fn supervisor_timer() {
+    let deadline = ALARM.lock().alarm();
+    timer::set_next_timeout(deadline);
+    schedule();
+}
+
+/// 处理处理器间中断：其他处理器核加入了待执行的线程，或者终止了当前线程，重新调度
+fn supervisor_soft() {
+    // 清除 SSIP，否则返回后会再次进入中断
+    unsafe { llvm_asm!("csrci sip, 1 << 1" :::: "volatile") };
+    schedule();
 }
 
 /// 处理外部中断，只实现了键盘输入
-fn supervisor_external(context: &mut Context) -> *mut Context {
+///
+/// Ctrl-C 不会进入输入缓冲区，而是向前台进程发送 `SIGINT`
+fn supervisor_external() {
     let mut c = console_getchar();
-    if c <= 255 {
+    if c == CTRL_C {
+        if let Some(process) = foreground_process() {
+            send_signal(&process, SIGINT);
+        }
+    } else if c <= 255 {
         if c == '\r' as usize {
             c = '\n' as usize;
         }
         STDIN.push(c as u8);
     }
-    context
 }
 
-/// 出现未能解决的异常，终止当前线程
-fn fault(msg: &str, scause: Scause, stval: usize) -> *mut Context {
+/// 处理缺页异常
+///
+/// 交给当前进程的 [`MemorySet`](crate::memory::mapping::MemorySet) 处理（例如写时复制），
+/// 无法处理时终止线程
+fn page_fault(context: &mut Context, scause: Scause, stval: usize) {
+    // 内核栈溢出时可能持有任意的锁，不能再去访问进程
+    if matches!(context.sstatus.spp(), SPP::Supervisor) && is_guard_page(stval) {
+        panic!("kernel stack overflow at {:#x}", stval);
+    }
+    let is_write = matches!(scause.cause(), Trap::Exception(Exception::StorePageFault));
+    let current_thread = PROCESSOR.lock().current_thread();
+    let result = current_thread
+        .process
+        .inner()
+        .memory_set
+        .handle_page_fault(VirtualAddress(stval), is_write);
+    if let Err(msg) = result {
+        fault(context, msg, scause, stval);
+    }
+}
+
+/// 出现未能解决的异常
+///
+/// 用户态的异常转换为信号交给当前线程：非法指令为 `SIGILL`，地址未对齐为 `SIGBUS`，其他为 `SIGSEGV`，
+/// 信号在返回用户态之前处理。内核态的异常终止当前线程
+fn fault(context: &mut Context, msg: &str, scause: Scause, stval: usize) {
+    if matches!(context.sstatus.spp(), SPP::User) {
+        let signal = match scause.cause() {
+            Trap::Exception(Exception::IllegalInstruction) => SIGILL,
+            Trap::Exception(Exception::InstructionMisaligned)
+            | Trap::Exception(Exception::LoadMisaligned)
+            | Trap::Exception(Exception::StoreMisaligned) => SIGBUS,
+            _ => SIGSEGV,
+        };
+        let thread = PROCESSOR.lock().current_thread();
+        debug_println!(
+            "thread {} {} at {:#x} ({:?}, stval: {:#x}), signal {}",
+            thread.id,
+            msg,
+            context.sepc,
+            scause.cause(),
+            stval,
+            signal
+        );
+        // 非法指令的 stval 是指令本身，传给处理函数的是指令地址
+        let address = if signal == SIGILL {
+            context.sepc
+        } else {
+            stval
+        };
+        force_signal(&thread, signal, address);
+        return;
+    }
     println!(
         "{:#x?} terminated: {}",
         PROCESSOR.lock().current_thread(),
@@ -109,7 +204,9 @@ fn fault(msg: &str, scause: Scause, stval: usize) -> *mut Context {
     );
     println!("cause: {:?}, stval: {:x}", scause.cause(), stval);
 
-    PROCESSOR.lock().kill_current_thread();
-    // 跳转到 PROCESSOR 调度的下一个线程
-    PROCESSOR.lock().prepare_next_thread()
+    let thread = PROCESSOR.lock().current_thread();
+    thread.process.exit_thread(thread.id, -1);
+    drop(thread);
+    // 切换到 PROCESSOR 调度的下一个线程
+    exit_current_thread();
 }
@@ -4,6 +4,11 @@
 .set    REG_SIZE, 8
 # Context 的大小
 .set    CONTEXT_SIZE, 34
+# 内核栈区域的槽位中，栈位于高一半，低一半为保护区（见 memory/mapping/stack_region.rs）
+# 地址的这一位为 0 说明位于保护区
+.set    STACK_SLOT_BIT, 15
+# 内核栈溢出时中断处理使用的栈的大小
+.set    OVERFLOW_STACK_SIZE, 4096 * 4
 
 # 宏：将寄存器存到栈上
 .macro SAVE reg, offset
@@ -30,12 +35,30 @@
 # 进入中断
 # 保存 Context 并且进入 Rust 中的中断处理函数 interrupt::handler::handle_interrupt()
 __interrupt:
-    # 因为线程当前的栈不一定可用，必须切换到内核栈来保存 Context 并进行中断流程
-    # 因此，我们使用 sscratch 寄存器保存内核栈地址
+    # 因为用户栈不一定可用，从用户态进入中断时必须切换到内核栈来保存 Context 并进行中断流程
+    # 因此，在用户态执行时 sscratch 保存当前线程的内核栈顶地址，在内核态执行时为 0
     # 思考：sscratch 的值最初是在什么地方写入的？
 
-    # 交换 sp 和 sscratch（切换到内核栈）
+    # 交换 sp 和 sscratch（从用户态进入时切换到内核栈）
     csrrw   sp, sscratch, sp
+    bnez    sp, .save_context
+    # 从内核态进入，已经在内核栈上，换回原来的 sp
+    csrr    sp, sscratch
+    # 不在内核栈区域（地址的高 34 位不全为 1）时直接使用
+    srai    sp, sp, 30
+    addi    sp, sp, 1
+    bnez    sp, .kernel_stack
+    # 放入 Context 之后会进入保护区，说明内核栈溢出，改用溢出时的栈
+    csrr    sp, sscratch
+    addi    sp, sp, -CONTEXT_SIZE * REG_SIZE
+    srli    sp, sp, STACK_SLOT_BIT
+    andi    sp, sp, 1
+    bnez    sp, .kernel_stack
+    la      sp, overflow_stack_top
+    j       .save_context
+.kernel_stack:
+    csrr    sp, sscratch
+.save_context:
     # 在内核栈开辟 Context 的空间
     addi    sp, sp, -CONTEXT_SIZE * REG_SIZE
 
@@ -56,6 +79,14 @@ __interrupt:
     csrr    t1, sepc
     SAVE    t0, 32
     SAVE    t1, 33
+
+    # 用户程序可能修改了 tp，从用户态进入时从内核栈顶取出处理器核编号写入 tp
+    andi    t2, t0, 1 << 8
+    bnez    t2, 1f
+    ld      tp, CONTEXT_SIZE * REG_SIZE(sp)
+1:
+    # 在内核态执行期间 sscratch 为 0
+    csrw    sscratch, zero
     # 调用 handle_interrupt，传入参数
     # context: &mut Context
     mv      a0, sp
@@ -79,9 +110,13 @@ __restore:
     LOAD    t1, 33
     csrw    sstatus, t0
     csrw    sepc, t1
-    # 将内核栈地址写入 sscratch
-    addi    t0, sp, CONTEXT_SIZE * REG_SIZE
-    csrw    sscratch, t0
+    # 返回用户态时将内核栈地址写入 sscratch（其上方保存着处理器核编号），返回内核态时写入 0
+    andi    t0, t0, 1 << 8
+    addi    t1, sp, CONTEXT_SIZE * REG_SIZE
+    beqz    t0, 1f
+    mv      t1, zero
+1:
+    csrw    sscratch, t1
 
     # 恢复通用寄存器
     LOAD    x1, 1
@@ -94,4 +129,11 @@ __restore:
 
     # 恢复 sp（又名 x2）这里最后恢复是为了上面可以正常使用 LOAD 宏
     LOAD    x2, 2
-    sret
\ No newline at end of file
+    sret
+
+    .section .bss
+    .align 12
+# 内核栈溢出时中断处理使用的栈，所有处理器核共用，中断处理随即 panic
+overflow_stack:
+    .space  OVERFLOW_STACK_SIZE
+overflow_stack_top:
@@ -4,7 +4,7 @@
 
 pub mod context;
 mod handler;
-mod timer;
+pub mod timer;
 
 /// 初始化中断相关的子模块
 ///
@@ -15,3 +15,12 @@ pub fn init() {
     timer::init();
     println!("mod interrupt initialized");
 }
+
+/// 初始化其他处理器核的中断处理，外部中断只由启动处理器核处理
+///
+/// - [`handler::init_secondary`]
+/// - [`timer::init`]
+pub fn init_secondary() {
+    handler::init_secondary();
+    timer::init();
+}
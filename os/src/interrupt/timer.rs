@@ -1,14 +1,28 @@
 //! 预约和处理时钟中断
+//!
+//! 时钟中断既用于抢占线程，也用于唤醒休眠到期的线程：
+//! 每次预约的时间取固定间隔和最近一个休眠期限中较早的一个。
+//!
+//! 处理器核只有空闲线程可以执行时不需要抢占，此时停止周期性的时钟中断（tickless），
+//! 只预约最近的休眠期限，其余时间由外部中断或处理器间中断唤醒；有线程可以执行时恢复周期性的时钟中断
 
 use crate::sbi::set_timer;
+use crate::smp::hart_id;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use riscv::register::{sie, time};
 
-/// 触发时钟中断计数
-pub static mut TICKS: usize = 0;
+/// `time` 寄存器的频率，从设备树的 `cpus` 节点读出，默认为 QEMU virt 的 10 MHz
+static TIMEBASE_FREQUENCY: AtomicUsize = AtomicUsize::new(10_000_000);
 
-/// 时钟中断的间隔，单位是 CPU 指令
+/// 每秒的纳秒数
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// 时钟中断的间隔，单位是 `time` 寄存器的计数
 static INTERVAL: usize = 100000;
 
+/// 停止了周期性时钟中断的处理器核，每一位对应一个处理器核
+static TICK_STOPPED: AtomicUsize = AtomicUsize::new(0);
+
 /// 初始化时钟中断
 ///
 /// 开启时钟中断使能，并且预约第一次时钟中断
@@ -18,29 +32,58 @@ pub fn init() {
         sie::set_stimer();
     }
     // 设置下一次时钟中断
-    set_next_timeout();
+    set_next_timeout(None);
 }
 
-/// 设置下一次时钟中断
+/// 设置当前处理器核的下一次时钟中断
 ///
-/// 获取当前时间，加上中断间隔，通过 SBI 调用预约下一次中断
-fn set_next_timeout() {
-    set_timer(time::read() + INTERVAL);
+/// 获取当前时间，加上中断间隔；`deadline` 更早时预约在 `deadline`
+pub fn set_next_timeout(deadline: Option<usize>) {
+    let next = time::read() + INTERVAL;
+    set_timer(deadline.map_or(next, |deadline| deadline.min(next)));
 }
 
-/// 每一次时钟中断时调用
-///
-/// 设置下一次时钟中断，同时计数 +1
-/// 若经历了约1秒，返回true；否则返回false
-pub fn tick_for_1sec() -> bool {
-    set_next_timeout();
-    unsafe {
-        TICKS += 1;
-        if TICKS == 100 {
-            TICKS = 0;
-            true
-        } else {
-            false
-        }
-    }
+/// 停止当前处理器核的周期性时钟中断，只在 `deadline` 预约一次；没有期限时不再预约
+pub fn stop_tick(deadline: Option<usize>) {
+    TICK_STOPPED.fetch_or(1 << hart_id(), Ordering::SeqCst);
+    // 预约在最远的时刻，相当于取消已经预约的时钟中断
+    set_timer(deadline.unwrap_or(usize::MAX));
+}
+
+/// 当前处理器核是否停止了周期性时钟中断
+pub fn tick_stopped() -> bool {
+    TICK_STOPPED.load(Ordering::SeqCst) & (1 << hart_id()) != 0
+}
+
+/// 恢复当前处理器核的周期性时钟中断，`deadline` 为最近的休眠期限
+pub fn start_tick(deadline: Option<usize>) {
+    TICK_STOPPED.fetch_and(!(1 << hart_id()), Ordering::SeqCst);
+    set_next_timeout(deadline);
+}
+
+/// `time` 寄存器的频率
+pub fn timebase_frequency() -> usize {
+    TIMEBASE_FREQUENCY.load(Ordering::Relaxed)
+}
+
+/// 设置 `time` 寄存器的频率，由设备树在启动时调用
+pub fn set_timebase_frequency(frequency: usize) {
+    TIMEBASE_FREQUENCY.store(frequency, Ordering::Relaxed);
+}
+
+/// 将纳秒数换算为 `time` 寄存器的计数，向上取整，保证不会提前唤醒
+pub fn nanos_to_ticks(nanos: usize) -> usize {
+    let frequency = timebase_frequency() as u128;
+    let ticks = (nanos as u128 * frequency + NANOS_PER_SEC - 1) / NANOS_PER_SEC;
+    ticks as usize
+}
+
+/// 将 `time` 寄存器的计数换算为纳秒数
+pub fn ticks_to_nanos(ticks: usize) -> u64 {
+    (ticks as u128 * NANOS_PER_SEC / timebase_frequency() as u128) as u64
+}
+
+/// 单调时间：自启动起的纳秒数
+pub fn monotonic_nanos() -> u64 {
+    ticks_to_nanos(time::read())
 }
@@ -1,45 +1,514 @@
 //! 文件相关的内核功能
 
 use super::*;
+use crate::fs::pipe::pipe;
+use crate::fs::{
+    absolute_path, lookup, split_parent, FileType, FsError, Metadata, OpenFile, PAGE_CACHE,
+};
+use crate::memory::PAGE_SIZE;
+use alloc::vec;
+use core::mem::size_of;
 use core::slice::from_raw_parts_mut;
+use lib_redos::{
+    Dirent, Stat, DIRENT_NAME_LENGTH, EBADF, EEXIST, EFAULT, EINTR, EINVAL, EIO, EISDIR, EMFILE,
+    ENOENT, ENOTDIR, ENOTEMPTY, EPIPE, ERANGE, ESPIPE, O_CLOEXEC, O_CREAT, O_EXCL, O_RDONLY,
+    O_TRUNC, O_WRONLY, S_IFBLK, S_IFCHR, S_IFDIR, S_IFIFO, S_IFLNK, S_IFREG, S_IFSOCK,
+};
+
+/// 读写可能休眠的文件（如标准输入、管道）时，每次经过的内核缓冲区的大小
+///
+/// 休眠期间同一进程的其他线程可能 fork、munmap 或 mprotect 用户缓冲区，
+/// 因此休眠时只访问内核缓冲区，醒来之后重新检查用户缓冲区再复制
+const BOUNCE_BUFFER_SIZE: usize = PAGE_SIZE;
 
 /// 从指定的文件中读取字符
 ///
-/// 如果缓冲区暂无数据，返回 0；出现错误返回 -1
+/// 普通文件从读写位置读取，读到文件末尾（或管道写端全部关闭）时返回 0。
+/// 其他文件（如标准输入、管道）暂无数据时，当前线程休眠直到有数据；线程被要求终止时返回 `-EINTR`。
+/// 其他文件每次最多读取 [`BOUNCE_BUFFER_SIZE`] 字节
 pub(super) fn sys_read(fd: usize, buffer: *mut u8, size: usize) -> SyscallResult {
-    // 从进程中获取 inode
+    // 从进程中获取文件
     let process = PROCESSOR.lock().current_thread().process.clone();
-    if let Some(inode) = process.inner().descriptors.get(fd) {
-        // 从系统调用传入的参数生成缓冲区
+    if process
+        .prepare_user_access(buffer as usize, size, true)
+        .is_err()
+    {
+        return SyscallResult::Proceed(-EFAULT);
+    }
+    let file = match process.get_descriptor(fd) {
+        Some(file) if file.readable() => file,
+        _ => return SyscallResult::Proceed(-EBADF),
+    };
+    if file.is_regular() {
+        // 页面缓存的读取不会休眠，直接读入用户缓冲区
         let buffer = unsafe { from_raw_parts_mut(buffer, size) };
-        // 尝试读取
-        if let Ok(ret) = inode.read_at(0, buffer) {
-            let ret = ret as isize;
-            if ret > 0 {
-                return SyscallResult::Proceed(ret);
-            }
-            if ret == 0 {
-                return SyscallResult::Park(ret);
-            }
-        }
+        return match file.read(buffer) {
+            Ok(ret) => SyscallResult::Proceed(ret as isize),
+            Err(e) => SyscallResult::Proceed(-fs_error(e)),
+        };
+    }
+    let mut bounce = vec![0u8; size.min(BOUNCE_BUFFER_SIZE)];
+    let read = match file.read(&mut bounce) {
+        Ok(read) => read,
+        Err(e) => return SyscallResult::Proceed(-fs_error(e)),
+    };
+    match copy_to_user(&process, buffer, &bounce[..read]) {
+        Some(()) => SyscallResult::Proceed(read as isize),
+        None => SyscallResult::Proceed(-EFAULT),
     }
-    SyscallResult::Proceed(-1)
 }
 
 /// 将字符写入指定的文件
+///
+/// 管道缓冲区已满时，当前线程休眠直到有空间；线程被要求终止时返回 `-EINTR`；
+/// 管道的读端全部关闭时返回 `-EPIPE`
 pub(super) fn sys_write(fd: usize, buffer: *mut u8, size: usize) -> SyscallResult {
-    // 从进程中获取 inode
+    // 从进程中获取文件
     let process = PROCESSOR.lock().current_thread().process.clone();
-    if let Some(inode) = process.inner().descriptors.get(fd) {
-        // 从系统调用传入的参数生成缓冲区
+    if process
+        .prepare_user_access(buffer as usize, size, false)
+        .is_err()
+    {
+        return SyscallResult::Proceed(-EFAULT);
+    }
+    let file = match process.get_descriptor(fd) {
+        Some(file) if file.writable() => file,
+        _ => return SyscallResult::Proceed(-EBADF),
+    };
+    if file.is_broken_pipe() {
+        return SyscallResult::Proceed(-EPIPE);
+    }
+    if file.is_regular() {
+        // 页面缓存的写入不会休眠，直接从用户缓冲区写入
         let buffer = unsafe { from_raw_parts_mut(buffer, size) };
-        // 尝试写入
-        if let Ok(ret) = inode.write_at(0, buffer) {
-            let ret = ret as isize;
-            if ret >= 0 {
-                return SyscallResult::Proceed(ret);
+        return match file.write(buffer) {
+            Ok(ret) => SyscallResult::Proceed(ret as isize),
+            Err(e) => SyscallResult::Proceed(-fs_error(e)),
+        };
+    }
+    // 每次复制一段到内核缓冲区再写入，已经写入一部分之后出错时返回写入的字节数
+    let mut written = 0;
+    while written < size {
+        let chunk = (size - written).min(BOUNCE_BUFFER_SIZE);
+        let bounce = match copy_from_user(&process, unsafe { buffer.add(written) }, chunk) {
+            Some(bounce) => bounce,
+            None if written > 0 => break,
+            None => return SyscallResult::Proceed(-EFAULT),
+        };
+        match file.write(&bounce) {
+            Ok(ret) => {
+                written += ret;
+                if ret < chunk {
+                    break;
+                }
+            }
+            Err(_) if written > 0 => break,
+            // 休眠期间读端可能已经全部关闭
+            Err(_) if file.is_broken_pipe() => return SyscallResult::Proceed(-EPIPE),
+            Err(e) => return SyscallResult::Proceed(-fs_error(e)),
+        }
+    }
+    SyscallResult::Proceed(written as isize)
+}
+
+/// 打开路径为 `path` 的文件，返回新的文件描述符
+///
+/// 设置了 `O_CREAT` 时，文件不存在则创建一个普通文件；设置了 `O_CLOEXEC` 时，exec 时关闭该文件描述符
+pub(super) fn sys_open(path: *const u8, flags: usize) -> SyscallResult {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    let path = match read_user_string(&process, path) {
+        Some(path) => path,
+        None => return SyscallResult::Proceed(-EFAULT),
+    };
+    let cwd = process.cwd();
+    let inode = match lookup(&cwd, &path) {
+        Ok(_) if flags & O_CREAT != 0 && flags & O_EXCL != 0 => {
+            return SyscallResult::Proceed(-EEXIST)
+        }
+        Ok(inode) => inode,
+        Err(FsError::EntryNotFound) if flags & O_CREAT != 0 => {
+            // 以 `/` 结尾的路径只能是目录，不能创建为普通文件
+            if path.ends_with('/') {
+                return SyscallResult::Proceed(-EISDIR);
+            }
+            let (parent, name) = split_parent(&path);
+            if !is_valid_name(name) {
+                return SyscallResult::Proceed(-EINVAL);
+            }
+            let created =
+                lookup(&cwd, parent).and_then(|dir| dir.create(name, FileType::File, 0o666));
+            match created {
+                Ok(inode) => inode,
+                Err(e) => return SyscallResult::Proceed(-fs_error(e)),
+            }
+        }
+        Err(e) => return SyscallResult::Proceed(-fs_error(e)),
+    };
+    let file = OpenFile::new(inode, flags);
+    let is_dir = matches!(file.inode.metadata(), Ok(metadata) if metadata.type_ == FileType::Dir);
+    if is_dir && file.writable() {
+        return SyscallResult::Proceed(-EISDIR);
+    }
+    if flags & O_TRUNC != 0
+        && file.writable()
+        && file.is_regular()
+        && PAGE_CACHE.lock().resize(&file.inode, 0).is_err()
+    {
+        return SyscallResult::Proceed(-EIO);
+    }
+    match process.add_descriptor(file, flags & O_CLOEXEC != 0) {
+        Some(fd) => SyscallResult::Proceed(fd as isize),
+        None => SyscallResult::Proceed(-EMFILE),
+    }
+}
+
+/// 创建一个管道，将读端和写端的文件描述符依次写入 `fds`
+pub(super) fn sys_pipe(fds: *mut usize) -> SyscallResult {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    if process
+        .prepare_user_access(fds as usize, 2 * size_of::<usize>(), true)
+        .is_err()
+    {
+        return SyscallResult::Proceed(-EFAULT);
+    }
+    let (reader, writer) = pipe();
+    let reader_fd = match process.add_descriptor(OpenFile::new(reader, O_RDONLY), false) {
+        Some(fd) => fd,
+        None => return SyscallResult::Proceed(-EMFILE),
+    };
+    let writer_fd = match process.add_descriptor(OpenFile::new(writer, O_WRONLY), false) {
+        Some(fd) => fd,
+        None => {
+            process.remove_descriptor(reader_fd);
+            return SyscallResult::Proceed(-EMFILE);
+        }
+    };
+    unsafe {
+        *fds = reader_fd;
+        *fds.add(1) = writer_fd;
+    }
+    SyscallResult::Proceed(0)
+}
+
+/// 复制文件描述符，新的文件描述符为最小的未被占用的编号，与原来的共享读写位置
+pub(super) fn sys_dup(fd: usize) -> SyscallResult {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    let file = match process.get_descriptor(fd) {
+        Some(file) => file,
+        None => return SyscallResult::Proceed(-EBADF),
+    };
+    match process.add_descriptor(file, false) {
+        Some(fd) => SyscallResult::Proceed(fd as isize),
+        None => SyscallResult::Proceed(-EMFILE),
+    }
+}
+
+/// 令文件描述符 `new_fd` 指向 `old_fd` 所指向的文件，`new_fd` 原本打开的文件会被关闭
+///
+/// `flags` 可以为 `O_CLOEXEC`，用于 I/O 重定向
+pub(super) fn sys_dup2(old_fd: usize, new_fd: usize, flags: usize) -> SyscallResult {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    if old_fd == new_fd {
+        return match process.get_descriptor(old_fd) {
+            Some(_) => SyscallResult::Proceed(new_fd as isize),
+            None => SyscallResult::Proceed(-EBADF),
+        };
+    }
+    match process.duplicate_descriptor(old_fd, new_fd, flags & O_CLOEXEC != 0) {
+        Ok(_) => SyscallResult::Proceed(new_fd as isize),
+        Err(_) => SyscallResult::Proceed(-EBADF),
+    }
+}
+
+/// 关闭文件描述符
+pub(super) fn sys_close(fd: usize) -> SyscallResult {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    match process.remove_descriptor(fd) {
+        Some(_) => SyscallResult::Proceed(0),
+        None => SyscallResult::Proceed(-EBADF),
+    }
+}
+
+/// 移动文件的读写位置，返回新的读写位置
+pub(super) fn sys_lseek(fd: usize, offset: isize, whence: usize) -> SyscallResult {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    let file = match process.get_descriptor(fd) {
+        Some(file) => file,
+        None => return SyscallResult::Proceed(-EBADF),
+    };
+    if !file.is_regular() {
+        return SyscallResult::Proceed(-ESPIPE);
+    }
+    match file.seek(offset, whence) {
+        Ok(offset) => SyscallResult::Proceed(offset as isize),
+        Err(_) => SyscallResult::Proceed(-EINVAL),
+    }
+}
+
+/// 创建目录
+pub(super) fn sys_mkdir(path: *const u8) -> SyscallResult {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    let path = match read_user_string(&process, path) {
+        Some(path) => path,
+        None => return SyscallResult::Proceed(-EFAULT),
+    };
+    let (parent, name) = split_parent(&path);
+    // 根目录、`.` 和 `..` 总是存在
+    if !is_valid_name(name) {
+        return SyscallResult::Proceed(-EEXIST);
+    }
+    match lookup(&process.cwd(), parent).and_then(|dir| dir.create(name, FileType::Dir, 0o755)) {
+        Ok(_) => SyscallResult::Proceed(0),
+        Err(e) => SyscallResult::Proceed(-fs_error(e)),
+    }
+}
+
+/// 删除文件或空目录
+///
+/// 文件的链接数变为 0 时丢弃其页面缓存；已经打开的文件描述符仍然可以读写
+pub(super) fn sys_unlink(path: *const u8) -> SyscallResult {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    let path = match read_user_string(&process, path) {
+        Some(path) => path,
+        None => return SyscallResult::Proceed(-EFAULT),
+    };
+    let (parent, name) = split_parent(&path);
+    if !is_valid_name(name) {
+        return SyscallResult::Proceed(-EINVAL);
+    }
+    let result = lookup(&process.cwd(), parent).and_then(|dir| {
+        let inode = dir.find(name)?;
+        dir.unlink(name)?;
+        Ok(inode)
+    });
+    match result {
+        Ok(inode) => {
+            if matches!(inode.metadata(), Ok(metadata) if metadata.nlinks == 0) {
+                PAGE_CACHE.lock().invalidate(&inode).ok();
+            }
+            SyscallResult::Proceed(0)
+        }
+        Err(e) => SyscallResult::Proceed(-fs_error(e)),
+    }
+}
+
+/// 移动或重命名文件，目标已存在时将其替换，但不能替换目录
+pub(super) fn sys_rename(old_path: *const u8, new_path: *const u8) -> SyscallResult {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    let (old_path, new_path) = match (
+        read_user_string(&process, old_path),
+        read_user_string(&process, new_path),
+    ) {
+        (Some(old_path), Some(new_path)) => (old_path, new_path),
+        _ => return SyscallResult::Proceed(-EFAULT),
+    };
+    let (old_parent, old_name) = split_parent(&old_path);
+    let (new_parent, new_name) = split_parent(&new_path);
+    if !is_valid_name(old_name) || !is_valid_name(new_name) {
+        return SyscallResult::Proceed(-EINVAL);
+    }
+    let cwd = process.cwd();
+    let result = lookup(&cwd, old_parent).and_then(|old_dir| {
+        let new_dir = lookup(&cwd, new_parent)?;
+        let inode = old_dir.find(old_name)?;
+        if let Ok(target) = new_dir.find(new_name) {
+            if target.metadata()?.inode == inode.metadata()?.inode {
+                return Ok(());
+            }
+            if target.metadata()?.type_ == FileType::Dir {
+                return Err(FsError::IsDir);
+            }
+            new_dir.unlink(new_name)?;
+            if target.metadata()?.nlinks == 0 {
+                PAGE_CACHE.lock().invalidate(&target).ok();
             }
         }
+        old_dir.move_(old_name, &new_dir, new_name)
+    });
+    match result {
+        Ok(()) => SyscallResult::Proceed(0),
+        Err(e) => SyscallResult::Proceed(-fs_error(e)),
+    }
+}
+
+/// 路径的最后一项能否作为新建、删除或移动的目录项名字
+///
+/// 路径为空或 `/` 时名字为空；`.` 和 `..` 是每个目录固有的目录项
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".."
+}
+
+/// 获取路径为 `path` 的文件的信息
+pub(super) fn sys_stat(path: *const u8, stat: *mut Stat) -> SyscallResult {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    let path = match read_user_string(&process, path) {
+        Some(path) => path,
+        None => return SyscallResult::Proceed(-EFAULT),
+    };
+    if process
+        .prepare_user_access(stat as usize, size_of::<Stat>(), true)
+        .is_err()
+    {
+        return SyscallResult::Proceed(-EFAULT);
+    }
+    match lookup(&process.cwd(), &path).and_then(|inode| inode.metadata()) {
+        Ok(metadata) => {
+            unsafe { *stat = to_stat(&metadata) };
+            SyscallResult::Proceed(0)
+        }
+        Err(e) => SyscallResult::Proceed(-fs_error(e)),
+    }
+}
+
+/// 获取文件描述符对应的文件的信息
+pub(super) fn sys_fstat(fd: usize, stat: *mut Stat) -> SyscallResult {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    if process
+        .prepare_user_access(stat as usize, size_of::<Stat>(), true)
+        .is_err()
+    {
+        return SyscallResult::Proceed(-EFAULT);
+    }
+    let file = match process.get_descriptor(fd) {
+        Some(file) => file,
+        None => return SyscallResult::Proceed(-EBADF),
+    };
+    match file.inode.metadata() {
+        Ok(metadata) => {
+            unsafe { *stat = to_stat(&metadata) };
+            SyscallResult::Proceed(0)
+        }
+        Err(e) => SyscallResult::Proceed(-fs_error(e)),
+    }
+}
+
+/// 从目录中读取最多 `count` 个目录项，返回读取的项数，读完时返回 0
+pub(super) fn sys_getdents(fd: usize, dirents: *mut Dirent, count: usize) -> SyscallResult {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    let size = match count.checked_mul(size_of::<Dirent>()) {
+        Some(size) => size,
+        None => return SyscallResult::Proceed(-EINVAL),
+    };
+    if process
+        .prepare_user_access(dirents as usize, size, true)
+        .is_err()
+    {
+        return SyscallResult::Proceed(-EFAULT);
+    }
+    let file = match process.get_descriptor(fd) {
+        Some(file) => file,
+        None => return SyscallResult::Proceed(-EBADF),
+    };
+    match file.inode.metadata() {
+        Ok(metadata) if metadata.type_ == FileType::Dir => {}
+        Ok(_) => return SyscallResult::Proceed(-ENOTDIR),
+        Err(e) => return SyscallResult::Proceed(-fs_error(e)),
+    }
+    let dirents = unsafe { from_raw_parts_mut(dirents, count) };
+    let mut read = 0;
+    while read < count {
+        let name = match file.next_entry() {
+            Some(name) => name,
+            None => break,
+        };
+        let dirent = &mut dirents[read];
+        *dirent = Dirent::default();
+        if let Ok(metadata) = file.inode.find(&name).and_then(|inode| inode.metadata()) {
+            dirent.ino = metadata.inode as u64;
+            dirent.mode = file_type_mode(metadata.type_);
+        }
+        // 过长的名字会被截断，保留结尾的 `\0`
+        let len = name.len().min(DIRENT_NAME_LENGTH - 1);
+        dirent.name[..len].copy_from_slice(&name.as_bytes()[..len]);
+        read += 1;
+    }
+    SyscallResult::Proceed(read as isize)
+}
+
+/// 改变当前进程的工作目录
+pub(super) fn sys_chdir(path: *const u8) -> SyscallResult {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    let path = match read_user_string(&process, path) {
+        Some(path) => path,
+        None => return SyscallResult::Proceed(-EFAULT),
+    };
+    let dir = match lookup(&process.cwd(), &path) {
+        Ok(dir) => dir,
+        Err(e) => return SyscallResult::Proceed(-fs_error(e)),
+    };
+    match dir.metadata() {
+        Ok(metadata) if metadata.type_ == FileType::Dir => {
+            process.inner().cwd = dir;
+            SyscallResult::Proceed(0)
+        }
+        Ok(_) => SyscallResult::Proceed(-ENOTDIR),
+        Err(e) => SyscallResult::Proceed(-fs_error(e)),
+    }
+}
+
+/// 将当前进程工作目录的绝对路径以 `\0` 结尾写入 `buffer`，返回路径的长度（不含 `\0`）
+///
+/// 缓冲区不足时返回 `-ERANGE`
+pub(super) fn sys_getcwd(buffer: *mut u8, size: usize) -> SyscallResult {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    let path = match absolute_path(&process.cwd()) {
+        Ok(path) => path,
+        Err(e) => return SyscallResult::Proceed(-fs_error(e)),
+    };
+    if path.len() + 1 > size {
+        return SyscallResult::Proceed(-ERANGE);
+    }
+    if process
+        .prepare_user_access(buffer as usize, path.len() + 1, true)
+        .is_err()
+    {
+        return SyscallResult::Proceed(-EFAULT);
+    }
+    let buffer = unsafe { from_raw_parts_mut(buffer, path.len() + 1) };
+    buffer[..path.len()].copy_from_slice(path.as_bytes());
+    buffer[path.len()] = 0;
+    SyscallResult::Proceed(path.len() as isize)
+}
+
+/// 将文件系统中的 [`Metadata`] 转换为 [`Stat`]
+fn to_stat(metadata: &Metadata) -> Stat {
+    Stat {
+        dev: metadata.dev as u64,
+        ino: metadata.inode as u64,
+        mode: file_type_mode(metadata.type_) | metadata.mode as u32,
+        nlink: metadata.nlinks as u32,
+        size: metadata.size as u64,
+        blksize: metadata.blk_size as u64,
+        blocks: metadata.blocks as u64,
+        atime: metadata.atime.sec,
+        mtime: metadata.mtime.sec,
+        ctime: metadata.ctime.sec,
+    }
+}
+
+/// 文件类型在 `mode` 中的表示
+fn file_type_mode(type_: FileType) -> u32 {
+    match type_ {
+        FileType::File => S_IFREG,
+        FileType::Dir => S_IFDIR,
+        FileType::SymLink => S_IFLNK,
+        FileType::CharDevice => S_IFCHR,
+        FileType::BlockDevice => S_IFBLK,
+        FileType::NamedPipe => S_IFIFO,
+        FileType::Socket => S_IFSOCK,
+    }
+}
+
+/// 将文件系统的错误转换为错误码
+fn fs_error(error: FsError) -> isize {
+    match error {
+        FsError::EntryNotFound => ENOENT,
+        FsError::EntryExist => EEXIST,
+        FsError::NotDir => ENOTDIR,
+        FsError::IsDir => EISDIR,
+        FsError::InvalidParam => EINVAL,
+        FsError::DirNotEmpty => ENOTEMPTY,
+        FsError::Interrupted => EINTR,
+        _ => EIO,
     }
-    SyscallResult::Proceed(-1)
 }
@@ -0,0 +1,154 @@
+//! 内存映射相关的系统调用
+
+use super::*;
+use crate::memory::addr::VirtualAddress;
+use crate::memory::mapping::Flags;
+use crate::memory::range::Range;
+use crate::memory::{PAGE_SIZE, USER_SPACE_END};
+use lib_redos::{
+    EBADF, EINVAL, EIO, ENOMEM, MAP_ANONYMOUS, MAP_FIXED, MAP_PRIVATE, MAP_SHARED, PROT_EXEC,
+    PROT_READ, PROT_WRITE,
+};
+
+/// 将当前进程的 program break 设置为 `brk`，返回新的 program break
+///
+/// `brk` 为 0 时仅查询当前值；调整失败时返回原来的值
+pub(super) fn sys_brk(brk: usize) -> SyscallResult {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    SyscallResult::Proceed(process.set_brk(brk).0 as isize)
+}
+
+/// 在当前进程中建立一段内存映射，返回映射的起始地址
+///
+/// `address` 不为 0 时作为建议的地址，设置了 `MAP_FIXED` 时必须使用该地址并覆盖原有的映射，
+/// 此时不能映射第 0 页。不支持共享的匿名映射（`MAP_SHARED | MAP_ANONYMOUS`），返回 `-EINVAL`。
+/// 匿名映射的物理页面在第一次访问时才分配；文件映射的页面在第一次访问时从页面缓存中取得，
+/// 只支持按页对齐的 `offset`，且映射的末尾在文件中的偏移量不能超过 `isize::MAX`
+pub(super) fn sys_mmap(
+    address: usize,
+    length: usize,
+    prot: usize,
+    flags: usize,
+    fd: usize,
+    offset: usize,
+) -> SyscallResult {
+    let shared = match flags & (MAP_SHARED | MAP_PRIVATE) {
+        MAP_SHARED => true,
+        MAP_PRIVATE => false,
+        _ => return SyscallResult::Proceed(-EINVAL),
+    };
+    // 匿名映射的页面在 fork 之后会写时复制，无法在父子进程之间共享
+    if shared && flags & MAP_ANONYMOUS != 0 {
+        return SyscallResult::Proceed(-EINVAL);
+    }
+    let length = match page_aligned_range(0, length) {
+        Some(range) => range.end.0,
+        None => return SyscallResult::Proceed(-EINVAL),
+    };
+    if flags & MAP_ANONYMOUS == 0
+        && (offset % PAGE_SIZE != 0
+            || !matches!(offset.checked_add(length), Some(end) if end <= isize::MAX as usize))
+    {
+        return SyscallResult::Proceed(-EINVAL);
+    }
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    let range = if flags & MAP_FIXED != 0 {
+        // 第 0 页保持不映射，使空指针的访问总是出错
+        match page_aligned_range(address, length) {
+            Some(range) if address >= PAGE_SIZE => range,
+            _ => return SyscallResult::Proceed(-EINVAL),
+        }
+    } else {
+        match page_aligned_range(address, length) {
+            Some(range) if address != 0 && process.is_range_free(range) => range,
+            _ => match process.find_free_range(length) {
+                Ok(range) => range,
+                Err(_) => return SyscallResult::Proceed(-ENOMEM),
+            },
+        }
+    };
+    let page_flags = prot_to_flags(prot);
+    let result = if flags & MAP_ANONYMOUS != 0 {
+        process.map_fixed(range, page_flags)
+    } else {
+        let file = match process.get_descriptor(fd) {
+            Some(file) if file.readable() => file,
+            _ => return SyscallResult::Proceed(-EBADF),
+        };
+        // 共享的可写映射会写回文件
+        if shared && prot & PROT_WRITE != 0 && !file.writable() {
+            return SyscallResult::Proceed(-EBADF);
+        }
+        // 只有普通文件可以被映射
+        if !file.is_regular() {
+            return SyscallResult::Proceed(-EINVAL);
+        }
+        let inode = file.inode.clone();
+        process.map_file(range, page_flags, inode, offset, shared)
+    };
+    match result {
+        Ok(()) => SyscallResult::Proceed(range.start.0 as isize),
+        Err(_) => SyscallResult::Proceed(-ENOMEM),
+    }
+}
+
+/// 移除当前进程中一段地址区间的映射
+pub(super) fn sys_munmap(address: usize, length: usize) -> SyscallResult {
+    let range = match page_aligned_range(address, length) {
+        Some(range) => range,
+        None => return SyscallResult::Proceed(-EINVAL),
+    };
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    match process.unmap_range(range) {
+        Ok(()) => SyscallResult::Proceed(0),
+        Err(_) => SyscallResult::Proceed(-EINVAL),
+    }
+}
+
+/// 将一段地址区间内共享文件映射的修改写回文件
+pub(super) fn sys_msync(address: usize, length: usize, _flags: usize) -> SyscallResult {
+    let range = match page_aligned_range(address, length) {
+        Some(range) => range,
+        None => return SyscallResult::Proceed(-EINVAL),
+    };
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    match process.sync_range(range) {
+        Ok(()) => SyscallResult::Proceed(0),
+        Err(_) => SyscallResult::Proceed(-EIO),
+    }
+}
+
+/// 修改当前进程中一段地址区间的权限，区间中有未映射的页面时返回 `-ENOMEM`
+pub(super) fn sys_mprotect(address: usize, length: usize, prot: usize) -> SyscallResult {
+    let range = match page_aligned_range(address, length) {
+        Some(range) => range,
+        None => return SyscallResult::Proceed(-EINVAL),
+    };
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    match process.protect_range(range, prot_to_flags(prot)) {
+        Ok(()) => SyscallResult::Proceed(0),
+        Err(_) => SyscallResult::Proceed(-ENOMEM),
+    }
+}
+
+/// 检查起始地址是否按页对齐，并将长度向上取整到页
+///
+/// 长度为 0 或者区间超出用户地址空间 [`USER_SPACE_END`] 时返回 `None`
+fn page_aligned_range(address: usize, length: usize) -> Option<Range<VirtualAddress>> {
+    if address % PAGE_SIZE != 0 || length == 0 {
+        return None;
+    }
+    let length = length.checked_add(PAGE_SIZE - 1)? & !(PAGE_SIZE - 1);
+    let end = address.checked_add(length)?;
+    if end > USER_SPACE_END {
+        return None;
+    }
+    Some(Range::from(address..end))
+}
+
+/// 将 `PROT_*` 转换为页表项的权限，RISC-V 不允许只写不读的页面
+fn prot_to_flags(prot: usize) -> Flags {
+    Flags::readable(prot & (PROT_READ | PROT_WRITE) != 0)
+        | Flags::writable(prot & PROT_WRITE != 0)
+        | Flags::executable(prot & PROT_EXEC != 0)
+}
@@ -1,15 +1,23 @@
 //! 为进程提供系统调用等内核功能
 
 pub(self) use fs::*;
+pub(self) use memory::*;
 pub(self) use process::*;
+pub(self) use signal::*;
 pub use syscall::syscall_handler;
 pub(crate) use syscall::*;
+pub(self) use time::*;
+pub(self) use user::*;
 
 pub use crate::process::condvar::Condvar;
 pub use crate::process::*;
 
 mod fs;
+mod memory;
 mod process;
+mod signal;
 pub mod syscall;
+mod time;
+mod user;
 
 extern crate alloc;
@@ -1,12 +1,243 @@
 //! 进程相关的内核功能
 
 use super::*;
+use crate::fs::stdin::Stdin;
+use crate::fs::{lookup, INodeExt};
+use crate::interrupt::context::Context;
+use crate::process::process::set_foreground_process;
+use crate::process::process::{find_process, Process};
+use crate::process::processor::{add_thread, schedule, set_nice};
+use crate::process::thread::Thread;
+use crate::process::thread::ThreadState::Dead;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::mem::size_of;
+use lib_redos::{
+    ProcessID, ECHILD, EFAULT, EINTR, EINVAL, EIO, ENOENT, ENOEXEC, ENOMEM, ESRCH, NICE_MAX,
+    NICE_MIN, PRIO_PROCESS, WNOHANG,
+};
+use xmas_elf::ElfFile;
 
-pub(super) fn sys_exit(code: usize) -> SyscallResult {
-    println!(
-        "thread {} exit with code {}",
-        PROCESSOR.lock().current_thread().id,
-        code
-    );
+/// 结束当前线程，如果是进程中最后一个线程，则进程以 `code` 退出
+pub(super) fn sys_exit(code: isize) -> SyscallResult {
+    let current_thread = PROCESSOR.lock().current_thread();
+    debug_println!("thread {} exit with code {}", current_thread.id, code);
+    current_thread.process.exit_thread(current_thread.id, code);
+    SyscallResult::Kill
+}
+
+/// 等待子进程退出并回收，`pid` 为 -1 时等待任意子进程
+///
+/// 返回子进程 ID，并将其返回值写入 `status`（可以为空指针）。
+/// 没有符合条件的子进程时返回 `-ECHILD`；子进程都未退出时，若设置了 `WNOHANG` 则返回 0，
+/// 否则当前线程休眠直到有子进程退出；线程被要求终止时返回 `-EINTR`。
+/// 休眠期间 `status` 的映射可能改变，醒来之后才检查它，不可写入时子进程仍被回收，返回 `-EFAULT`
+pub(super) fn sys_waitpid(pid: ProcessID, status: *mut isize, options: usize) -> SyscallResult {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    let mut reaped = Ok(None);
+    let waited = process.child_exit.wait_while(|| {
+        reaped = process.reap_child(pid);
+        matches!(reaped, Ok(None)) && options & WNOHANG == 0
+    });
+    if waited.is_err() {
+        return SyscallResult::Proceed(-EINTR);
+    }
+    match reaped {
+        Ok(Some((pid, code))) => {
+            if !status.is_null() {
+                if process
+                    .prepare_user_access(status as usize, size_of::<isize>(), true)
+                    .is_err()
+                {
+                    return SyscallResult::Proceed(-EFAULT);
+                }
+                unsafe { *status = code };
+            }
+            SyscallResult::Proceed(pid)
+        }
+        Ok(None) => SyscallResult::Proceed(0),
+        Err(_) => SyscallResult::Proceed(-ECHILD),
+    }
+}
+
+/// 结束整个进程，终止进程中的所有线程
+pub(super) fn sys_exit_group(code: isize) -> SyscallResult {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    debug_println!("process {} exit with code {}", process.pid, code);
+    process.exit_group(code);
     SyscallResult::Kill
 }
+
+/// 复制当前进程，子进程中只包含调用 fork 的线程
+///
+/// 父进程中返回子进程 ID，子进程中返回 0；出现错误返回 -1
+pub(super) fn sys_fork(context: &Context) -> SyscallResult {
+    let current_thread = PROCESSOR.lock().current_thread();
+    match current_thread.process.fork() {
+        Ok(process) => {
+            let pid = process.pid;
+            match current_thread.fork(process.clone(), context) {
+                Ok(thread) => {
+                    add_thread(thread);
+                    SyscallResult::Proceed(pid)
+                }
+                Err(e) => {
+                    // 没有线程的子进程直接退出，留给父进程回收
+                    debug_println!("error in sys_fork: {}", e);
+                    process.exit(-1);
+                    SyscallResult::Proceed(-ENOMEM)
+                }
+            }
+        }
+        Err(e) => {
+            debug_println!("error in sys_fork: {}", e);
+            SyscallResult::Proceed(-ENOMEM)
+        }
+    }
+}
+
+/// 用文件系统中的程序替换当前进程
+///
+/// 成功时不会回到原来的程序，新程序的入口参数为 `argc` `argv` `envp`；
+/// 失败时返回负的错误码，原来的程序继续执行。
+/// 进程中的其他线程会被终止，等它们全部结束之后才替换内存空间，之后即使失败也不会恢复
+pub(super) fn sys_exec(
+    context: &mut Context,
+    path: *const u8,
+    argv: *const *const u8,
+    envp: *const *const u8,
+) -> SyscallResult {
+    let current_thread = PROCESSOR.lock().current_thread();
+    let process = current_thread.process.clone();
+    // 旧的内存空间会被释放，所以需要先读出所有参数
+    let (path, argv, envp) = match (
+        read_user_string(&process, path),
+        read_user_string_array(&process, argv),
+        read_user_string_array(&process, envp),
+    ) {
+        (Some(path), Some(argv), Some(envp)) => (path, argv, envp),
+        _ => return SyscallResult::Proceed(-EFAULT),
+    };
+    // 其他线程仍在使用旧的内存空间，终止它们并让出处理器核，直到它们全部结束并切换出去
+    let others = process.kill_other_threads(&current_thread);
+    while others
+        .iter()
+        .any(|thread| thread.inner().state != Dead || thread.is_running())
+    {
+        // 其他线程同时调用了 exec 或 exit_group
+        if current_thread.inner().killed {
+            return SyscallResult::Kill;
+        }
+        schedule();
+    }
+    drop(others);
+    // 从文件系统中读取并解析 ELF
+    let data = match lookup(&process.cwd(), &path) {
+        Ok(inode) => match inode.readall() {
+            Ok(data) => data,
+            Err(_) => return SyscallResult::Proceed(-EIO),
+        },
+        Err(_) => return SyscallResult::Proceed(-ENOENT),
+    };
+    let elf = match ElfFile::new(data.as_slice()) {
+        Ok(elf) => elf,
+        Err(_) => return SyscallResult::Proceed(-ENOEXEC),
+    };
+    if let Err(e) = process.exec(&elf, current_thread.stack) {
+        debug_println!("error in sys_exec: {}", e);
+        return SyscallResult::Proceed(-ENOEXEC);
+    }
+
+    // 此时已经切换到新的内存空间，将参数放在新的栈上
+    let stack_top: usize = current_thread.stack.end.into();
+    let addresses = push_user_strings(&process, stack_top, &envp).and_then(|envp_address| {
+        push_user_strings(&process, envp_address, &argv)
+            .map(|argv_address| (argv_address, envp_address))
+    });
+    let (argv_address, envp_address) = match addresses {
+        Some(addresses) => addresses,
+        None => {
+            // 原来的程序已经不存在，只能终止线程
+            debug_println!("error in sys_exec: failed to push arguments");
+            return SyscallResult::Kill;
+        }
+    };
+    // 重置线程的 Context，从新程序的入口开始执行
+    *context = Context::new(
+        argv_address & !0xf,
+        elf.header.pt2.entry_point() as usize,
+        Some(&[argv.len(), argv_address, envp_address]),
+        process.is_user,
+    );
+    // 从控制台读取输入的程序成为前台进程
+    let reads_console = process
+        .get_descriptor(0)
+        .map_or(false, |file| file.inode.as_any_ref().is::<Stdin>());
+    if reads_console {
+        set_foreground_process(&process);
+    }
+    // 返回值会写入 a0，即新程序的 argc
+    SyscallResult::Proceed(argv.len() as isize)
+}
+
+/// 让出处理器，当前线程仍然可以被调度器选中
+pub(super) fn sys_sched_yield() -> SyscallResult {
+    SyscallResult::Park(0)
+}
+
+/// 设置进程中所有线程的 nice 值，超出范围的值会被截断到 `NICE_MIN..=NICE_MAX`
+///
+/// `which` 只支持 `PRIO_PROCESS`，`pid` 为 0 时表示当前进程
+pub(super) fn sys_setpriority(which: usize, pid: ProcessID, nice: isize) -> SyscallResult {
+    let process = match priority_target(which, pid) {
+        Ok(process) => process,
+        Err(e) => return SyscallResult::Proceed(-e),
+    };
+    let nice = nice.max(NICE_MIN).min(NICE_MAX);
+    for thread in live_threads(&process).iter() {
+        set_nice(thread, nice);
+    }
+    SyscallResult::Proceed(0)
+}
+
+/// 获取进程的 nice 值（进程中各线程的最小值）
+///
+/// 与 Linux 相同，为了与错误码区分，返回 `20 - nice`，范围为 1 到 40
+pub(super) fn sys_getpriority(which: usize, pid: ProcessID) -> SyscallResult {
+    let process = match priority_target(which, pid) {
+        Ok(process) => process,
+        Err(e) => return SyscallResult::Proceed(-e),
+    };
+    let nice = live_threads(&process)
+        .iter()
+        .map(|thread| thread.inner().nice)
+        .min()
+        .unwrap_or(0);
+    SyscallResult::Proceed(20 - nice)
+}
+
+/// setpriority / getpriority 作用的进程，出错时返回错误码
+fn priority_target(which: usize, pid: ProcessID) -> Result<Arc<Process>, isize> {
+    if which != PRIO_PROCESS {
+        return Err(EINVAL);
+    }
+    let process = if pid == 0 {
+        Some(PROCESSOR.lock().current_thread().process.clone())
+    } else {
+        find_process(pid)
+    };
+    match process {
+        Some(process) if process.is_user && process.inner().exit_code.is_none() => Ok(process),
+        _ => Err(ESRCH),
+    }
+}
+
+/// 进程中的所有线程
+fn live_threads(process: &Process) -> Vec<Arc<Thread>> {
+    process
+        .inner()
+        .threads
+        .values()
+        .filter_map(Weak::upgrade)
+        .collect()
+}
@@ -0,0 +1,125 @@
+//! 信号相关的系统调用
+
+use super::*;
+use crate::interrupt::context::Context;
+use crate::process::process::find_process;
+use crate::process::signal::{force_signal, is_valid, restore_frame, send_signal, UNBLOCKABLE};
+use core::mem::size_of;
+use lib_redos::{
+    ProcessID, SigAction, SigSet, EFAULT, EINVAL, ESRCH, NSIG, SIGKILL, SIGSEGV, SIGSTOP,
+    SIG_BLOCK, SIG_SETMASK, SIG_UNBLOCK,
+};
+
+/// 向进程 `pid` 发送信号，`signal` 为 0 时只检查进程是否存在
+///
+/// 只支持向单个进程发送，进程不存在时返回 `-ESRCH`
+pub(super) fn sys_kill(pid: ProcessID, signal: usize) -> SyscallResult {
+    if signal >= NSIG {
+        return SyscallResult::Proceed(-EINVAL);
+    }
+    let process = match find_process(pid) {
+        Some(process) if process.is_user => process,
+        _ => return SyscallResult::Proceed(-ESRCH),
+    };
+    if signal != 0 {
+        send_signal(&process, signal);
+    }
+    SyscallResult::Proceed(0)
+}
+
+/// 设置信号的处理方式，`action` 和 `old_action` 都可以为空指针
+///
+/// 原来的处理方式写入 `old_action`。`SIGKILL` 和 `SIGSTOP` 的处理方式不能被修改
+pub(super) fn sys_sigaction(
+    signal: usize,
+    action: *const SigAction,
+    old_action: *mut SigAction,
+) -> SyscallResult {
+    if !is_valid(signal) || (!action.is_null() && (signal == SIGKILL || signal == SIGSTOP)) {
+        return SyscallResult::Proceed(-EINVAL);
+    }
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    let size = size_of::<SigAction>();
+    if (!action.is_null()
+        && process
+            .prepare_user_access(action as usize, size, false)
+            .is_err())
+        || (!old_action.is_null()
+            && process
+                .prepare_user_access(old_action as usize, size, true)
+                .is_err())
+    {
+        return SyscallResult::Proceed(-EFAULT);
+    }
+    let previous = {
+        let mut inner = process.inner();
+        let previous = inner.signal_actions[signal];
+        if !action.is_null() {
+            inner.signal_actions[signal] = unsafe { *action };
+        }
+        previous
+    };
+    if !old_action.is_null() {
+        unsafe { *old_action = previous };
+    }
+    SyscallResult::Proceed(0)
+}
+
+/// 修改当前线程屏蔽的信号，`set` 和 `old_set` 都可以为空指针
+///
+/// `how` 为 `SIG_BLOCK` `SIG_UNBLOCK` 或 `SIG_SETMASK`。`SIGKILL` 和 `SIGSTOP` 不能被屏蔽
+pub(super) fn sys_sigprocmask(
+    how: usize,
+    set: *const SigSet,
+    old_set: *mut SigSet,
+) -> SyscallResult {
+    let thread = PROCESSOR.lock().current_thread();
+    let size = size_of::<SigSet>();
+    if (!set.is_null()
+        && thread
+            .process
+            .prepare_user_access(set as usize, size, false)
+            .is_err())
+        || (!old_set.is_null()
+            && thread
+                .process
+                .prepare_user_access(old_set as usize, size, true)
+                .is_err())
+    {
+        return SyscallResult::Proceed(-EFAULT);
+    }
+    let previous = {
+        let mut inner = thread.inner();
+        let previous = inner.mask;
+        if !set.is_null() {
+            let set = unsafe { *set };
+            let mask = match how {
+                SIG_BLOCK => previous | set,
+                SIG_UNBLOCK => previous & !set,
+                SIG_SETMASK => set,
+                _ => return SyscallResult::Proceed(-EINVAL),
+            };
+            inner.mask = mask & !UNBLOCKABLE;
+        }
+        previous
+    };
+    if !old_set.is_null() {
+        unsafe { *old_set = previous };
+    }
+    SyscallResult::Proceed(0)
+}
+
+/// 从信号处理函数返回，恢复被信号打断时的现场
+///
+/// 用户栈上保存的现场无法读取时，向当前线程发送 `SIGSEGV`
+pub(super) fn sys_sigreturn(context: &mut Context) -> SyscallResult {
+    let thread = PROCESSOR.lock().current_thread();
+    match restore_frame(&thread, context) {
+        Ok(ret) => SyscallResult::Proceed(ret as isize),
+        Err(_) => {
+            let address = context.sp();
+            force_signal(&thread, SIGSEGV, address);
+            SyscallResult::Proceed(-EFAULT)
+        }
+    }
+}
@@ -4,37 +4,67 @@ use super::*;
 use crate::interrupt::context::Context;
 use crate::kernel::mutex::{sys_mutex_create, sys_mutex_destroy, sys_mutex_lock};
 use crate::kernel::thread::sys_join;
-use crate::process::alarm::sys_sleep;
+use crate::process::alarm::{sys_nanosleep, sys_sleep};
 use crate::process::mutex::sys_mutex_unlock;
+use crate::process::processor::{add_thread, exit_current_thread, schedule};
+use crate::process::semaphore::{sys_sem_create, sys_sem_destroy, sys_sem_post, sys_sem_wait};
 use crate::process::thread::{Thread, ThreadID};
 use core::ffi::c_void;
-use lib_redos::MutexID;
+use lib_redos::{
+    Dirent, MutexID, ProcessID, SemID, SigAction, SigSet, Stat, TimeSpec, TimeVal, ENOMEM,
+};
 
 /// 系统调用在内核之内的返回值
 pub enum SyscallResult {
     /// 继续执行，带返回值
     Proceed(isize),
-    /// 记录返回值，但暂存当前线程
+    /// 记录返回值，让出处理器，再次被调度时继续执行
     Park(isize),
-    /// 丢弃当前 context，调度下一个线程继续执行
+    /// 终止当前线程，调度下一个线程继续执行
     Kill,
 }
 
 /// 系统调用的总入口
-pub fn syscall_handler(context: &mut Context) -> *mut Context {
+pub fn syscall_handler(context: &mut Context) {
     // 无论如何处理，一定会跳过当前的 ecall 指令
     context.sepc += 4;
 
     let syscall_id = context.x[17];
-    let args = [context.x[10], context.x[11], context.x[12], context.x[13]];
+    let args = [
+        context.x[10],
+        context.x[11],
+        context.x[12],
+        context.x[13],
+        context.x[14],
+        context.x[15],
+    ];
 
     let result = match syscall_id {
-        lib_redos::SYS_SLEEP => sys_sleep(args[0] as u64),
+        lib_redos::SYS_SLEEP => sys_sleep(args[0]),
+        lib_redos::SYS_NANOSLEEP => sys_nanosleep(args[0]),
+        lib_redos::SYS_CLOCK_GETTIME => sys_clock_gettime(args[0], args[1] as *mut TimeSpec),
+        lib_redos::SYS_GETTIMEOFDAY => sys_gettimeofday(args[0] as *mut TimeVal),
         lib_redos::SYS_JOIN => sys_join(args[0] as ThreadID),
+        lib_redos::SYS_LSEEK => sys_lseek(args[0], args[1] as isize, args[2]),
+        lib_redos::SYS_GETCWD => sys_getcwd(args[0] as *mut u8, args[1]),
         lib_redos::SYS_MUTEX_CREATE => sys_mutex_create(args[0] as *mut MutexID),
         lib_redos::SYS_MUTEX_DESTROY => sys_mutex_destroy(args[0] as *mut MutexID),
         lib_redos::SYS_MUTEX_LOCK => sys_mutex_lock(args[0] as *mut MutexID),
         lib_redos::SYS_MUTEX_UNLOCK => sys_mutex_unlock(args[0] as *mut MutexID),
+        lib_redos::SYS_SEM_CREATE => sys_sem_create(args[0] as *mut SemID, args[1]),
+        lib_redos::SYS_SEM_WAIT => sys_sem_wait(args[0] as *const SemID),
+        lib_redos::SYS_SEM_POST => sys_sem_post(args[0] as *const SemID),
+        lib_redos::SYS_SEM_DESTROY => sys_sem_destroy(args[0] as *const SemID),
+        lib_redos::SYS_DUP => sys_dup(args[0]),
+        lib_redos::SYS_DUP2 => sys_dup2(args[0], args[1], args[2]),
+        lib_redos::SYS_MKDIR => sys_mkdir(args[0] as *const u8),
+        lib_redos::SYS_UNLINK => sys_unlink(args[0] as *const u8),
+        lib_redos::SYS_RENAME => sys_rename(args[0] as *const u8, args[1] as *const u8),
+        lib_redos::SYS_CHDIR => sys_chdir(args[0] as *const u8),
+        lib_redos::SYS_OPEN => sys_open(args[0] as *const u8, args[1]),
+        lib_redos::SYS_CLOSE => sys_close(args[0]),
+        lib_redos::SYS_PIPE => sys_pipe(args[0] as *mut usize),
+        lib_redos::SYS_GETDENTS => sys_getdents(args[0], args[1] as *mut Dirent, args[2]),
         lib_redos::SYS_CREATE_THREAD => sys_create_thread(
             args[0] as *mut ThreadID,
             args[1],
@@ -43,7 +73,38 @@ pub fn syscall_handler(context: &mut Context) -> *mut Context {
         ),
         lib_redos::SYS_READ => sys_read(args[0], args[1] as *mut u8, args[2]),
         lib_redos::SYS_WRITE => sys_write(args[0], args[1] as *mut u8, args[2]),
-        lib_redos::SYS_EXIT => sys_exit(args[0]),
+        lib_redos::SYS_STAT => sys_stat(args[0] as *const u8, args[1] as *mut Stat),
+        lib_redos::SYS_FSTAT => sys_fstat(args[0], args[1] as *mut Stat),
+        lib_redos::SYS_EXIT => sys_exit(args[0] as isize),
+        lib_redos::SYS_EXIT_GROUP => sys_exit_group(args[0] as isize),
+        lib_redos::SYS_FORK => sys_fork(context),
+        lib_redos::SYS_EXEC => sys_exec(
+            context,
+            args[0] as *const u8,
+            args[1] as *const *const u8,
+            args[2] as *const *const u8,
+        ),
+        lib_redos::SYS_BRK => sys_brk(args[0]),
+        lib_redos::SYS_MMAP => sys_mmap(args[0], args[1], args[2], args[3], args[4], args[5]),
+        lib_redos::SYS_MUNMAP => sys_munmap(args[0], args[1]),
+        lib_redos::SYS_MPROTECT => sys_mprotect(args[0], args[1], args[2]),
+        lib_redos::SYS_MSYNC => sys_msync(args[0], args[1], args[2]),
+        lib_redos::SYS_SCHED_YIELD => sys_sched_yield(),
+        lib_redos::SYS_KILL => sys_kill(args[0] as ProcessID, args[1]),
+        lib_redos::SYS_SIGACTION => sys_sigaction(
+            args[0],
+            args[1] as *const SigAction,
+            args[2] as *mut SigAction,
+        ),
+        lib_redos::SYS_SIGPROCMASK => {
+            sys_sigprocmask(args[0], args[1] as *const SigSet, args[2] as *mut SigSet)
+        }
+        lib_redos::SYS_SIGRETURN => sys_sigreturn(context),
+        lib_redos::SYS_SETPRIORITY => {
+            sys_setpriority(args[0], args[1] as ProcessID, args[2] as isize)
+        }
+        lib_redos::SYS_GETPRIORITY => sys_getpriority(args[0], args[1] as ProcessID),
+        lib_redos::SYS_WAITPID => sys_waitpid(args[0] as ProcessID, args[1] as *mut isize, args[2]),
         _ => {
             println!("unimplemented syscall: {}", syscall_id);
             SyscallResult::Kill
@@ -54,21 +115,15 @@ pub fn syscall_handler(context: &mut Context) -> *mut Context {
         SyscallResult::Proceed(ret) => {
             // 将返回值放入 context 中
             context.x[10] = ret as usize;
-            context
         }
         SyscallResult::Park(ret) => {
-            // 将返回值放入 context 中
+            // 将返回值放入 context 中，切换到下一个线程
             context.x[10] = ret as usize;
-            // 保存 context，准备下一个线程
-            let mut guard = PROCESSOR.lock();
-            guard.park_current_thread(context);
-            guard.prepare_next_thread()
+            schedule();
         }
         SyscallResult::Kill => {
-            // 终止，跳转到 PROCESSOR 调度的下一个线程
-            let t = PROCESSOR.lock().kill_current_thread();
-            drop(t);
-            PROCESSOR.lock().prepare_next_thread()
+            // 终止，不会再返回
+            exit_current_thread();
         }
     }
 }
@@ -80,17 +135,20 @@ fn sys_create_thread(
     exit_fn: usize,
     args: *const c_void,
 ) -> SyscallResult {
-    match Thread::spawn(entry_point, exit_fn, args) {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    let prepared =
+        process.prepare_user_access(thread_id as usize, core::mem::size_of::<ThreadID>(), true);
+    match prepared.and_then(|_| Thread::spawn(entry_point, exit_fn, args)) {
         Ok(nt) => {
             unsafe {
                 *thread_id = nt.id;
             }
-            PROCESSOR.lock().add_thread(nt);
+            add_thread(nt);
             SyscallResult::Proceed(0)
         }
         Err(e) => {
-            println!("error in sys_create_thread: {}", e);
-            SyscallResult::Proceed(-1)
+            debug_println!("error in sys_create_thread: {}", e);
+            SyscallResult::Proceed(-ENOMEM)
         }
     }
 }
@@ -0,0 +1,40 @@
+//! 时间相关的系统调用
+
+use super::*;
+use crate::drivers::rtc::realtime_nanos;
+use crate::interrupt::timer::monotonic_nanos;
+use core::mem::size_of;
+use lib_redos::{TimeSpec, TimeVal, CLOCK_MONOTONIC, CLOCK_REALTIME, EFAULT, EINVAL};
+
+/// 读取时钟 `clock` 的时间写入 `time`
+///
+/// 支持 `CLOCK_REALTIME` 和 `CLOCK_MONOTONIC`，其他时钟返回 `-EINVAL`
+pub(super) fn sys_clock_gettime(clock: usize, time: *mut TimeSpec) -> SyscallResult {
+    let nanos = match clock {
+        CLOCK_REALTIME => realtime_nanos(),
+        CLOCK_MONOTONIC => monotonic_nanos(),
+        _ => return SyscallResult::Proceed(-EINVAL),
+    };
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    if process
+        .prepare_user_access(time as usize, size_of::<TimeSpec>(), true)
+        .is_err()
+    {
+        return SyscallResult::Proceed(-EFAULT);
+    }
+    unsafe { *time = TimeSpec::from_nanos(nanos) };
+    SyscallResult::Proceed(0)
+}
+
+/// 读取墙上时间写入 `time`，精度为微秒
+pub(super) fn sys_gettimeofday(time: *mut TimeVal) -> SyscallResult {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    if process
+        .prepare_user_access(time as usize, size_of::<TimeVal>(), true)
+        .is_err()
+    {
+        return SyscallResult::Proceed(-EFAULT);
+    }
+    unsafe { *time = TimeVal::from_nanos(realtime_nanos()) };
+    SyscallResult::Proceed(0)
+}
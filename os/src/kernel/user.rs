@@ -0,0 +1,119 @@
+//! 在系统调用中读取用户内存
+//!
+//! 内核直接通过用户地址访问数据，访问之前需要调用 [`Process::prepare_user_access`]
+
+extern crate alloc;
+
+use super::*;
+use crate::memory::PAGE_SIZE;
+use crate::process::process::Process;
+use alloc::{string::String, vec::Vec};
+use core::mem::size_of;
+
+/// 用户传入的字符串的最大长度
+const MAX_STRING_LENGTH: usize = 1024;
+
+/// 用户传入的字符串数组的最大长度
+///
+/// 和 [`MAX_STRING_LENGTH`] 一起保证 exec 的参数能够放入线程栈中
+const MAX_ARRAY_LENGTH: usize = 64;
+
+/// 从用户内存中读取一个以 `\0` 结尾的字符串
+pub(super) fn read_user_string(process: &Process, pointer: *const u8) -> Option<String> {
+    let mut bytes = Vec::new();
+    let mut address = pointer as usize;
+    loop {
+        // 每进入一个新的页面都需要检查
+        if bytes.is_empty() || address % PAGE_SIZE == 0 {
+            process.prepare_user_access(address, 1, false).ok()?;
+        }
+        let byte = unsafe { *(address as *const u8) };
+        if byte == 0 {
+            return String::from_utf8(bytes).ok();
+        }
+        if bytes.len() == MAX_STRING_LENGTH {
+            return None;
+        }
+        bytes.push(byte);
+        address += 1;
+    }
+}
+
+/// 从用户内存中读取一个以空指针结尾的字符串指针数组
+///
+/// `pointer` 为空指针时视为空数组
+pub(super) fn read_user_string_array(
+    process: &Process,
+    pointer: *const *const u8,
+) -> Option<Vec<String>> {
+    let mut strings = Vec::new();
+    if pointer.is_null() {
+        return Some(strings);
+    }
+    loop {
+        let address = pointer as usize + strings.len() * size_of::<usize>();
+        process
+            .prepare_user_access(address, size_of::<usize>(), false)
+            .ok()?;
+        let string_pointer = unsafe { *(address as *const *const u8) };
+        if string_pointer.is_null() {
+            return Some(strings);
+        }
+        if strings.len() == MAX_ARRAY_LENGTH {
+            return None;
+        }
+        strings.push(read_user_string(process, string_pointer)?);
+    }
+}
+
+/// 检查并读取一段用户内存，复制到内核缓冲区中
+pub(super) fn copy_from_user(
+    process: &Process,
+    pointer: *const u8,
+    size: usize,
+) -> Option<Vec<u8>> {
+    process
+        .prepare_user_access(pointer as usize, size, false)
+        .ok()?;
+    Some(unsafe { core::slice::from_raw_parts(pointer, size) }.to_vec())
+}
+
+/// 检查一段用户内存并将 `data` 写入
+pub(super) fn copy_to_user(process: &Process, pointer: *mut u8, data: &[u8]) -> Option<()> {
+    process
+        .prepare_user_access(pointer as usize, data.len(), true)
+        .ok()?;
+    unsafe { core::slice::from_raw_parts_mut(pointer, data.len()) }.copy_from_slice(data);
+    Some(())
+}
+
+/// 将字符串数组压入用户栈，用于 exec 传递参数
+///
+/// 字符串以 `\0` 结尾，之后是以空指针结尾的指针数组。返回新的栈顶，也即指针数组的地址
+pub(super) fn push_user_strings(
+    process: &Process,
+    stack_top: usize,
+    strings: &[String],
+) -> Option<usize> {
+    let strings_size: usize = strings.iter().map(|s| s.len() + 1).sum();
+    let array_size = (strings.len() + 1) * size_of::<usize>();
+    // 指针数组需要按 usize 对齐
+    let array_address = (stack_top - strings_size - array_size) & !(size_of::<usize>() - 1);
+    process
+        .prepare_user_access(array_address, stack_top - array_address, true)
+        .ok()?;
+
+    let mut string_address = stack_top;
+    let array =
+        unsafe { core::slice::from_raw_parts_mut(array_address as *mut usize, strings.len() + 1) };
+    for (pointer, string) in array.iter_mut().zip(strings) {
+        string_address -= string.len() + 1;
+        let buffer =
+            unsafe { core::slice::from_raw_parts_mut(string_address as *mut u8, string.len() + 1) };
+        buffer[..string.len()].copy_from_slice(string.as_bytes());
+        buffer[string.len()] = 0;
+        *pointer = string_address;
+    }
+    array[strings.len()] = 0;
+    Some(array_address)
+}
@@ -28,6 +28,7 @@
 #[macro_use]
 pub mod console;
 pub mod arena;
+pub mod cmdline;
 pub mod drivers;
 pub mod fs;
 pub mod interrupt;
@@ -36,6 +37,7 @@ pub mod memory;
 mod panic;
 pub mod process;
 pub mod sbi;
+pub mod smp;
 
 type KResult<T> = Result<T, &'static str>;
 
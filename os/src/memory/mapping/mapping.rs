@@ -10,8 +10,9 @@ use crate::memory::frame_tracker::FrameTracker;
 use crate::memory::mapping::page_table::{PageTable, PageTableTracker};
 use crate::memory::mapping::page_table_entry::{Flags, PageTableEntry};
 use crate::memory::mapping::segment::{MapType, Segment};
-use alloc::{collections::VecDeque, vec, vec::Vec};
-use core::cmp::min;
+use crate::memory::mapping::stack_region::STACK_REGION;
+use alloc::{collections::BTreeMap, sync::Arc, vec, vec::Vec};
+use core::cmp::{max, min};
 use core::ptr::slice_from_raw_parts_mut;
 
 #[derive(Default)]
@@ -22,7 +23,9 @@ pub struct Mapping {
     /// 根页表的物理页号
     root_ppn: PhysicalPageNumber,
     /// 所有分配的物理页面映射信息
-    mapped_pairs: VecDeque<(VirtualPageNumber, FrameTracker)>,
+    ///
+    /// fork 之后父子进程会共享同一个物理页面，直到其中一方写入时才复制
+    mapped_pairs: BTreeMap<VirtualPageNumber, Arc<FrameTracker>>,
 }
 
 impl Mapping {
@@ -30,6 +33,7 @@ impl Mapping {
     pub fn activate(&self) {
         // satp 低 27 位为页号，高 4 位为模式，8 表示 Sv39
         let new_satp = self.root_ppn.0 | (8 << 60);
+        crate::smp::set_active_root(self.root_ppn.0);
         unsafe {
             // 将 new_satp 的值写到 satp 寄存器
             llvm_asm!("csrw satp, $0" :: "r"(new_satp) :: "volatile");
@@ -38,14 +42,31 @@ impl Mapping {
         }
     }
 
+    /// 刷新 TLB，在移除页表项或降低权限后调用
+    ///
+    /// 同一个进程的线程可能正在其他处理器核上执行，因此同时刷新正在使用这个页表的处理器核的 TLB
+    fn flush_tlb(&self) {
+        crate::smp::flush_tlb_of(self.root_ppn.0);
+    }
+
+    /// 只刷新当前处理器核的 TLB，在新建页表项或提升权限后调用
+    ///
+    /// 其他处理器核如果因为 TLB 中残留的旧项发生缺页异常，会在 [`MemorySet::handle_page_fault`] 中刷新
+    ///
+    /// [`MemorySet::handle_page_fault`]: crate::memory::mapping::MemorySet::handle_page_fault
+    pub fn flush_local_tlb() {
+        crate::smp::flush_local_tlb();
+    }
+
     /// 创建一个有根节点的映射
     pub fn new() -> KResult<Mapping> {
-        let root_table = PageTableTracker::new(FRAME_ALLOCATOR.lock().alloc()?);
+        let mut root_table = PageTableTracker::new(FRAME_ALLOCATOR.lock().alloc()?);
+        STACK_REGION.lock().link(&mut root_table);
         let root_ppn = root_table.page_number();
         Ok(Mapping {
             page_tables: vec![root_table],
             root_ppn,
-            mapped_pairs: VecDeque::new(),
+            mapped_pairs: BTreeMap::new(),
         })
     }
 
@@ -69,33 +90,30 @@ impl Mapping {
             }
             // 需要分配帧进行映射
             MapType::Framed => {
+                // 提供的数据所覆盖的地址区间
+                let data_end = segment.range.start + init_data.map_or(0, |data| data.len());
                 for vpn in segment.page_range().iter() {
+                    // 拷贝时必须考虑区间与整页不对齐的情况
+                    //    start（仅第一页时非零）
+                    //      |        stop（仅最后一页时非零）
+                    // 0    |---data---|          4096
+                    // |------------page------------|
+                    let page_address = VirtualAddress::from(vpn);
+                    let start = max(segment.range.start, page_address);
+                    let stop = min(data_end, page_address + PAGE_SIZE);
+                    if start >= stop {
+                        // 没有数据的页面暂不分配，等到发生 PageFault 时再分配
+                        continue;
+                    }
+
                     // 页面的数据，默认为全零
                     let mut page_data = [0u8; PAGE_SIZE];
-                    // 如果提供了数据，则使用这些数据来填充 page_data
-                    if let Some(init_data) = init_data {
-                        if !init_data.is_empty() {
-                            // 这里必须进行一些调整，因为传入的数据可能并非按照整页对齐
-
-                            // 拷贝时必须考虑区间与整页不对齐的情况
-                            //    start（仅第一页时非零）
-                            //      |        stop（仅最后一页时非零）
-                            // 0    |---data---|          4096
-                            // |------------page------------|
-                            let page_address = VirtualAddress::from(vpn);
-                            let start = if segment.range.start > page_address {
-                                segment.range.start - page_address
-                            } else {
-                                0
-                            };
-                            let stop = min(PAGE_SIZE, segment.range.end - page_address);
-                            // 计算来源和目标区间并进行拷贝
-                            let dst_slice = &mut page_data[start..stop];
-                            let src_slice = &init_data[(page_address + start - segment.range.start)
-                                ..(page_address + stop - segment.range.start)];
-                            dst_slice.copy_from_slice(src_slice);
-                        }
-                    };
+                    // 计算来源和目标区间并进行拷贝
+                    let init_data = init_data.unwrap();
+                    let dst_slice = &mut page_data[(start - page_address)..(stop - page_address)];
+                    let src_slice =
+                        &init_data[(start - segment.range.start)..(stop - segment.range.start)];
+                    dst_slice.copy_from_slice(src_slice);
 
                     // 建立映射
                     let mut frame = FRAME_ALLOCATOR.lock().alloc()?;
@@ -104,9 +122,11 @@ impl Mapping {
                     // 写入数据
                     (*frame).copy_from_slice(&page_data);
                     // 保存
-                    self.mapped_pairs.push_back((vpn, frame));
+                    self.mapped_pairs.insert(vpn, Arc::new(frame));
                 }
             }
+            // 文件映射的页面在发生 PageFault 时从页面缓存中取得
+            MapType::File { .. } => {}
         }
         Ok(())
     }
@@ -114,14 +134,111 @@ impl Mapping {
     /// 移除一段映射
     pub fn unmap(&mut self, segment: &Segment) {
         for vpn in segment.page_range().iter() {
-            let entry = self.find_entry(vpn).unwrap();
-            assert!(!entry.is_empty());
-            // 从页表中清除项
-            entry.clear();
+            // 尚未分配的页面没有页表项
+            if self.mapped_pairs.contains_key(&vpn) || segment.map_type == MapType::Linear {
+                let entry = self.find_entry(vpn).unwrap();
+                assert!(!entry.is_empty());
+                // 从页表中清除项
+                entry.clear();
+            }
+        }
+        // 移除相应的页面，刷新所有处理器核的 TLB 之后才能释放
+        let frames = segment
+            .page_range()
+            .iter()
+            .filter_map(|vpn| self.mapped_pairs.remove(&vpn))
+            .collect::<Vec<_>>();
+        self.flush_tlb();
+        drop(frames);
+    }
+
+    /// 将一段按帧映射的 [`Segment`] 共享给 `child`，用于 fork
+    ///
+    /// 父子进程引用同一组物理页面。可写的页面在双方页表中都会被改为只读，
+    /// 直到某一方写入时在 [`Mapping::copy_on_write`] 中复制。
+    pub fn share_segment(&mut self, child: &mut Mapping, segment: &Segment) -> KResult<()> {
+        let flags = if segment.map_type.is_shared() {
+            segment.flags
+        } else {
+            segment.flags - Flags::WRITABLE
+        };
+        for vpn in segment.page_range().iter() {
+            if let Some(frame) = self.mapped_pairs.get(&vpn).cloned() {
+                self.set_entry_flags(vpn, flags)?;
+                child.map_one(vpn, Some(frame.page_number()), flags)?;
+                child.set_entry_flags(vpn, flags)?;
+                child.mapped_pairs.insert(vpn, frame);
+            }
+        }
+        // 子进程的页表还没有被使用过，只需要刷新父进程的
+        self.flush_tlb();
+        Ok(())
+    }
+
+    /// 按照 [`Segment`] 新的权限修改其中已分配页面的页表项，用于 mprotect
+    ///
+    /// 仍与其他进程共享的页面保持只读，写入时再复制
+    pub fn protect(&mut self, segment: &Segment) -> KResult<()> {
+        for vpn in segment.page_range().iter() {
+            let shared = match self.mapped_pairs.get(&vpn) {
+                Some(frame) => Arc::strong_count(frame) > 1,
+                None => continue,
+            };
+            if shared && !segment.map_type.is_shared() {
+                self.set_entry_flags(vpn, segment.flags - Flags::WRITABLE)?;
+            } else {
+                self.set_entry_flags(vpn, segment.flags)?;
+            }
         }
-        // 移除相应的页面
-        self.mapped_pairs
-            .retain(|(vpn, _)| !segment.page_range().contains(*vpn))
+        self.flush_tlb();
+        Ok(())
+    }
+
+    /// 为按需分配的页面分配一个全零的物理页面，并以 `flags` 建立映射
+    pub fn map_zeroed(&mut self, vpn: VirtualPageNumber, flags: Flags) -> KResult<()> {
+        let mut frame = FRAME_ALLOCATOR.lock().alloc()?;
+        (*frame).copy_from_slice(&[0u8; PAGE_SIZE]);
+        self.map_frame(vpn, Arc::new(frame), flags)
+    }
+
+    /// 将一个已有的物理页面以 `flags` 映射到 `vpn`，页面可能与页面缓存或其他进程共享
+    pub fn map_frame(
+        &mut self,
+        vpn: VirtualPageNumber,
+        frame: Arc<FrameTracker>,
+        flags: Flags,
+    ) -> KResult<()> {
+        self.map_one(vpn, Some(frame.page_number()), flags)?;
+        self.mapped_pairs.insert(vpn, frame);
+        Self::flush_local_tlb();
+        Ok(())
+    }
+
+    /// 处理写时复制：令 `vpn` 独占一个物理页面，并以 `flags` 重新写入页表项
+    ///
+    /// 如果页面仍被其他进程共享，则复制一份新的页面；否则直接恢复写权限。
+    /// 复制之后其他处理器核可能仍通过 TLB 读取旧的页面，刷新它们的 TLB 之后才释放对旧页面的引用
+    pub fn copy_on_write(&mut self, vpn: VirtualPageNumber, flags: Flags) -> KResult<()> {
+        let frame = self
+            .mapped_pairs
+            .get_mut(&vpn)
+            .ok_or("page is not mapped")?;
+        let old_frame = if Arc::strong_count(frame) > 1 {
+            let mut new_frame = FRAME_ALLOCATOR.lock().alloc()?;
+            (*new_frame).copy_from_slice(&***frame);
+            Some(core::mem::replace(frame, Arc::new(new_frame)))
+        } else {
+            None
+        };
+        let ppn = frame.page_number();
+        *self.find_entry(vpn)? = PageTableEntry::new(Some(ppn), flags);
+        if old_frame.is_some() {
+            self.flush_tlb();
+        } else {
+            Self::flush_local_tlb();
+        }
+        drop(old_frame);
+        Ok(())
     }
 
     /// 找到给定虚拟页号的三级页表项
@@ -179,6 +296,20 @@ impl Mapping {
         Some(PhysicalAddress(base + offset))
     }
 
+    /// 修改已映射页面的页表项权限
+    ///
+    /// 没有任何 rwx 权限的页面会被标记为无效但保留物理页号，
+    /// 因为 rwx 全为 0 的有效页表项表示指向下一级页表
+    fn set_entry_flags(&mut self, vpn: VirtualPageNumber, flags: Flags) -> KResult<()> {
+        let entry = self.find_entry(vpn)?;
+        if flags.intersects(Flags::READABLE | Flags::WRITABLE | Flags::EXECUTABLE) {
+            entry.set_flags(flags | Flags::VALID);
+        } else {
+            entry.set_flags(flags - Flags::VALID);
+        }
+        Ok(())
+    }
+
     /// 为给定的虚拟 / 物理页号建立映射关系
     fn map_one(
         &mut self,
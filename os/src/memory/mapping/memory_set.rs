@@ -1,5 +1,6 @@
 //! 一个线程中关于内存空间的所有信息 [`MemorySet`]
 //!
+use crate::fs::{INode, PAGE_CACHE};
 use crate::memory::{
     addr::*,
     mapping::{mapping::Mapping, page_table_entry::Flags, segment::MapType, segment::Segment},
@@ -8,7 +9,8 @@ use crate::memory::{
 };
 use crate::KResult;
 extern crate alloc;
-use alloc::{vec, vec::Vec};
+use alloc::{collections::BTreeMap, sync::Arc, vec, vec::Vec};
+use core::cmp::{max, min};
 use xmas_elf::program::{SegmentData, Type};
 use xmas_elf::ElfFile;
 
@@ -18,6 +20,31 @@ pub struct MemorySet {
     pub mapping: Mapping,
     /// 每个字段
     pub segments: Vec<Segment>,
+    /// 用户堆的区间，`end` 即为当前的 program break
+    pub heap: Range<VirtualAddress>,
+    /// 文件映射所对应的文件，以 [`MapType::File`] 中的 `id` 索引
+    pub files: BTreeMap<usize, MappedFile>,
+}
+
+/// 一段文件映射所对应的文件
+#[derive(Clone)]
+pub struct MappedFile {
+    /// 被映射的文件
+    pub inode: Arc<dyn INode>,
+    /// 映射的起始地址
+    pub start: VirtualAddress,
+    /// 起始地址对应的文件偏移量，按页对齐
+    pub offset: usize,
+}
+
+impl MappedFile {
+    /// 虚拟页面对应文件中的页号
+    fn page_index(&self, vpn: VirtualPageNumber) -> KResult<usize> {
+        let offset = (VirtualAddress::from(vpn) - self.start)
+            .checked_add(self.offset)
+            .ok_or("file offset overflows")?;
+        Ok(offset / PAGE_SIZE)
+    }
 }
 
 impl MemorySet {
@@ -70,7 +97,12 @@ impl MemorySet {
         for segment in segments.iter() {
             mapping.map(segment, None)?;
         }
-        Ok(MemorySet { mapping, segments })
+        Ok(MemorySet {
+            mapping,
+            segments,
+            heap: Range::from(0..0),
+            files: BTreeMap::new(),
+        })
     }
 
     /// 通过 elf 文件创建内存映射（不包括栈）
@@ -107,9 +139,122 @@ impl MemorySet {
             memory_set.add_segment(segment, Some(data))?;
         }
 
+        // 堆紧接在 elf 文件最后一个字段之后，初始大小为 0
+        let heap_start = memory_set
+            .segments
+            .iter()
+            .filter(|segment| segment.map_type == MapType::Framed)
+            .map(|segment| VirtualAddress::from(segment.page_range().end))
+            .max()
+            .unwrap_or_default();
+        memory_set.heap = Range::from(heap_start..heap_start);
+
         Ok(memory_set)
     }
 
+    /// 为 fork 复制一份内存映射
+    ///
+    /// 线性映射的部分直接重新建立；按帧映射的部分和子进程共享物理页面，写入时再复制
+    pub fn fork(&mut self) -> KResult<MemorySet> {
+        let mut mapping = Mapping::new()?;
+        for segment in self.segments.iter() {
+            match segment.map_type {
+                MapType::Linear => mapping.map(segment, None)?,
+                MapType::Framed | MapType::File { .. } => {
+                    self.mapping.share_segment(&mut mapping, segment)?
+                }
+            }
+        }
+        Ok(MemorySet {
+            mapping,
+            segments: self.segments.clone(),
+            heap: self.heap,
+            files: self.files.clone(),
+        })
+    }
+
+    /// 处理缺页异常
+    ///
+    /// - 按帧映射但尚未分配的页面：分配一个全零的页面
+    /// - 文件映射中尚未映射的页面：从页面缓存中取得，私有映射在写入时复制
+    /// - 对写时复制页面的写入：复制页面或恢复写权限
+    ///
+    /// 其他情况返回 `Err`，由调用者终止线程
+    pub fn handle_page_fault(&mut self, address: VirtualAddress, is_write: bool) -> KResult<()> {
+        let vpn = VirtualPageNumber::floor(address);
+        let segment = *self
+            .segments
+            .iter()
+            .find(|s| s.page_range().contains(vpn))
+            .ok_or("address is not mapped")?;
+        if segment.map_type == MapType::Linear {
+            return Err("page fault in linear segment");
+        }
+        if !segment
+            .flags
+            .intersects(Flags::READABLE | Flags::WRITABLE | Flags::EXECUTABLE)
+        {
+            return Err("access to inaccessible memory");
+        }
+        if is_write && !segment.flags.contains(Flags::WRITABLE) {
+            return Err("write to read-only memory");
+        }
+        let flags = self.mapping.find_entry(vpn)?.flags();
+        if !flags.contains(Flags::VALID) {
+            match segment.map_type {
+                MapType::File { id, shared } => {
+                    let file = &self.files[&id];
+                    let frame = PAGE_CACHE.lock().get(&file.inode, file.page_index(vpn)?)?;
+                    if shared {
+                        self.mapping.map_frame(vpn, frame, segment.flags)
+                    } else {
+                        // 私有映射先以只读方式共享缓存页面，写入时再复制
+                        self.mapping
+                            .map_frame(vpn, frame, segment.flags - Flags::WRITABLE)?;
+                        if is_write {
+                            self.mapping.copy_on_write(vpn, segment.flags)?;
+                        }
+                        Ok(())
+                    }
+                }
+                _ => self.mapping.map_zeroed(vpn, segment.flags),
+            }
+        } else if !is_write || flags.contains(Flags::WRITABLE) {
+            // 页表项已经允许这次访问：其他处理器核刚刚建立了映射，当前处理器核的 TLB 中仍是旧项
+            Mapping::flush_local_tlb();
+            Ok(())
+        } else if is_write && !segment.map_type.is_shared() {
+            self.mapping.copy_on_write(vpn, segment.flags)
+        } else {
+            Err("access violation")
+        }
+    }
+
+    /// 在内核读写一段用户内存之前，提前处理其中会发生的缺页异常
+    ///
+    /// 中断处理流程中无法再处理缺页异常，所以内核访问用户缓冲区之前必须先调用此函数。
+    /// 区间中的每一页都必须属于带有 `USER` 权限的字段，内核的线性映射虽然在页表中有效，也不允许访问
+    pub fn prepare_access(&mut self, range: Range<VirtualAddress>, is_write: bool) -> KResult<()> {
+        let page_range = Range::<VirtualPageNumber>::from(
+            VirtualPageNumber::floor(range.start)..VirtualPageNumber::ceil(range.end),
+        );
+        for vpn in page_range.iter() {
+            let segment = self
+                .segments
+                .iter()
+                .find(|s| s.page_range().contains(vpn))
+                .ok_or("address is not mapped")?;
+            if segment.map_type == MapType::Linear || !segment.flags.contains(Flags::USER) {
+                return Err("access to kernel memory");
+            }
+            let flags = self.mapping.find_entry(vpn)?.flags();
+            if !flags.contains(Flags::VALID) || (is_write && !flags.contains(Flags::WRITABLE)) {
+                self.handle_page_fault(VirtualAddress::from(vpn), is_write)?;
+            }
+        }
+        Ok(())
+    }
+
     /// 替换 `satp` 以激活页表
     ///
     /// 如果当前页表就是自身，则不会替换，但仍然会刷新 TLB。
@@ -143,6 +288,233 @@ impl MemorySet {
         Ok(())
     }
 
+    /// 移除所有按帧映射和文件映射的 [`Segment`]，释放其物理页面
+    ///
+    /// 共享的文件映射会先写回文件。内核的线性映射保持不变，因此即使页表正在使用也可以调用
+    pub fn clear_framed_segments(&mut self) {
+        self.sync_all();
+        for segment in self.segments.iter() {
+            if segment.map_type != MapType::Linear {
+                self.mapping.unmap(segment);
+            }
+        }
+        self.segments.retain(|s| s.map_type == MapType::Linear);
+        self.files.clear();
+        PAGE_CACHE.lock().shrink();
+    }
+
+    /// 将文件 `inode` 从 `offset` 开始的部分映射到页对齐的区间 `range`，页面按需从页面缓存中取得
+    pub fn add_file_segment(
+        &mut self,
+        range: Range<VirtualAddress>,
+        flags: Flags,
+        inode: Arc<dyn INode>,
+        offset: usize,
+        shared: bool,
+    ) -> KResult<()> {
+        let id = self.files.keys().next_back().map_or(0, |id| id + 1);
+        self.files.insert(
+            id,
+            MappedFile {
+                inode,
+                start: range.start,
+                offset,
+            },
+        );
+        self.add_segment(
+            Segment {
+                map_type: MapType::File { id, shared },
+                range,
+                flags,
+            },
+            None,
+        )
+    }
+
+    /// 将一段页面区间内共享文件映射的页面写回文件，用于 msync
+    pub fn sync_range(&self, range: Range<VirtualPageNumber>) -> KResult<()> {
+        for segment in self.segments.iter() {
+            let page_range = segment.page_range();
+            if !page_range.overlap_with(&range) {
+                continue;
+            }
+            let start = max(page_range.start, range.start);
+            let end = min(page_range.end, range.end);
+            self.sync_segment(&Segment {
+                range: Range::from(VirtualAddress::from(start)..VirtualAddress::from(end)),
+                ..*segment
+            })?;
+        }
+        Ok(())
+    }
+
+    /// 将所有共享文件映射的页面写回文件，出错时继续写回其他页面
+    fn sync_all(&self) {
+        for segment in self.segments.iter() {
+            if let Err(e) = self.sync_segment(segment) {
+                debug_println!("failed to write back mapped file: {}", e);
+            }
+        }
+    }
+
+    /// 如果 `segment` 是共享文件映射，将其中已缓存的页面写回文件
+    fn sync_segment(&self, segment: &Segment) -> KResult<()> {
+        if let MapType::File { id, shared: true } = segment.map_type {
+            let file = &self.files[&id];
+            let page_range = segment.page_range();
+            let start = file.page_index(page_range.start)?;
+            PAGE_CACHE
+                .lock()
+                .writeback(&file.inode, start..start + page_range.len())?;
+        }
+        Ok(())
+    }
+
+    /// 将 program break 设置为 `brk`，用于 brk 系统调用，返回新的 program break
+    ///
+    /// 堆按页扩展或收缩，扩展的页面按需分配；不能低于堆的起始地址，也不能与其他映射重叠
+    pub fn set_brk(&mut self, brk: VirtualAddress, flags: Flags) -> KResult<VirtualAddress> {
+        if brk < self.heap.start {
+            return Err("program break below heap start");
+        }
+        let old_end = VirtualPageNumber::ceil(self.heap.end);
+        let new_end = VirtualPageNumber::ceil(brk);
+        if new_end > old_end {
+            let grown = Range::<VirtualPageNumber>::from(old_end..new_end);
+            if self.overlap_with(grown) {
+                return Err("heap overlaps with other segments");
+            }
+            // 尽量延长原有的堆字段，而不是每次都加入新的字段
+            let heap_start = self.heap.start;
+            let heap_segment = self.segments.iter_mut().find(|segment| {
+                segment.map_type == MapType::Framed
+                    && segment.flags == flags
+                    && segment.range.end == VirtualAddress::from(old_end)
+                    && segment.range.start >= heap_start
+            });
+            match heap_segment {
+                Some(segment) => segment.range.end = VirtualAddress::from(new_end),
+                None => self.add_segment(
+                    Segment {
+                        map_type: MapType::Framed,
+                        range: grown.into(),
+                        flags,
+                    },
+                    None,
+                )?,
+            }
+        } else if new_end < old_end {
+            self.remove_range(Range::from(new_end..old_end))?;
+        }
+        self.heap.end = brk;
+        Ok(brk)
+    }
+
+    /// 移除一段页面区间内的映射，用于 munmap
+    ///
+    /// 与区间部分重叠的 [`Segment`] 会被切分，只保留区间以外的部分
+    pub fn remove_range(&mut self, range: Range<VirtualPageNumber>) -> KResult<()> {
+        let taken = self.take_range(range)?;
+        // 共享的文件映射在移除前写回文件
+        for segment in taken.iter() {
+            if let Err(e) = self.sync_segment(segment) {
+                debug_println!("failed to write back mapped file: {}", e);
+            }
+            self.mapping.unmap(segment);
+        }
+        // 不再被任何字段使用的文件
+        let unused: Vec<usize> = self
+            .files
+            .keys()
+            .filter(|&&id| {
+                !self
+                    .segments
+                    .iter()
+                    .any(|s| matches!(s.map_type, MapType::File { id: i, .. } if i == id))
+            })
+            .copied()
+            .collect();
+        for id in unused {
+            self.files.remove(&id);
+        }
+        PAGE_CACHE.lock().shrink();
+        Ok(())
+    }
+
+    /// 修改一段页面区间内的权限，用于 mprotect
+    ///
+    /// 与区间部分重叠的 [`Segment`] 会被切分，区间内的部分使用新的权限；
+    /// 区间中有未映射的页面时不做任何修改，返回 `Err`
+    pub fn protect_range(&mut self, range: Range<VirtualPageNumber>, flags: Flags) -> KResult<()> {
+        if !self.covers(range) {
+            return Err("range is not fully mapped");
+        }
+        for segment in self.take_range(range)? {
+            let segment = Segment { flags, ..segment };
+            self.mapping.protect(&segment)?;
+            self.segments.push(segment);
+        }
+        Ok(())
+    }
+
+    /// 在 `range` 的边界处切分与之重叠的 [`Segment`]，并将位于区间内的部分移出 `segments`
+    ///
+    /// 内核的线性映射不能被切分
+    fn take_range(&mut self, range: Range<VirtualPageNumber>) -> KResult<Vec<Segment>> {
+        if self
+            .segments
+            .iter()
+            .any(|s| s.map_type == MapType::Linear && s.page_range().overlap_with(&range))
+        {
+            return Err("cannot modify linear segments");
+        }
+        let mut taken = Vec::new();
+        let mut remaining = Vec::with_capacity(self.segments.len());
+        for segment in self.segments.drain(..) {
+            let page_range = segment.page_range();
+            if !page_range.overlap_with(&range) {
+                remaining.push(segment);
+                continue;
+            }
+            let start = max(segment.range.start, VirtualAddress::from(range.start));
+            let end = min(segment.range.end, VirtualAddress::from(range.end));
+            // 区间左侧和右侧剩余的部分
+            if segment.range.start < start {
+                remaining.push(Segment {
+                    range: Range::from(segment.range.start..start),
+                    ..segment
+                });
+            }
+            if end < segment.range.end {
+                remaining.push(Segment {
+                    range: Range::from(end..segment.range.end),
+                    ..segment
+                });
+            }
+            taken.push(Segment {
+                range: Range::from(start..end),
+                ..segment
+            });
+        }
+        self.segments = remaining;
+        Ok(taken)
+    }
+
+    /// 检测一段页面区间是否全部被已有的 [`Segment`] 映射
+    fn covers(&self, range: Range<VirtualPageNumber>) -> bool {
+        // 各个字段互不重叠，重叠部分的页数之和等于区间大小即为全部映射
+        let mapped: usize = self
+            .segments
+            .iter()
+            .map(|segment| segment.page_range())
+            .filter(|page_range| page_range.overlap_with(&range))
+            .map(|page_range| {
+                min(page_range.end, range.end).0 - max(page_range.start, range.start).0
+            })
+            .sum();
+        mapped == range.len()
+    }
+
     /// 检测一段内存区域和已有的是否存在重叠区域
     pub fn overlap_with(&self, range: Range<VirtualPageNumber>) -> bool {
         for seg in self.segments.iter() {
@@ -153,3 +525,10 @@ impl MemorySet {
         false
     }
 }
+
+/// 释放前将共享文件映射写回文件
+impl Drop for MemorySet {
+    fn drop(&mut self) {
+        self.sync_all();
+    }
+}
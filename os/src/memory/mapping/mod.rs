@@ -3,9 +3,11 @@ mod memory_set;
 mod page_table;
 mod page_table_entry;
 mod segment;
+mod stack_region;
 
 pub use mapping::Mapping;
-pub use memory_set::MemorySet;
+pub use memory_set::{MappedFile, MemorySet};
 pub use page_table::{PageTable, PageTableTracker};
 pub use page_table_entry::{Flags, PageTableEntry};
 pub use segment::{MapType, Segment};
+pub use stack_region::{is_guard_page, stack_bottom, StackRegion, STACK_REGION};
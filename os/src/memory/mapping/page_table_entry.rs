@@ -48,6 +48,10 @@ impl PageTableEntry {
                 .set_bits(PAGE_NUMBER_RANGE, 0);
         }
     }
+    /// 替换标志位，保留物理页号
+    pub fn set_flags(&mut self, flags: Flags) {
+        self.0.set_bits(FLAG_RANGE, flags.bits() as usize);
+    }
     /// 清除
     pub fn clear(&mut self) {
         self.0 = 0;
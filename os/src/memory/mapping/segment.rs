@@ -9,6 +9,17 @@ pub enum MapType {
     Linear,
     /// 按帧分配映射
     Framed,
+    /// 文件映射，页面来自页面缓存
+    ///
+    /// `id` 对应 [`MemorySet`](super::MemorySet) 中记录的文件，`shared` 表示写入对其他进程可见
+    File { id: usize, shared: bool },
+}
+
+impl MapType {
+    /// 写入是否对其他进程可见，这样的页面不进行写时复制
+    pub fn is_shared(&self) -> bool {
+        matches!(self, MapType::File { shared: true, .. })
+    }
 }
 
 /// 一个映射片段（对应旧 tutorial 的 `MemoryArea`）
@@ -29,7 +40,7 @@ impl Segment {
             // 线性映射可以直接将虚拟地址转换
             MapType::Linear => Some(self.page_range().into().iter()),
             // 按帧映射无法直接获得物理地址，需要分配
-            MapType::Framed => None,
+            MapType::Framed | MapType::File { .. } => None,
         }
     }
 
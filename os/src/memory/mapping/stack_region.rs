@@ -0,0 +1,159 @@
+//! 内核栈区域 [`StackRegion`]
+//!
+//! 线程的内核栈不从内核堆中分配，而是用单独分配的物理页面映射在虚拟地址空间最高的 1 GB
+//! （根页表的第 511 项）中。区域被划分为大小相同的槽位，每个槽位的低一半不映射，作为保护区：
+//! 内核栈溢出时立即触发缺页异常，而不会改写相邻的内存。
+//!
+//! 槽位大小是 2 的幂，中断入口（`interrupt.asm`）只需检查地址的一位就能判断是否位于保护区，
+//! 溢出时改用单独的栈来处理中断
+//!
+//! 区域中的下级页表由所有页表共享：启动页表和每个新建的根页表的第 511 项都指向同一个页表，
+//! 因此这里建立的映射对所有进程立即可见；移除映射之后则需要刷新所有处理器核的 TLB
+
+use super::page_table::{PageTable, PageTableTracker};
+use super::page_table_entry::{Flags, PageTableEntry};
+use crate::memory::{addr::*, frame::FRAME_ALLOCATOR, frame_tracker::FrameTracker, PAGE_SIZE};
+use crate::process::KERNEL_STACK_SIZE;
+use crate::KResult;
+use alloc::{collections::BTreeMap, vec::Vec};
+use lazy_static::lazy_static;
+use spin::Mutex;
+
+/// 内核栈区域的起始地址
+pub const STACK_REGION_START: usize = 0xffff_ffff_c000_0000;
+
+/// 内核栈区域在根页表中的下标
+const ROOT_INDEX: usize = 511;
+
+/// 每个槽位的大小：与栈同样大小的保护区和大小为 [`KERNEL_STACK_SIZE`] 的栈
+///
+/// 需要与 `interrupt.asm` 中的 `STACK_SLOT_BIT` 一致
+const SLOT_SIZE: usize = 2 * KERNEL_STACK_SIZE;
+
+/// 槽位的数量
+const SLOT_COUNT: usize = (1 << 30) / SLOT_SIZE;
+
+lazy_static! {
+    /// 全局的内核栈区域
+    pub static ref STACK_REGION: Mutex<StackRegion> =
+        Mutex::new(StackRegion::new().expect("failed to create kernel stack region"));
+}
+
+/// 内核栈区域，记录每个槽位映射的物理页面
+pub struct StackRegion {
+    /// 区域对应的二级页表，所有根页表的第 511 项都指向它
+    table: PageTableTracker,
+    /// 区域中用到的三级页表
+    leaf_tables: Vec<PageTableTracker>,
+    /// 正在使用的槽位及其物理页面
+    stacks: BTreeMap<usize, Vec<FrameTracker>>,
+    /// 被释放的槽位
+    free_slots: Vec<usize>,
+    /// 从未使用过的最小槽位
+    next_slot: usize,
+}
+
+impl StackRegion {
+    /// 创建区域的页表，并令启动页表共享它
+    fn new() -> KResult<Self> {
+        extern "C" {
+            /// `entry.asm` 中的启动页表
+            fn boot_page_table();
+        }
+        let region = Self {
+            table: PageTableTracker::new(FRAME_ALLOCATOR.lock().alloc()?),
+            leaf_tables: Vec::new(),
+            stacks: BTreeMap::new(),
+            free_slots: Vec::new(),
+            next_slot: 0,
+        };
+        region.link(unsafe { &mut *(boot_page_table as usize as *mut PageTable) });
+        crate::smp::flush_tlb();
+        Ok(region)
+    }
+
+    /// 令根页表 `root` 共享内核栈区域的映射
+    pub fn link(&self, root: &mut PageTable) {
+        root.entries[ROOT_INDEX] =
+            PageTableEntry::new(Some(self.table.page_number()), Flags::VALID);
+    }
+
+    /// 分配一个内核栈并建立映射，返回其槽位
+    pub fn alloc(&mut self) -> KResult<usize> {
+        let frames = (0..KERNEL_STACK_SIZE / PAGE_SIZE)
+            .map(|_| FRAME_ALLOCATOR.lock().alloc())
+            .collect::<KResult<Vec<FrameTracker>>>()?;
+        let slot = match self.free_slots.pop() {
+            Some(slot) => slot,
+            None if self.next_slot < SLOT_COUNT => {
+                self.next_slot += 1;
+                self.next_slot - 1
+            }
+            None => return Err("too many kernel stacks"),
+        };
+        let bottom = VirtualPageNumber::floor(VirtualAddress(stack_bottom(slot)));
+        for (i, frame) in frames.iter().enumerate() {
+            match self.find_entry(VirtualPageNumber(bottom.0 + i)) {
+                Ok(entry) => {
+                    *entry = PageTableEntry::new(
+                        Some(frame.page_number()),
+                        Flags::READABLE | Flags::WRITABLE,
+                    )
+                }
+                Err(e) => {
+                    // 已经写入的页表项还没有被访问过，直接清除即可
+                    for j in 0..i {
+                        self.find_entry(VirtualPageNumber(bottom.0 + j))?.clear();
+                    }
+                    self.free_slots.push(slot);
+                    return Err(e);
+                }
+            }
+        }
+        self.stacks.insert(slot, frames);
+        Ok(slot)
+    }
+
+    /// 移除一个内核栈的映射并释放其物理页面
+    ///
+    /// 刷新所有处理器核的 TLB 之后才释放物理页面，以免其他处理器核通过旧的页表项访问
+    pub fn dealloc(&mut self, slot: usize) {
+        let frames = self
+            .stacks
+            .remove(&slot)
+            .expect("kernel stack to free cannot be found");
+        let bottom = VirtualPageNumber::floor(VirtualAddress(stack_bottom(slot)));
+        for i in 0..frames.len() {
+            self.find_entry(VirtualPageNumber(bottom.0 + i))
+                .unwrap()
+                .clear();
+        }
+        crate::smp::flush_tlb();
+        drop(frames);
+        self.free_slots.push(slot);
+    }
+
+    /// 找到区域中虚拟页号对应的三级页表项，没有三级页表时分配
+    fn find_entry(&mut self, vpn: VirtualPageNumber) -> KResult<&mut PageTableEntry> {
+        let [root_index, middle_index, leaf_index] = vpn.levels();
+        assert_eq!(root_index, ROOT_INDEX);
+        let entry = &mut self.table.entries[middle_index];
+        if entry.is_empty() {
+            let new_table = PageTableTracker::new(FRAME_ALLOCATOR.lock().alloc()?);
+            *entry = PageTableEntry::new(Some(new_table.page_number()), Flags::VALID);
+            self.leaf_tables.push(new_table);
+        }
+        Ok(&mut entry.get_next_table().entries[leaf_index])
+    }
+}
+
+/// 槽位中栈的最低地址，其下方为保护区
+pub fn stack_bottom(slot: usize) -> usize {
+    STACK_REGION_START + slot * SLOT_SIZE + SLOT_SIZE - KERNEL_STACK_SIZE
+}
+
+/// 地址是否位于某个内核栈的保护区中
+pub fn is_guard_page(address: usize) -> bool {
+    address >= STACK_REGION_START
+        && (address - STACK_REGION_START) % SLOT_SIZE < SLOT_SIZE - KERNEL_STACK_SIZE
+}
@@ -15,6 +15,11 @@ pub const PAGE_SIZE: usize = 4096;
 /// 内核使用线性映射的偏移量
 pub const KERNEL_MAP_OFFSET: usize = 0xffff_ffff_0000_0000;
 
+/// 用户地址空间的上界，即 Sv39 低半部分的末尾
+///
+/// 线程栈和 mmap 从这里向下分配，堆从 elf 文件末尾向上增长，二者之间留有足够的空间
+pub const USER_SPACE_END: usize = 0x40_0000_0000;
+
 lazy_static! {
     /// 内核代码结束的地址，即可以用来分配的内存起始地址
     ///
@@ -1,12 +1,16 @@
 use core::cmp::Ordering;
 
 use lazy_static::*;
+use lib_redos::{ProcessID, EINTR};
+use riscv::register::time;
 
+use crate::interrupt::timer::{self, nanos_to_ticks, timebase_frequency};
 use crate::kernel::SyscallResult;
 use crate::process::lock::Lock;
+use crate::process::processor::{schedule, wake_thread};
 use crate::process::thread::Thread;
-use crate::process::thread::ThreadState::Dead;
 use crate::process::PROCESSOR;
+use crate::KResult;
 
 use super::alloc::collections::BinaryHeap;
 use super::alloc::sync::Arc;
@@ -18,11 +22,12 @@ lazy_static! {
 
 struct ThreadWithAlarmTime {
     thread: Arc<Thread>,
-    alarm_time: u64,
+    /// 唤醒的时刻，为 `time` 寄存器的值
+    alarm_time: usize,
 }
 
 impl ThreadWithAlarmTime {
-    fn new(thread: Arc<Thread>, alarm_time: u64) -> ThreadWithAlarmTime {
+    fn new(thread: Arc<Thread>, alarm_time: usize) -> ThreadWithAlarmTime {
         ThreadWithAlarmTime { thread, alarm_time }
     }
 }
@@ -49,36 +54,87 @@ impl Ord for ThreadWithAlarmTime {
 
 #[derive(Default)]
 pub struct AlarmClock {
-    clock: u64,
     /// 在某一时刻需要唤醒的线程
     alarm_threads: BinaryHeap<ThreadWithAlarmTime>,
 }
 
 impl AlarmClock {
-    pub fn put_current_thread_to_alarm_threads(&mut self, sec: u64) {
-        let current_thread = PROCESSOR.lock().sleep_current_thread();
+    /// 令当前线程休眠，`time` 寄存器到达 `deadline` 时唤醒；线程已经被要求终止时返回 `Err`
+    ///
+    /// 期限早于当前处理器核预约的时钟中断时，重新预约
+    pub fn put_current_thread_to_alarm_threads(&mut self, deadline: usize) -> KResult<()> {
+        let current_thread = PROCESSOR.lock().sleep_current_thread()?;
         self.alarm_threads
-            .push(ThreadWithAlarmTime::new(current_thread, self.clock + sec))
+            .push(ThreadWithAlarmTime::new(current_thread, deadline));
+        timer::set_next_timeout(self.next_deadline());
+        Ok(())
     }
 
-    /// 每隔1秒查看`alarm_threads`中有没有需要唤醒的线程
-    pub fn alarm(&mut self) {
-        self.clock += 1;
+    /// 唤醒 `alarm_threads` 中已经到期的线程，返回最近的下一个期限
+    pub fn alarm(&mut self) -> Option<usize> {
+        let now = time::read();
         while let Some(thread) = self.alarm_threads.peek() {
-            if thread.alarm_time <= self.clock {
-                if thread.thread.inner().state != Dead {
-                    let t = self.alarm_threads.pop().unwrap();
-                    PROCESSOR.lock().wake_thread(t.thread);
-                }
+            if thread.alarm_time <= now {
+                let t = self.alarm_threads.pop().unwrap();
+                // 已经结束的线程不会被唤醒，直接丢弃
+                wake_thread(&t.thread);
             } else {
-                return;
+                break;
             }
         }
+        self.next_deadline()
     }
+
+    /// 最近的一个期限
+    pub fn next_deadline(&self) -> Option<usize> {
+        self.alarm_threads.peek().map(|t| t.alarm_time)
+    }
+
+    /// 移除被提前唤醒的线程
+    pub fn remove_thread(&mut self, thread: &Arc<Thread>) {
+        let alarm_threads = core::mem::take(&mut self.alarm_threads);
+        self.alarm_threads = alarm_threads
+            .into_iter()
+            .filter(|t| !Arc::ptr_eq(&t.thread, thread))
+            .collect();
+    }
+
+    /// 移除某个进程的所有线程，用于进程退出
+    pub fn remove_process(&mut self, pid: ProcessID) {
+        let alarm_threads = core::mem::take(&mut self.alarm_threads);
+        self.alarm_threads = alarm_threads
+            .into_iter()
+            .filter(|t| t.thread.process.pid != pid)
+            .collect();
+    }
+}
+
+/// 休眠到 `time` 寄存器到达 `deadline`，线程被要求终止时提前返回 `-EINTR`
+fn sleep_until(deadline: usize) -> SyscallResult {
+    if ALARM
+        .lock()
+        .put_current_thread_to_alarm_threads(deadline)
+        .is_err()
+    {
+        return SyscallResult::Proceed(-EINTR);
+    }
+    // 释放 ALARM 的锁之后再切换，被唤醒时从这里继续
+    schedule();
+    // 被 kill_thread 提前唤醒时，线程仍在等待队列中
+    let current_thread = PROCESSOR.lock().current_thread();
+    if current_thread.inner().killed {
+        ALARM.lock().remove_thread(&current_thread);
+        return SyscallResult::Proceed(-EINTR);
+    }
+    SyscallResult::Proceed(0)
+}
+
+/// 休眠 `sec` 秒，线程被要求终止时提前返回 `-EINTR`
+pub(crate) fn sys_sleep(sec: usize) -> SyscallResult {
+    sleep_until(time::read().saturating_add(sec.saturating_mul(timebase_frequency())))
 }
 
-/// `context`: 当前线程的上下文
-pub(crate) fn sys_sleep(sec: u64) -> SyscallResult {
-    ALARM.lock().put_current_thread_to_alarm_threads(sec);
-    SyscallResult::Park(0)
+/// 休眠 `nanos` 纳秒，精度为 `time` 寄存器的计数；线程被要求终止时提前返回 `-EINTR`
+pub(crate) fn sys_nanosleep(nanos: usize) -> SyscallResult {
+    sleep_until(time::read().saturating_add(nanos_to_ticks(nanos)))
 }
@@ -3,7 +3,9 @@
 extern crate alloc;
 
 use crate::kernel::*;
+use crate::process::processor::{schedule, wake_thread};
 use crate::process::thread::Thread;
+use crate::KResult;
 use alloc::collections::VecDeque;
 use alloc::sync::Arc;
 use core::option::Option::Some;
@@ -16,24 +18,52 @@ pub struct Condvar {
 }
 
 impl Condvar {
-    /// 令当前线程休眠，等待此条件变量
-    pub fn wait(&self) {
-        let thread = PROCESSOR.lock().sleep_current_thread();
-        self.watchers.lock().push_back(thread);
+    /// 条件成立时令当前线程休眠，被唤醒后重新检查，直到条件不成立
+    ///
+    /// 条件在持有等待队列的锁时检查，因此检查之后的唤醒不会被错过。
+    /// 线程已经被要求终止时不再休眠，返回 `Err`
+    pub fn wait_while(&self, mut condition: impl FnMut() -> bool) -> KResult<()> {
+        loop {
+            let thread = {
+                let mut guard = self.watchers.lock();
+                if !condition() {
+                    return Ok(());
+                }
+                let thread = PROCESSOR.lock().sleep_current_thread()?;
+                guard.push_back(thread.clone());
+                thread
+            };
+            // 释放等待队列的锁之后再切换，被唤醒时从这里继续
+            schedule();
+            // 被 kill_thread 直接唤醒时仍在等待队列中，移除以免线程结束后仍被持有
+            if thread.inner().killed {
+                self.watchers
+                    .lock()
+                    .retain(|watcher| !Arc::ptr_eq(watcher, &thread));
+            }
+        }
     }
 
     /// 唤起一个等待此条件变量的线程
+    ///
+    /// 等待期间已经被唤醒或终止的线程会被直接移除
     pub fn notify_one(&self) {
-        if let Some(thread) = self.watchers.lock().pop_front() {
-            PROCESSOR.lock().wake_thread(thread);
+        loop {
+            let thread = match self.watchers.lock().pop_front() {
+                Some(thread) => thread,
+                None => return,
+            };
+            if wake_thread(&thread) {
+                return;
+            }
         }
     }
 
+    /// 唤起所有等待此条件变量的线程
     pub fn notify_all(&self) {
-        let mut guard = self.watchers.lock();
-        let mut processor_guard = PROCESSOR.lock();
-        while let Some(t) = guard.pop_front() {
-            processor_guard.wake_thread(t);
+        let threads = core::mem::take(&mut *self.watchers.lock());
+        for thread in threads.iter() {
+            wake_thread(thread);
         }
     }
 }
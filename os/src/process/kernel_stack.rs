@@ -1,41 +1,89 @@
 //! 内核栈 [`KernelStack`]
 //!
 //! 用户态的线程出现中断时，因为用户栈无法保证可用性，中断处理流程必须在内核栈上进行。
-//! 所以我们创建一个公用的内核栈，即当发生中断时，会将 Context 写到内核栈顶。
+//! 每个线程有自己的内核栈，线程可以在内核中休眠，此时它的内核栈保留着系统调用执行到一半的状态。
 //!
 //! ### 线程 [`Context`] 的存放
-//! > 1. 线程初始化时，一个 `Context` 放置在内核栈顶，`sp` 指向 `Context` 的位置
-//! >   （即栈顶 - `size_of::<Context>()`）
+//! > 1. 线程初始化时，一个 `Context` 放置在内核栈顶
 //! > 2. 切换到线程，执行 `__restore` 时，将 `Context` 的数据恢复到寄存器中后，
 //! >   会将 `Context` 出栈（即 `sp += size_of::<Context>()`），
-//! >   然后保存 `sp` 至 `sscratch`（此时 `sscratch` 即为内核栈顶）
-//! > 3. 发生中断时，将 `sscratch` 和 `sp` 互换，入栈一个 `Context` 并保存数据
+//! >   然后返回用户态时保存 `sp` 至 `sscratch`（此时 `sscratch` 即为内核栈顶），返回内核态时 `sscratch` 为 0
+//! > 3. 从用户态进入中断时，将 `sscratch` 和 `sp` 互换，入栈一个 `Context` 并保存数据；
+//! >   从内核态进入中断时直接在当前的栈上入栈
 //!
-//! 容易发现，线程的 `Context` 一定保存在内核栈顶。因此，当线程需要运行时，
-//! 从 [`Thread`] 中取出 `Context` 然后置于内核栈顶即可
+//! 容易发现，线程的 `Context` 一定保存在它的内核栈顶，不需要在切换线程时复制。
+//!
+//! 内核栈最顶端保留 [`HART_SLOT_SIZE`] 字节存放处理器核的编号，中断入口从这里恢复 `tp`，
+//! `Context` 放在它的下方。内核栈映射在 [`STACK_REGION`] 中，下方是不映射的保护区，
+//! 栈溢出时立即触发缺页异常
 
 use super::*;
 use crate::interrupt::context::Context;
+use crate::memory::mapping::{stack_bottom, STACK_REGION};
+use crate::smp::hart_id;
+use crate::KResult;
 use core::mem::size_of;
+use riscv::register::sstatus::SPP;
 
-/// 内核栈
-#[repr(align(16))]
-#[repr(C)]
-pub struct KernelStack([u8; KERNEL_STACK_SIZE]);
+/// 内核栈顶保留的空间，存放处理器核的编号
+const HART_SLOT_SIZE: usize = 16;
 
-/// 公用的内核栈
-pub static mut KERNEL_STACK: KernelStack = KernelStack([0; KERNEL_STACK_SIZE]);
+/// 线程的内核栈，占用 [`STACK_REGION`] 中的一个槽位
+pub struct KernelStack {
+    /// 在内核栈区域中的槽位
+    slot: usize,
+}
 
 impl KernelStack {
-    /// 在栈顶加入 Context 并且返回新的栈顶指针
-    pub fn push_context(&mut self, context: Context) -> *mut Context {
-        // 栈顶
-        let stack_top = &self.0 as *const _ as usize + size_of::<Self>();
-        // Context 的位置
-        let push_address = (stack_top - size_of::<Context>()) as *mut Context;
+    /// 分配一个内核栈，并将 `context` 放在栈顶
+    pub fn new(context: Context) -> KResult<Self> {
+        let stack = Self {
+            slot: STACK_REGION.lock().alloc()?,
+        };
+        unsafe { *stack.context() = context };
+        Ok(stack)
+    }
+
+    /// 栈顶的 Context 的位置
+    pub fn context(&self) -> *mut Context {
+        (self.top() - HART_SLOT_SIZE - size_of::<Context>()) as *mut Context
+    }
+
+    /// 在切换到线程之前，为当前处理器核准备内核栈
+    ///
+    /// 写入处理器核的编号；内核线程可能换到了另一个处理器核上执行，需要更新其 Context 中的 `tp`
+    pub fn prepare(&self) {
+        let hart_slot = (self.top() - HART_SLOT_SIZE) as *mut usize;
         unsafe {
-            *push_address = context;
+            *hart_slot = hart_id();
+            let context = &mut *self.context();
+            if matches!(context.sstatus.spp(), SPP::Supervisor) {
+                context.x[4] = hart_id();
+            }
         }
-        push_address
+    }
+
+    /// 栈底
+    fn bottom(&self) -> usize {
+        stack_bottom(self.slot)
+    }
+
+    /// 栈顶
+    fn top(&self) -> usize {
+        self.bottom() + KERNEL_STACK_SIZE
+    }
+}
+
+/// 释放时移除映射并归还物理页面
+impl Drop for KernelStack {
+    fn drop(&mut self) {
+        STACK_REGION.lock().dealloc(self.slot);
+    }
+}
+
+/// 打印内核栈的地址范围
+impl core::fmt::Debug for KernelStack {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(formatter, "{:#x}..{:#x}", self.bottom(), self.top())
     }
 }
@@ -1,5 +1,7 @@
-//! 一个关闭中断的互斥锁 [`Lock`]
+//! 一个关闭中断的互斥锁 [`Lock`]，以及每个处理器核各有一份的 [`PerHart`]
 
+use super::alloc::vec::Vec;
+use crate::smp::{hart_id, MAX_HARTS};
 use spin::{Mutex, MutexGuard};
 
 /// 关闭中断的互斥锁
@@ -14,6 +16,20 @@ pub struct LockGuard<'a, T> {
     sstatus: usize,
 }
 
+/// 关闭中断，返回关中断前的 sstatus
+fn disable_interrupts() -> usize {
+    let sstatus: usize;
+    unsafe {
+        llvm_asm!("csrrci $0, sstatus, 1 << 1" : "=r"(sstatus) ::: "volatile");
+    }
+    sstatus
+}
+
+/// 按照关中断前的 sstatus 恢复中断
+fn restore_interrupts(sstatus: usize) {
+    unsafe { llvm_asm!("csrs sstatus, $0" :: "r"(sstatus & 2) :: "volatile") };
+}
+
 impl<T> Lock<T> {
     /// 创建一个新对象
     pub fn new(obj: T) -> Self {
@@ -22,10 +38,27 @@ impl<T> Lock<T> {
 
     /// 获得上锁的对象
     pub fn lock(&self) -> LockGuard<'_, T> {
-        let sstatus: usize;
-        unsafe {
-            llvm_asm!("csrrci $0, sstatus, 1 << 1" : "=r"(sstatus) ::: "volatile");
+        let sstatus = disable_interrupts();
+        self.lock_with(sstatus)
+    }
+
+    /// 尝试获得上锁的对象，已经被占用时返回 `None`
+    pub fn try_lock(&self) -> Option<LockGuard<'_, T>> {
+        let sstatus = disable_interrupts();
+        match self.0.try_lock() {
+            Some(guard) => Some(LockGuard {
+                guard: Some(guard),
+                sstatus,
+            }),
+            None => {
+                restore_interrupts(sstatus);
+                None
+            }
         }
+    }
+
+    /// 在已经关闭中断的情况下上锁，`sstatus` 为关中断前的值
+    fn lock_with(&self, sstatus: usize) -> LockGuard<'_, T> {
         LockGuard {
             guard: Some(self.0.lock()),
             sstatus,
@@ -37,7 +70,7 @@ impl<T> Lock<T> {
 impl<'a, T> Drop for LockGuard<'a, T> {
     fn drop(&mut self) {
         self.guard.take();
-        unsafe { llvm_asm!("csrs sstatus, $0" :: "r"(self.sstatus & 2) :: "volatile") };
+        restore_interrupts(self.sstatus);
     }
 }
 
@@ -53,3 +86,31 @@ impl<'a, T> core::ops::DerefMut for LockGuard<'a, T> {
         self.guard.as_mut().unwrap().deref_mut()
     }
 }
+
+/// 每个处理器核各有一份的对象，各自由一个关闭中断的互斥锁保护
+pub struct PerHart<T>(Vec<Lock<T>>);
+
+impl<T> PerHart<T> {
+    /// 用 `f(处理器核编号)` 为每个处理器核创建对象
+    pub fn new(f: impl Fn(usize) -> T) -> Self {
+        Self((0..MAX_HARTS).map(|hart| Lock::new(f(hart))).collect())
+    }
+
+    /// 获得当前处理器核的对象
+    ///
+    /// 先关闭中断再读取处理器核编号，因此不会在两者之间被换到其他处理器核上
+    pub fn lock(&self) -> LockGuard<'_, T> {
+        let sstatus = disable_interrupts();
+        self.0[hart_id()].lock_with(sstatus)
+    }
+
+    /// 获得编号为 `hart` 的处理器核的对象
+    pub fn lock_hart(&self, hart: usize) -> LockGuard<'_, T> {
+        self.0[hart].lock()
+    }
+
+    /// 尝试获得编号为 `hart` 的处理器核的对象，已经被占用时返回 `None`
+    pub fn try_lock_hart(&self, hart: usize) -> Option<LockGuard<'_, T>> {
+        self.0[hart].try_lock()
+    }
+}
@@ -1,4 +1,5 @@
 pub use processor::PROCESSOR;
+pub use scheduler::SchedulerKind;
 
 pub mod alarm;
 pub mod condvar;
@@ -7,13 +8,21 @@ mod lock;
 pub mod mutex;
 pub mod process;
 pub mod processor;
-mod scheduler;
+pub mod scheduler;
+pub mod semaphore;
+pub mod signal;
+mod switch;
 pub mod thread;
 
 extern crate alloc;
 
 /// 每个线程的运行栈大小 512 KB
+///
+/// 栈的物理页面在第一次访问时才会分配
 pub const STACK_SIZE: usize = 0x8_0000;
 
-/// 共用的内核栈大小 512 KB
-pub const KERNEL_STACK_SIZE: usize = 0x8_0000;
+/// 每个进程最多打开的文件描述符个数
+pub const MAX_DESCRIPTORS: usize = 256;
+
+/// 每个线程的内核栈大小 32 KB
+pub const KERNEL_STACK_SIZE: usize = 0x8000;
@@ -1,71 +1,72 @@
+use core::mem::size_of;
 use core::sync::atomic::{AtomicIsize, Ordering};
 
-use super::alloc::collections::VecDeque;
 use super::alloc::sync::Arc;
 use crate::kernel::SyscallResult;
-use crate::process::thread::Thread;
-use crate::process::thread::ThreadState::Sleeping;
+use crate::process::condvar::Condvar;
 use crate::process::PROCESSOR;
-use lib_redos::MutexID;
+use lib_redos::{MutexID, EINTR};
 
 static NO_OWNER: isize = -123;
 
 /// 通过阻塞其它线程实现的互斥锁
 pub struct Mutex {
-    queue: VecDeque<Arc<Thread>>,
+    /// 等待互斥锁的线程
+    waiters: Condvar,
     owner_thread_id: AtomicIsize,
 }
 
 impl Default for Mutex {
     fn default() -> Self {
         Mutex {
-            queue: VecDeque::with_capacity(2),
+            waiters: Condvar::default(),
             owner_thread_id: AtomicIsize::new(NO_OWNER),
         }
     }
 }
 
 impl Mutex {
-    pub fn lock(&mut self) -> SyscallResult {
-        let mut processor_guard = PROCESSOR.lock();
-        let current_thread = processor_guard.current_thread();
-
-        let res = self.owner_thread_id.compare_exchange(
-            NO_OWNER,
-            current_thread.id,
-            Ordering::Acquire,
-            Ordering::Relaxed,
-        );
-        match res {
-            Ok(old) => {
-                debug_assert_eq!(old, NO_OWNER);
-                SyscallResult::Proceed(0)
+    /// 获取互斥锁，被占用时休眠，被唤醒后重新尝试获取
+    pub fn lock(&self) -> SyscallResult {
+        let current_thread = PROCESSOR.lock().current_thread();
+        loop {
+            let res = self.owner_thread_id.compare_exchange(
+                NO_OWNER,
+                current_thread.id,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            );
+            if res.is_ok() {
+                return SyscallResult::Proceed(0);
             }
-            Err(_) => {
-                self.queue.push_back(current_thread);
-                processor_guard.sleep_current_thread();
-                SyscallResult::Park(0)
+            let waited = self
+                .waiters
+                .wait_while(|| self.owner_thread_id.load(Ordering::Relaxed) != NO_OWNER);
+            if waited.is_err() {
+                return SyscallResult::Proceed(-EINTR);
             }
         }
     }
 
-    pub fn unlock(&mut self) -> SyscallResult {
+    /// 释放互斥锁，唤醒一个等待的线程
+    pub fn unlock(&self) -> SyscallResult {
         self.owner_thread_id.store(NO_OWNER, Ordering::Release);
-        let mut processor_guard = PROCESSOR.lock();
-        while let Some(t) = self.queue.pop_front() {
-            if t.inner().state == Sleeping {
-                processor_guard.wake_thread(t);
-                break;
-            }
-        }
-        SyscallResult::Park(0)
+        self.waiters.notify_one();
+        SyscallResult::Proceed(0)
     }
 }
 
 pub(crate) fn sys_mutex_create(mutex_id: *mut MutexID) -> SyscallResult {
+    let current_thread = PROCESSOR.lock().current_thread();
+    let prepared =
+        current_thread
+            .process
+            .prepare_user_access(mutex_id as usize, size_of::<MutexID>(), true);
+    if prepared.is_err() {
+        return SyscallResult::Proceed(-1);
+    }
     match unsafe { mutex_id.as_mut() } {
         Some(m) => {
-            let current_thread = PROCESSOR.lock().current_thread();
             let mid: MutexID = current_thread.process.create_mutex();
 
             *m = mid;
@@ -76,34 +77,46 @@ pub(crate) fn sys_mutex_create(mutex_id: *mut MutexID) -> SyscallResult {
 }
 
 pub(crate) fn sys_mutex_lock(mutex_id: *const MutexID) -> SyscallResult {
-    if let Some(m) = unsafe { mutex_id.as_ref() } {
-        let current_thread = PROCESSOR.lock().current_thread();
-        let mut guard = current_thread.process.inner();
-        if let Some(mu) = guard.mutex_queue.get_mut(m) {
-            return mu.lock();
-        }
+    match find_mutex(mutex_id) {
+        Some(mutex) => mutex.lock(),
+        None => SyscallResult::Proceed(-1),
     }
-    SyscallResult::Proceed(-1)
 }
 
 pub(crate) fn sys_mutex_unlock(mutex_id: *const MutexID) -> SyscallResult {
-    if let Some(m) = unsafe { mutex_id.as_ref() } {
-        let current_thread = PROCESSOR.lock().current_thread();
-        let mut guard = current_thread.process.inner();
-        if let Some(mu) = guard.mutex_queue.get_mut(m) {
-            return mu.unlock();
-        }
+    match find_mutex(mutex_id) {
+        Some(mutex) => mutex.unlock(),
+        None => SyscallResult::Proceed(-1),
     }
-    SyscallResult::Proceed(-1)
 }
 
 pub(crate) fn sys_mutex_destroy(mutex_id: *const MutexID) -> SyscallResult {
-    if let Some(m) = unsafe { mutex_id.as_ref() } {
+    if let Some(m) = read_mutex_id(mutex_id) {
         let current_thread = PROCESSOR.lock().current_thread();
         let mut guard = current_thread.process.inner();
-        if guard.mutex_queue.remove(m).is_some() {
+        if guard.mutex_queue.remove(&m).is_some() {
             return SyscallResult::Proceed(0);
         }
     }
     SyscallResult::Proceed(-1)
 }
+
+/// 从用户内存中读取互斥锁 ID，找到当前进程中对应的互斥锁
+///
+/// 返回互斥锁的引用，以便在休眠前释放进程的锁
+fn find_mutex(mutex_id: *const MutexID) -> Option<Arc<Mutex>> {
+    let m = read_mutex_id(mutex_id)?;
+    let current_thread = PROCESSOR.lock().current_thread();
+    let mutex = current_thread.process.inner().mutex_queue.get(&m).cloned();
+    mutex
+}
+
+/// 从用户内存中读取互斥锁 ID
+fn read_mutex_id(mutex_id: *const MutexID) -> Option<MutexID> {
+    let current_thread = PROCESSOR.lock().current_thread();
+    current_thread
+        .process
+        .prepare_user_access(mutex_id as usize, size_of::<MutexID>(), false)
+        .ok()?;
+    unsafe { mutex_id.as_ref() }.copied()
+}
@@ -4,71 +4,403 @@ extern crate alloc;
 
 use crate::fs::stdin::STDIN;
 use crate::fs::INode;
+use crate::fs::ROOT_INODE;
 use crate::fs::STDOUT;
+use crate::fs::{FileDescriptor, OpenFile};
 use crate::kernel::thread::ThreadID;
 use crate::memory::addr::VirtualAddress;
 use crate::memory::mapping::{Flags, MapType, MemorySet, Segment};
 use crate::memory::range::Range;
-use crate::memory::PAGE_SIZE;
+use crate::memory::{PAGE_SIZE, USER_SPACE_END};
+use crate::process::alarm::ALARM;
+use crate::process::condvar::Condvar;
+use crate::process::processor::kill_thread;
+use crate::process::semaphore::Semaphore;
 use crate::process::thread::Thread;
+use crate::process::thread::ThreadState::Dead;
+use crate::process::{MAX_DESCRIPTORS, PROCESSOR};
 use crate::KResult;
+use alloc::collections::BTreeMap;
 use alloc::sync::Weak;
 use alloc::{vec, vec::Vec};
+use core::sync::atomic::{AtomicIsize, Ordering};
 use hashbrown::HashMap;
-use lib_redos::MutexID;
+use lazy_static::*;
+use lib_redos::{MutexID, ProcessID, SemID, SigAction, NSIG, O_RDONLY, O_WRONLY, SIG_IGN};
 use spin::Mutex;
 use xmas_elf::ElfFile;
 
 use super::alloc::sync::Arc;
 
+/// 进程计数，用于设置进程 ID
+static PROCESS_COUNTER: AtomicIsize = AtomicIsize::new(0);
+
+lazy_static! {
+    /// init 进程，父进程退出后，其子进程会交给 init 进程回收
+    static ref INIT_PROCESS: Mutex<Weak<Process>> = Mutex::new(Weak::new());
+    /// 前台进程，控制台的 Ctrl-C 会向它发送 `SIGINT`
+    static ref FOREGROUND_PROCESS: Mutex<Weak<Process>> = Mutex::new(Weak::new());
+    /// 所有尚未被释放的进程，用于按进程 ID 查找
+    static ref PROCESSES: Mutex<BTreeMap<ProcessID, Weak<Process>>> = Mutex::new(BTreeMap::new());
+}
+
+/// 设置 init 进程
+pub fn set_init_process(process: &Arc<Process>) {
+    *INIT_PROCESS.lock() = Arc::downgrade(process);
+}
+
+/// 设置前台进程
+pub fn set_foreground_process(process: &Arc<Process>) {
+    *FOREGROUND_PROCESS.lock() = Arc::downgrade(process);
+}
+
+/// 获取前台进程
+pub fn foreground_process() -> Option<Arc<Process>> {
+    FOREGROUND_PROCESS.lock().upgrade()
+}
+
+/// 按进程 ID 查找进程，包括已经退出但尚未被回收的进程
+pub fn find_process(pid: ProcessID) -> Option<Arc<Process>> {
+    PROCESSES.lock().get(&pid).and_then(Weak::upgrade)
+}
+
 /// 进程的信息
 pub struct Process {
+    /// 进程 ID
+    pub pid: ProcessID,
     /// 是否属于用户态
     pub is_user: bool,
     /// 用 `Mutex` 包装一些可变的变量
     pub inner: Mutex<ProcessInner>,
+    /// 等待子进程退出的线程
+    pub child_exit: Condvar,
+    /// 进程被暂停时，等待 `SIGCONT` 的线程
+    pub continued: Condvar,
 }
 
 pub struct ProcessInner {
     /// 进程中的线程公用页表 / 内存映射
     pub memory_set: MemorySet,
-    /// 打开的文件描述符
-    pub descriptors: Vec<Arc<dyn INode>>,
+    /// 打开的文件描述符，关闭的描述符为 `None`
+    pub descriptors: Vec<Option<FileDescriptor>>,
+    /// 当前工作目录，相对路径从这里开始解析
+    pub cwd: Arc<dyn INode>,
     pub threads: HashMap<ThreadID, Weak<Thread>>,
-    pub mutex_queue: HashMap<MutexID, super::mutex::Mutex>,
+    pub mutex_queue: HashMap<MutexID, Arc<super::mutex::Mutex>>,
     next_mutex_id: MutexID,
+    /// 进程中的计数信号量
+    pub sem_table: HashMap<SemID, Arc<Semaphore>>,
+    next_sem_id: SemID,
+    /// 父进程，由内核直接创建的进程没有父进程
+    pub parent: Weak<Process>,
+    /// 子进程，包括已经退出但尚未被回收的子进程
+    pub children: Vec<Arc<Process>>,
+    /// 进程的返回值，为 `Some` 时表示进程已经退出，等待父进程回收（僵尸进程）
+    pub exit_code: Option<isize>,
+    /// 每个信号的处理方式，由进程中的所有线程共享
+    pub signal_actions: [SigAction; NSIG],
+    /// 进程是否被暂停，被暂停的进程中的线程会在回到用户态之前休眠
+    pub stopped: bool,
+    /// 是否已经有线程调用了 [`Process::exit_group`]
+    exiting: bool,
 }
 
 #[allow(unused)]
 impl Process {
     /// 创建一个内核进程
     pub fn new_kernel() -> KResult<Arc<Self>> {
-        Ok(Arc::new(Self {
+        Ok(Self::register(Self {
+            pid: Self::next_pid(),
             is_user: false,
             inner: Mutex::new(ProcessInner {
                 memory_set: MemorySet::new_kernel()?,
-                descriptors: vec![STDIN.clone(), STDOUT.clone()],
+                descriptors: standard_descriptors(),
+                cwd: ROOT_INODE.clone(),
                 threads: HashMap::default(),
                 mutex_queue: HashMap::default(),
                 next_mutex_id: 0,
+                sem_table: HashMap::default(),
+                next_sem_id: 0,
+                parent: Weak::new(),
+                children: Vec::new(),
+                exit_code: None,
+                signal_actions: [SigAction::default(); NSIG],
+                stopped: false,
+                exiting: false,
             }),
+            child_exit: Condvar::default(),
+            continued: Condvar::default(),
         }))
     }
 
     /// 创建进程，从文件中读取代码
     pub fn from_elf(file: &ElfFile, is_user: bool) -> KResult<Arc<Self>> {
-        Ok(Arc::new(Process {
+        Ok(Self::register(Process {
+            pid: Self::next_pid(),
             is_user,
             inner: Mutex::new(ProcessInner {
                 memory_set: MemorySet::from_elf(file, is_user)?,
-                descriptors: vec![STDIN.clone(), STDOUT.clone()],
+                descriptors: standard_descriptors(),
+                cwd: ROOT_INODE.clone(),
                 threads: HashMap::default(),
                 mutex_queue: HashMap::default(),
                 next_mutex_id: 0,
+                sem_table: HashMap::default(),
+                next_sem_id: 0,
+                parent: Weak::new(),
+                children: Vec::new(),
+                exit_code: None,
+                signal_actions: [SigAction::default(); NSIG],
+                stopped: false,
+                exiting: false,
+            }),
+            child_exit: Condvar::default(),
+            continued: Condvar::default(),
+        }))
+    }
+
+    /// 复制当前进程，用于 fork
+    ///
+    /// 内存空间采用写时复制；文件描述符共享同一个 [`OpenFile`]；工作目录和信号处理方式与父进程相同；
+    /// 互斥锁表保留原有的 ID，但子进程中的锁均为未上锁状态；信号量保留原有的 ID 和当前的资源数。
+    /// 子进程不包含任何线程，需要由调用者加入。
+    pub fn fork(self: &Arc<Self>) -> KResult<Arc<Self>> {
+        let mut inner = self.inner();
+        let child = Self::register(Process {
+            pid: Self::next_pid(),
+            is_user: self.is_user,
+            inner: Mutex::new(ProcessInner {
+                memory_set: inner.memory_set.fork()?,
+                descriptors: inner.descriptors.clone(),
+                cwd: inner.cwd.clone(),
+                threads: HashMap::default(),
+                mutex_queue: inner
+                    .mutex_queue
+                    .keys()
+                    .map(|id| (*id, Arc::new(super::mutex::Mutex::default())))
+                    .collect(),
+                next_mutex_id: inner.next_mutex_id,
+                sem_table: inner
+                    .sem_table
+                    .iter()
+                    .map(|(id, semaphore)| (*id, Arc::new(semaphore.fork())))
+                    .collect(),
+                next_sem_id: inner.next_sem_id,
+                parent: Arc::downgrade(self),
+                children: Vec::new(),
+                exit_code: None,
+                signal_actions: inner.signal_actions,
+                stopped: false,
+                exiting: false,
             }),
+            child_exit: Condvar::default(),
+            continued: Condvar::default(),
+        });
+        inner.children.push(child.clone());
+        Ok(child)
+    }
+
+    /// 用 ELF 文件替换进程的内存空间，用于 exec
+    ///
+    /// `stack` 为调用 exec 的线程的栈，会在新的内存空间中以相同的地址重新映射。
+    /// 新的内存空间会立即激活，旧的内存空间随之释放。互斥锁表和信号量表会被清空，
+    /// 设置了处理函数的信号恢复为默认处理方式（被忽略的信号仍然被忽略）。
+    pub fn exec(&self, file: &ElfFile, stack: Range<VirtualAddress>) -> KResult<()> {
+        let mut memory_set = MemorySet::from_elf(file, self.is_user)?;
+        let stack_segment = Segment {
+            map_type: MapType::Framed,
+            range: stack,
+            flags: Flags::READABLE | Flags::WRITABLE | Flags::user(self.is_user),
+        };
+        if memory_set.overlap_with(stack_segment.page_range()) {
+            return Err("thread stack overlaps with elf segments");
+        }
+        memory_set.add_segment(stack_segment, None)?;
+
+        let mut inner = self.inner();
+        // 先激活新的页表，再释放旧的页表
+        let old_memory_set = core::mem::replace(&mut inner.memory_set, memory_set);
+        inner.memory_set.activate();
+        drop(old_memory_set);
+        inner.mutex_queue.clear();
+        inner.sem_table.clear();
+        for action in inner.signal_actions.iter_mut() {
+            if action.handler != SIG_IGN {
+                *action = SigAction::default();
+            }
+        }
+        // 关闭设置了 close-on-exec 的文件描述符
+        for descriptor in inner.descriptors.iter_mut() {
+            if descriptor.as_ref().map_or(false, |d| d.close_on_exec) {
+                *descriptor = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// 进程中的一个线程退出
+    ///
+    /// 如果进程中已经没有其他存活的线程，则整个进程以 `code` 退出，见 [`Process::exit`]
+    pub fn exit_thread(&self, thread_id: ThreadID, code: isize) {
+        let last_thread = !self.inner().threads.iter().any(|(id, thread)| {
+            *id != thread_id
+                && thread
+                    .upgrade()
+                    .map_or(false, |thread| thread.inner().state != Dead)
+        });
+        if last_thread {
+            self.exit(code);
+        }
+    }
+
+    /// 结束整个进程
+    ///
+    /// 终止进程中除当前线程以外的所有线程，立即释放文件描述符、互斥锁和信号量，然后进程以 `code` 退出。
+    /// 如果当前线程属于这个进程，需要由调用者终止。进程已经在结束时直接返回。
+    ///
+    /// 其他线程可能仍在别的处理器核上执行用户程序，或者在内核中访问用户内存，
+    /// 因此内存空间在所有线程结束并切换出去之后才由 [`Process::release_memory`] 释放。
+    /// 这里不等待其他线程，可以在中断处理中调用
+    pub fn exit_group(&self, code: isize) {
+        // 进程只结束一次，后来者直接返回，由调用者终止
+        if core::mem::replace(&mut self.inner().exiting, true) {
+            return;
+        }
+        ALARM.lock().remove_process(self.pid);
+        let current_thread = PROCESSOR.lock().current_thread();
+        let threads = self.kill_other_threads(&current_thread);
+        // 释放资源。`threads` 仍持有线程的引用，所以这里不会在持有锁时析构线程
+        {
+            let mut inner = self.inner();
+            inner.mutex_queue.clear();
+            inner.sem_table.clear();
+            inner.descriptors.clear();
+        }
+        // 其他线程都已经结束时（例如由其他进程终止）立即释放内存空间
+        self.release_memory();
+        self.exit(code);
+    }
+
+    /// 终止进程中除 `current_thread` 以外的所有线程，返回被终止的线程
+    ///
+    /// 休眠的线程被唤醒，在返回用户态之前结束；正在其他处理器核上执行的线程会在下一次进入中断时结束
+    pub fn kill_other_threads(&self, current_thread: &Arc<Thread>) -> Vec<Arc<Thread>> {
+        let threads: Vec<Arc<Thread>> = self
+            .inner()
+            .threads
+            .values()
+            .filter_map(Weak::upgrade)
+            .filter(|thread| thread != current_thread)
+            .collect();
+        for thread in threads.iter() {
+            kill_thread(thread);
+        }
+        threads
+    }
+
+    /// 正在结束的进程中所有线程都已经结束并切换出去之后，释放按帧映射和文件映射的内存
+    ///
+    /// 由调度循环在线程结束并切换出去之后调用，其他情况不做任何事
+    pub fn release_memory(&self) {
+        let threads: Vec<Arc<Thread>> = {
+            let inner = self.inner();
+            if !inner.exiting {
+                return;
+            }
+            inner.threads.values().filter_map(Weak::upgrade).collect()
+        };
+        let finished = threads
+            .iter()
+            .all(|thread| thread.inner().state == Dead && !thread.is_running());
+        if finished {
+            self.inner().memory_set.clear_framed_segments();
+        }
+        // 释放锁之后再释放线程的引用，线程的析构需要获取锁
+    }
+
+    /// 进程退出，成为僵尸进程，等待父进程回收
+    ///
+    /// 所有子进程交给 init 进程，然后唤醒等待子进程退出的父进程
+    pub fn exit(&self, code: isize) {
+        let children = {
+            let mut inner = self.inner();
+            inner.exit_code = Some(code);
+            core::mem::take(&mut inner.children)
+        };
+        if !children.is_empty() {
+            let init = INIT_PROCESS.lock().upgrade();
+            match init {
+                Some(init) if init.pid != self.pid => {
+                    for child in children.iter() {
+                        child.inner().parent = Arc::downgrade(&init);
+                    }
+                    init.inner().children.extend(children);
+                    // 孤儿进程中可能已有僵尸进程
+                    init.child_exit.notify_all();
+                }
+                _ => {
+                    for child in children.iter() {
+                        child.inner().parent = Weak::new();
+                    }
+                }
+            }
+        }
+        let parent = self.inner().parent.upgrade();
+        // 前台进程退出后，由父进程回到前台
+        {
+            let mut foreground = FOREGROUND_PROCESS.lock();
+            if foreground.upgrade().map_or(false, |p| p.pid == self.pid) {
+                *foreground = parent.as_ref().map_or(Weak::new(), Arc::downgrade);
+            }
+        }
+        if let Some(parent) = parent {
+            parent.child_exit.notify_all();
+        }
+    }
+
+    /// 是否为 init 进程
+    pub fn is_init(&self) -> bool {
+        INIT_PROCESS
+            .lock()
+            .upgrade()
+            .map_or(false, |init| init.pid == self.pid)
+    }
+
+    /// 回收一个已经退出的子进程，返回其进程 ID 和返回值
+    ///
+    /// `pid` 为 -1 时回收任意子进程。如果不存在符合条件的子进程，返回 `Err`；
+    /// 如果子进程都还没有退出，返回 `Ok(None)`
+    pub fn reap_child(&self, pid: ProcessID) -> KResult<Option<(ProcessID, isize)>> {
+        let mut inner = self.inner();
+        let matches = |child: &Arc<Process>| pid == -1 || child.pid == pid;
+        if !inner.children.iter().any(matches) {
+            return Err("no such child process");
+        }
+        let zombie = inner
+            .children
+            .iter()
+            .position(|child| matches(child) && child.inner().exit_code.is_some());
+        Ok(zombie.map(|i| {
+            let child = inner.children.remove(i);
+            let code = child.inner().exit_code.unwrap();
+            (child.pid, code)
         }))
     }
 
+    /// 将新创建的进程登记到进程表中
+    fn register(process: Self) -> Arc<Self> {
+        let process = Arc::new(process);
+        PROCESSES
+            .lock()
+            .insert(process.pid, Arc::downgrade(&process));
+        process
+    }
+
+    /// 分配一个新的进程 ID
+    fn next_pid() -> ProcessID {
+        PROCESS_COUNTER.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
     /// 上锁并获得可变部分的引用
     pub fn inner(&self) -> spin::MutexGuard<ProcessInner> {
         self.inner.lock()
@@ -76,21 +408,15 @@ impl Process {
 
     /// 分配一定数量的连续虚拟空间
     ///
-    /// 从 `memory_set` 中找到一段给定长度的未占用虚拟地址空间，分配物理页面并建立映射。返回对应的页面区间。
+    /// 从 `memory_set` 中找到一段给定长度的未占用虚拟地址空间并建立映射，物理页面在第一次访问时才会分配。
+    /// 返回对应的页面区间。
     ///
     /// `flags` 只需包括 rwx 权限，user 位会根据进程而定。
     pub fn alloc_page_range(&self, size: usize, flags: Flags) -> KResult<Range<VirtualAddress>> {
         let memory_set = &mut self.inner().memory_set;
 
-        // memory_set 只能按页分配，所以让 size 向上取整页
-        let alloc_size = (size + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
-        // 从 memory_set 中找一段不会发生重叠的空间
-        let mut range = Range::<VirtualAddress>::from(0x1000000..0x1000000 + alloc_size);
-        while memory_set.overlap_with(range.into()) {
-            range.start += alloc_size;
-            range.end += alloc_size;
-        }
-        // 分配物理页面，建立映射
+        let range = Self::free_range(memory_set, size)?;
+        // 建立映射，物理页面按需分配
         memory_set.add_segment(
             Segment {
                 map_type: MapType::Framed,
@@ -103,11 +429,233 @@ impl Process {
         Ok(Range::from(range.start..(range.start + size)))
     }
 
+    /// 找一段大小至少为 `size` 且没有被映射的页对齐的地址区间
+    pub fn find_free_range(&self, size: usize) -> KResult<Range<VirtualAddress>> {
+        Self::free_range(&self.inner().memory_set, size)
+    }
+
+    /// 从 `memory_set` 中找一段不会发生重叠的空间
+    ///
+    /// 从 [`USER_SPACE_END`] 向下查找，把低地址留给向上增长的堆；第 0 页保持不映射，
+    /// 使返回的地址不会与空指针混淆。找不到时返回 `Err`
+    fn free_range(memory_set: &MemorySet, size: usize) -> KResult<Range<VirtualAddress>> {
+        // memory_set 只能按页分配，所以让 size 向上取整页
+        let alloc_size = size
+            .checked_add(PAGE_SIZE - 1)
+            .ok_or("no free address range")?
+            & !(PAGE_SIZE - 1);
+        let mut end = USER_SPACE_END;
+        loop {
+            let start = match end.checked_sub(alloc_size) {
+                Some(start) if start >= PAGE_SIZE => start,
+                _ => return Err("no free address range"),
+            };
+            let range = Range::<VirtualAddress>::from(start..end);
+            if !memory_set.overlap_with(range.into()) {
+                return Ok(range);
+            }
+            end = start;
+        }
+    }
+
+    /// 加入一个文件描述符，使用最小的未被占用的编号
+    ///
+    /// 文件描述符个数达到 [`MAX_DESCRIPTORS`] 时返回 `None`
+    pub fn add_descriptor(&self, file: Arc<OpenFile>, close_on_exec: bool) -> Option<usize> {
+        let descriptors = &mut self.inner().descriptors;
+        let descriptor = FileDescriptor {
+            file,
+            close_on_exec,
+        };
+        match descriptors.iter().position(Option::is_none) {
+            Some(fd) => {
+                descriptors[fd] = Some(descriptor);
+                Some(fd)
+            }
+            None if descriptors.len() < MAX_DESCRIPTORS => {
+                descriptors.push(Some(descriptor));
+                Some(descriptors.len() - 1)
+            }
+            None => None,
+        }
+    }
+
+    /// 当前工作目录
+    pub fn cwd(&self) -> Arc<dyn INode> {
+        self.inner().cwd.clone()
+    }
+
+    /// 获取文件描述符对应的 [`OpenFile`]
+    pub fn get_descriptor(&self, fd: usize) -> Option<Arc<OpenFile>> {
+        self.inner()
+            .descriptors
+            .get(fd)
+            .and_then(|descriptor| descriptor.as_ref())
+            .map(|descriptor| descriptor.file.clone())
+    }
+
+    /// 关闭文件描述符，返回其对应的 [`OpenFile`]
+    pub fn remove_descriptor(&self, fd: usize) -> Option<Arc<OpenFile>> {
+        self.inner()
+            .descriptors
+            .get_mut(fd)
+            .and_then(Option::take)
+            .map(|descriptor| descriptor.file)
+    }
+
+    /// 令 `new_fd` 指向 `old_fd` 所指向的文件，`new_fd` 原本打开的文件会被关闭
+    ///
+    /// 返回被关闭的文件，由调用者在释放锁之后析构
+    pub fn duplicate_descriptor(
+        &self,
+        old_fd: usize,
+        new_fd: usize,
+        close_on_exec: bool,
+    ) -> KResult<Option<Arc<OpenFile>>> {
+        if new_fd >= MAX_DESCRIPTORS {
+            return Err("file descriptor out of range");
+        }
+        let descriptors = &mut self.inner().descriptors;
+        let file = descriptors
+            .get(old_fd)
+            .and_then(|descriptor| descriptor.as_ref())
+            .ok_or("file descriptor is not open")?
+            .file
+            .clone();
+        if descriptors.len() <= new_fd {
+            descriptors.resize(new_fd + 1, None);
+        }
+        let old = descriptors[new_fd].replace(FileDescriptor {
+            file,
+            close_on_exec,
+        });
+        Ok(old.map(|descriptor| descriptor.file))
+    }
+
+    /// 调整堆的大小，`brk` 为 0 时仅查询，返回调整后的 program break
+    ///
+    /// 调整失败时 program break 保持不变
+    pub fn set_brk(&self, brk: usize) -> VirtualAddress {
+        let memory_set = &mut self.inner().memory_set;
+        if brk != 0 {
+            let flags = Flags::READABLE | Flags::WRITABLE | Flags::user(self.is_user);
+            if let Err(e) = memory_set.set_brk(VirtualAddress(brk), flags) {
+                debug_println!("error in brk: {}", e);
+            }
+        }
+        memory_set.heap.end
+    }
+
+    /// 将一段页对齐的地址区间映射为匿名内存，区间内原有的映射会被移除
+    pub fn map_fixed(&self, range: Range<VirtualAddress>, flags: Flags) -> KResult<()> {
+        let memory_set = &mut self.inner().memory_set;
+        memory_set.remove_range(range.into())?;
+        // 建立映射，物理页面按需分配
+        memory_set.add_segment(
+            Segment {
+                map_type: MapType::Framed,
+                range,
+                flags: flags | Flags::user(self.is_user),
+            },
+            None,
+        )
+    }
+
+    /// 将文件 `inode` 从 `offset` 开始的部分映射到页对齐的区间，区间内原有的映射会被移除
+    ///
+    /// `shared` 为真时写入对其他进程和文件可见，否则写入时复制
+    pub fn map_file(
+        &self,
+        range: Range<VirtualAddress>,
+        flags: Flags,
+        inode: Arc<dyn INode>,
+        offset: usize,
+        shared: bool,
+    ) -> KResult<()> {
+        let memory_set = &mut self.inner().memory_set;
+        memory_set.remove_range(range.into())?;
+        memory_set.add_file_segment(
+            range,
+            flags | Flags::user(self.is_user),
+            inode,
+            offset,
+            shared,
+        )
+    }
+
+    /// 将一段页对齐的地址区间内共享文件映射的页面写回文件
+    pub fn sync_range(&self, range: Range<VirtualAddress>) -> KResult<()> {
+        self.inner().memory_set.sync_range(range.into())
+    }
+
+    /// 一段页对齐的地址区间是否没有被映射
+    pub fn is_range_free(&self, range: Range<VirtualAddress>) -> bool {
+        !self.inner().memory_set.overlap_with(range.into())
+    }
+
+    /// 移除一段页对齐的地址区间内的映射
+    pub fn unmap_range(&self, range: Range<VirtualAddress>) -> KResult<()> {
+        self.inner().memory_set.remove_range(range.into())
+    }
+
+    /// 修改一段页对齐的地址区间内的权限
+    pub fn protect_range(&self, range: Range<VirtualAddress>, flags: Flags) -> KResult<()> {
+        self.inner()
+            .memory_set
+            .protect_range(range.into(), flags | Flags::user(self.is_user))
+    }
+
+    /// 内核读写用户内存之前调用，见 [`MemorySet::prepare_access`]；区间末尾溢出时返回 `Err`
+    pub fn prepare_user_access(&self, address: usize, size: usize, is_write: bool) -> KResult<()> {
+        let end = address
+            .checked_add(size)
+            .ok_or("user address range overflows")?;
+        self.inner()
+            .memory_set
+            .prepare_access(Range::from(address..end), is_write)
+    }
+
     pub fn create_mutex(&self) -> MutexID {
         let mut guard = self.inner.lock();
         let id: MutexID = guard.next_mutex_id;
-        guard.mutex_queue.insert(id, super::mutex::Mutex::default());
+        guard
+            .mutex_queue
+            .insert(id, Arc::new(super::mutex::Mutex::default()));
         guard.next_mutex_id += 1;
         id
     }
+
+    /// 创建资源数为 `value` 的信号量，返回它的编号
+    pub fn create_semaphore(&self, value: usize) -> SemID {
+        let mut inner = self.inner();
+        let id = inner.next_sem_id;
+        inner.sem_table.insert(id, Arc::new(Semaphore::new(value)));
+        inner.next_sem_id += 1;
+        id
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        PROCESSES.lock().remove(&self.pid);
+    }
+}
+
+/// 新进程默认打开的文件描述符：0 为标准输入，1 为标准输出，2 为标准错误输出（同样输出到控制台）
+fn standard_descriptors() -> Vec<Option<FileDescriptor>> {
+    let stdout = OpenFile::new(STDOUT.clone(), O_WRONLY);
+    vec![
+        Some(FileDescriptor {
+            file: OpenFile::new(STDIN.clone(), O_RDONLY),
+            close_on_exec: false,
+        }),
+        Some(FileDescriptor {
+            file: stdout.clone(),
+            close_on_exec: false,
+        }),
+        Some(FileDescriptor {
+            file: stdout,
+            close_on_exec: false,
+        }),
+    ]
 }
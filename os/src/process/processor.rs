@@ -1,31 +1,39 @@
 //! 实现线程的调度和管理 [`Processor`]
 
 use lazy_static::*;
+use riscv::register::time;
 
-use crate::interrupt::context::Context;
-use crate::kernel::syscall::SyscallResult;
+use crate::interrupt::timer;
 use crate::kernel::thread::ThreadState::Runnable;
 use crate::process::alarm::ALARM;
 use crate::process::thread::Thread;
 use crate::process::thread::ThreadState::{Dead, Sleeping};
+use crate::smp::{hart_id, idle_hart, is_online, send_ipi, set_idle, MAX_HARTS};
+use crate::KResult;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 use super::alloc::sync::Arc;
-use super::lock::Lock;
+use super::lock::PerHart;
 use super::process::Process;
 use super::scheduler::*;
+use super::switch::{switch_to_scheduler, SwitchContext};
 
 lazy_static! {
-    /// 全局的 [`Processor`]
-    pub static ref PROCESSOR: Lock<Processor> = Lock::new(Processor::default());
+    /// 每个处理器核各自的 [`Processor`]，`PROCESSOR.lock()` 获得当前处理器核的
+    pub static ref PROCESSOR: PerHart<Processor> = PerHart::new(Processor::new);
 }
 
-lazy_static! {
-    /// 空闲线程：当所有线程进入休眠时，切换到这个线程——它什么都不做，只会等待下一次中断
-    static ref IDLE_THREAD: Arc<Thread> = Thread::new(
+/// 所有处理器核上休眠的线程数
+static SLEEPING_THREADS: AtomicUsize = AtomicUsize::new(0);
+
+/// 创建空闲线程：当处理器核上所有线程进入休眠时，切换到这个线程——它什么都不做，只会等待下一次中断
+fn new_idle_thread() -> Arc<Thread> {
+    Thread::new(
         Process::new_kernel().unwrap(),
         wait_for_interrupt as usize,
         None,
-    ).unwrap();
+    )
+    .unwrap()
 }
 
 /// 不断让 CPU 进入休眠等待下一次中断
@@ -35,47 +43,63 @@ unsafe fn wait_for_interrupt() {
     }
 }
 
-/// 线程调度和管理
+/// 线程调度和管理，每个处理器核有一个
 ///
 /// 休眠线程会从调度器中移除，单独保存。在它们被唤醒之前，不会被调度器安排。
+/// 被唤醒的线程加入唤醒者所在处理器核的调度器；空闲的处理器核在每次调度时从最繁忙的处理器核迁移线程。
+///
+/// 线程通过 [`schedule`] 切换回处理器核的调度循环 [`run`]，由调度循环选出下一个线程。
 ///
 /// # 用例
 ///
 /// ### 切换线程（在中断中）
 /// ```rust
-/// processor.park_current_thread(context);
-/// processor.prepare_next_thread()
+/// schedule();
 /// ```
 ///
 /// ### 结束线程（在中断中）
 /// ```rust
-/// processor.kill_current_thread();
-/// processor.prepare_next_thread()
+/// exit_current_thread()
 /// ```
 ///
-/// ### 休眠线程（在中断中）
+/// ### 休眠线程
+/// 加入等待队列之后、释放等待队列的锁之后再切换，被唤醒后从 `schedule()` 返回。
 /// ```rust
-/// processor.park_current_thread(context);
-/// processor.sleep_current_thread();
-/// processor.prepare_next_thread()
+/// let thread = PROCESSOR.lock().sleep_current_thread()?;
+/// queue.lock().push_back(thread);
+/// schedule();
 /// ```
 ///
 /// ### 唤醒线程
 /// 线程会根据调度器分配执行，不一定会立即执行。
 /// ```rust
-/// processor.wake_thread(thread);
+/// wake_thread(&thread);
 /// ```
-#[derive(Default)]
 pub struct Processor {
+    /// 处理器核的编号
+    hart: usize,
     /// 当前正在执行的线程
     current_thread: Option<Arc<Thread>>,
     /// 线程调度器，记录活跃线程
     scheduler: SchedulerImpl<Arc<Thread>>,
-
-    num_sleeping_threads: u64,
+    /// 调度器中的线程数（包括正在执行的线程）
+    load: usize,
+    /// 空闲线程，第一次需要时创建
+    idle_thread: Option<Arc<Thread>>,
 }
 
 impl Processor {
+    /// 创建编号为 `hart` 的处理器核的 `Processor`
+    fn new(hart: usize) -> Self {
+        Self {
+            hart,
+            current_thread: None,
+            scheduler: SchedulerImpl::default(),
+            load: 0,
+            idle_thread: None,
+        }
+    }
+
     /// 获取一个当前线程的 `Arc` 引用
     pub fn current_thread(&self) -> Arc<Thread> {
         self.current_thread
@@ -84,58 +108,308 @@ impl Processor {
             .clone()
     }
 
-    /// 激活下一个线程的 `Context`
-    pub fn prepare_next_thread(&mut self) -> *mut Context {
+    /// 当前是否在执行空闲线程
+    pub fn is_idle(&self) -> bool {
+        self.current_thread.is_some() && self.current_thread == self.idle_thread
+    }
+
+    /// 正在执行空闲线程，而调度器中已经有线程等待执行（例如被中断处理唤醒）
+    pub fn should_leave_idle(&self) -> bool {
+        self.is_idle() && self.load > 0
+    }
+
+    /// 选出下一个线程，并记为当前线程
+    ///
+    /// 先尝试从其他处理器核迁移线程；没有活跃线程时返回空闲线程
+    fn prepare_next_thread(&mut self) -> Arc<Thread> {
+        self.balance();
         // 向调度器询问下一个线程
         if let Some(next_thread) = self.scheduler.get_next() {
-            // 准备下一个线程
-            let context = next_thread.prepare();
-            self.current_thread = Some(next_thread);
-            context
-        } else {
-            // 没有活跃线程
-            if self.num_sleeping_threads == 0 {
-                // 也没有休眠线程，则退出
-                panic!("all threads terminated, shutting down");
-            } else {
-                // 有休眠线程，则等待中断
-                self.current_thread = Some(IDLE_THREAD.clone());
-                IDLE_THREAD.prepare()
-            }
+            set_idle(self.hart, false);
+            self.current_thread = Some(next_thread.clone());
+            return next_thread;
+        }
+        // 没有活跃线程
+        if SLEEPING_THREADS.load(Ordering::SeqCst) == 0 && !self.others_busy() {
+            // 也没有休眠线程，其他处理器核上也没有线程，则退出
+            panic!("all threads terminated, shutting down");
         }
+        // 否则等待中断
+        set_idle(self.hart, true);
+        let idle_thread = self.idle_thread.get_or_insert_with(new_idle_thread).clone();
+        self.current_thread = Some(idle_thread.clone());
+        idle_thread
     }
 
-    /// 添加一个待执行的线程
-    pub fn add_thread(&mut self, thread: Arc<Thread>) {
-        debug_assert!(thread.inner().state == Runnable);
-        self.scheduler.add_thread(thread);
+    /// 更换调度算法，必须在加入任何线程之前调用
+    pub fn set_scheduler(&mut self, kind: SchedulerKind) {
+        assert!(self.current_thread.is_none());
+        self.scheduler = SchedulerImpl::new(kind);
     }
 
-    /// 保存当前线程的 `Context`
-    pub fn park_current_thread(&mut self, context: &Context) {
-        self.current_thread().park(*context);
+    /// 添加一个待执行的线程
+    ///
+    /// 不会通知空闲的处理器核，其他处理器核已经启动时应使用 [`add_thread`]
+    pub fn add_thread(&mut self, thread: Arc<Thread>) {
+        {
+            let mut inner = thread.inner();
+            debug_assert!(inner.state == Runnable);
+            inner.hart = self.hart;
+        }
+        self.enqueue(thread, None);
     }
 
-    pub fn sleep_current_thread(&mut self) -> Arc<Thread> {
+    /// 令当前线程休眠，将其从调度器中移除
+    ///
+    /// 调用者需要将返回的线程加入等待队列，然后通过 [`schedule`] 切换出去。
+    /// 线程已经被要求终止时不会休眠，返回 `Err`
+    pub fn sleep_current_thread(&mut self) -> KResult<Arc<Thread>> {
         let current_thread = self.current_thread();
-        self.scheduler.remove_thread(&current_thread);
-        self.num_sleeping_threads += 1;
-        current_thread.inner().state = Sleeping;
-        current_thread
+        let mut inner = current_thread.inner();
+        if inner.killed {
+            return Err("interrupted");
+        }
+        inner.state = Sleeping;
+        inner.sleep_time = time::read();
+        SLEEPING_THREADS.fetch_add(1, Ordering::SeqCst);
+        drop(inner);
+        self.dequeue(&current_thread);
+        Ok(current_thread)
     }
 
-    pub fn wake_thread(&mut self, thread: Arc<Thread>) {
-        debug_assert!(thread.inner().state == Sleeping);
-        self.num_sleeping_threads -= 1;
-        thread.inner().state = Runnable;
-        self.scheduler.add_thread(thread);
+    /// 唤醒一个休眠的线程，返回是否唤醒（线程已经被唤醒或终止时返回 `false`）
+    ///
+    /// 线程加入当前处理器核的调度器，调度器可以根据休眠的时长给予补偿。
+    /// 不会通知空闲的处理器核，通常应使用 [`wake_thread`]
+    pub fn wake_thread(&mut self, thread: &Arc<Thread>) -> bool {
+        let slept = {
+            let mut inner = thread.inner();
+            if inner.state != Sleeping {
+                return false;
+            }
+            inner.state = Runnable;
+            inner.hart = self.hart;
+            time::read() - inner.sleep_time
+        };
+        SLEEPING_THREADS.fetch_sub(1, Ordering::SeqCst);
+        self.enqueue(thread.clone(), Some(slept));
+        true
     }
 
-    /// 终止当前的线程
-    pub fn kill_current_thread(&mut self) -> Arc<Thread> {
-        // 从调度器中移除
+    /// 终止当前的线程，将其从调度器中移除
+    fn kill_current_thread(&mut self) -> Arc<Thread> {
         let thread = self.current_thread.take().unwrap();
-        self.scheduler.remove_thread(&thread);
+        self.dequeue(&thread);
+        thread.inner().state = Dead;
         thread
     }
+
+    /// 设置线程的 nice 值，线程在调度器中时立即生效，否则在被唤醒时生效
+    ///
+    /// 线程在其他处理器核的调度器中时返回 `false`
+    fn set_nice(&mut self, thread: &Arc<Thread>, nice: isize) -> bool {
+        let runnable = {
+            let mut inner = thread.inner();
+            if inner.state == Runnable && inner.hart != self.hart {
+                return false;
+            }
+            inner.nice = nice;
+            inner.state == Runnable
+        };
+        if runnable {
+            self.scheduler
+                .set_priority(thread.clone(), nice_to_priority(nice));
+        }
+        true
+    }
+
+    /// 将线程加入调度器，并按照它的 nice 值设置优先级；`slept` 为被唤醒的线程休眠的时长
+    fn enqueue(&mut self, thread: Arc<Thread>, slept: Option<usize>) {
+        let priority = nice_to_priority(thread.inner().nice);
+        match slept {
+            Some(slept) => self.scheduler.wake_thread(thread.clone(), slept),
+            None => self.scheduler.add_thread(thread.clone()),
+        }
+        self.scheduler.set_priority(thread, priority);
+        self.load += 1;
+    }
+
+    /// 将线程从调度器中移除
+    fn dequeue(&mut self, thread: &Arc<Thread>) {
+        self.scheduler.remove_thread(thread);
+        self.load -= 1;
+    }
+
+    /// 其他处理器核上是否还有线程，无法确定时视为有
+    fn others_busy(&self) -> bool {
+        (0..MAX_HARTS)
+            .filter(|&hart| hart != self.hart && is_online(hart))
+            .any(|hart| match PROCESSOR.try_lock_hart(hart) {
+                Some(other) => other.load > 0,
+                None => true,
+            })
+    }
+
+    /// 其他处理器核上是否有可以迁移过来的线程，无法确定时视为有
+    fn others_overloaded(&self) -> bool {
+        (0..MAX_HARTS)
+            .filter(|&hart| hart != self.hart && is_online(hart))
+            .any(|hart| match PROCESSOR.try_lock_hart(hart) {
+                Some(other) => other.load > 1,
+                None => true,
+            })
+    }
+
+    /// 负载均衡：最繁忙的处理器核比这个处理器核多至少两个线程时，从它那里迁移一个线程过来
+    ///
+    /// 只尝试获取其他处理器核的锁，获取不到时跳过，因此处理器核之间不会互相等待
+    fn balance(&mut self) {
+        let mut busiest: Option<(usize, usize)> = None;
+        for hart in (0..MAX_HARTS).filter(|&hart| hart != self.hart && is_online(hart)) {
+            if let Some(other) = PROCESSOR.try_lock_hart(hart) {
+                if other.load > busiest.map_or(0, |(_, load)| load) {
+                    busiest = Some((hart, other.load));
+                }
+            }
+        }
+        let hart = match busiest {
+            Some((hart, load)) if load > self.load + 1 => hart,
+            _ => return,
+        };
+        let thread = match PROCESSOR.try_lock_hart(hart) {
+            Some(mut other) => other.steal_thread(self.hart),
+            None => return,
+        };
+        if let Some(thread) = thread {
+            self.enqueue(thread, None);
+        }
+    }
+
+    /// 从调度器中取出一个不在执行的线程，迁移到编号为 `hart` 的处理器核
+    fn steal_thread(&mut self, hart: usize) -> Option<Arc<Thread>> {
+        let thread = self.scheduler.steal_thread(self.current_thread.as_ref())?;
+        self.load -= 1;
+        // 在释放这个处理器核的锁之前修改，其他处理器核才能找到线程所在的调度器
+        thread.inner().hart = hart;
+        Some(thread)
+    }
+}
+
+/// 处理器核的调度循环，在启动栈上执行，不会返回
+///
+/// 不断选出下一个线程并切换过去，线程让出处理器核后回到这里。
+/// 切换到空闲线程时停止周期性的时钟中断，离开空闲线程时恢复。
+/// 其他处理器核上有可以迁移的线程时（例如这次没有获取到它的锁），空闲时也保留时钟中断，以便下一次调度时重试
+pub fn run() -> ! {
+    loop {
+        let (thread, idle) = {
+            let mut processor = PROCESSOR.lock();
+            let thread = processor.prepare_next_thread();
+            let idle = processor.is_idle() && !processor.others_overloaded();
+            (thread, idle)
+        };
+        // 释放 PROCESSOR 的锁之后再获取 ALARM 的锁
+        if idle {
+            timer::stop_tick(ALARM.lock().next_deadline());
+        } else if timer::tick_stopped() {
+            timer::start_tick(ALARM.lock().next_deadline());
+        }
+        thread.run();
+        // 进程中最后一个结束的线程切换出去之后，释放进程的内存空间
+        if thread.inner().state == Dead {
+            thread.process.release_memory();
+        }
+        // 已经结束的线程在这里析构，此时不在它的内核栈上
+        drop(thread);
+    }
+}
+
+/// 当前线程让出处理器核，切换回调度循环；线程再次被调度时返回
+///
+/// 线程仍然可以被调度器选中，除非已经通过 [`Processor::sleep_current_thread`] 休眠。
+/// 调用时不能持有除 `PROCESSOR` 以外的锁
+pub fn schedule() {
+    let thread = PROCESSOR.lock().current_thread();
+    let switch_context = &mut thread.inner().switch_context as *mut SwitchContext;
+    // 调度循环仍持有线程的引用
+    drop(thread);
+    unsafe { switch_to_scheduler(switch_context) };
+}
+
+/// 结束当前线程，切换回调度循环，不再返回
+///
+/// 调用者需要先释放内核栈上持有的引用，否则它们不会被析构
+pub fn exit_current_thread() -> ! {
+    let thread = PROCESSOR.lock().kill_current_thread();
+    // 唤醒等待这个线程结束的线程
+    thread.join_handle.notify_all();
+    let switch_context = &mut thread.inner().switch_context as *mut SwitchContext;
+    // 调度循环仍持有线程的引用，切换出去之后才会析构
+    drop(thread);
+    unsafe { switch_to_scheduler(switch_context) };
+    unreachable!()
+}
+
+/// 添加一个待执行的线程，然后通知空闲的处理器核来迁移线程
+pub fn add_thread(thread: Arc<Thread>) {
+    let load = {
+        let mut processor = PROCESSOR.lock();
+        processor.add_thread(thread);
+        processor.load
+    };
+    notify_idle_hart(load);
+}
+
+/// 唤醒一个休眠的线程，返回是否唤醒，然后通知空闲的处理器核来迁移线程
+pub fn wake_thread(thread: &Arc<Thread>) -> bool {
+    let (woken, load) = {
+        let mut processor = PROCESSOR.lock();
+        (processor.wake_thread(thread), processor.load)
+    };
+    if woken {
+        notify_idle_hart(load);
+    }
+    woken
+}
+
+/// 当前处理器核上有线程在等待执行，而其他处理器核空闲时，通过处理器间中断让它来迁移线程
+///
+/// 必须在释放 `PROCESSOR` 的锁之后调用，否则被唤醒的处理器核获取不到锁，无法迁移线程
+fn notify_idle_hart(load: usize) {
+    if load > 1 {
+        if let Some(hart) = idle_hart(hart_id()) {
+            send_ipi(hart);
+        }
+    }
+}
+
+/// 要求一个线程终止，线程可以属于任何处理器核
+///
+/// 休眠的线程会被唤醒，从阻塞的地方返回；正在其他处理器核上执行的线程通过处理器间中断进入中断。
+/// 线程会在下一次离开内核之前结束
+pub fn kill_thread(thread: &Arc<Thread>) {
+    thread.inner().killed = true;
+    if !wake_thread(thread) {
+        let hart = thread.inner().hart;
+        if hart != hart_id() {
+            send_ipi(hart);
+        }
+    }
+}
+
+/// 更换所有处理器核的调度算法，必须在加入任何线程之前调用
+pub fn set_scheduler(kind: SchedulerKind) {
+    for hart in 0..MAX_HARTS {
+        PROCESSOR.lock_hart(hart).set_scheduler(kind);
+    }
+}
+
+/// 设置线程的 nice 值，线程可以属于任何处理器核
+pub fn set_nice(thread: &Arc<Thread>, nice: isize) {
+    loop {
+        let hart = thread.inner().hart;
+        if PROCESSOR.lock_hart(hart).set_nice(thread, nice) {
+            return;
+        }
+    }
 }
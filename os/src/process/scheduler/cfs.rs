@@ -0,0 +1,102 @@
+//! 完全公平调度算法的调度器 [`CfsScheduler`]
+
+use super::run_queue::RunQueue;
+use super::{Priority, Scheduler, DEFAULT_PRIORITY};
+use core::hash::Hash;
+use riscv::register::time;
+
+/// nice 值为 0 的线程的权重
+const NICE_0_WEIGHT: usize = 1024;
+
+/// nice 值从 -20 到 19 对应的权重（与 Linux 相同），相邻两级相差约 1.25 倍
+const NICE_TO_WEIGHT: [usize; 40] = [
+    88761, 71755, 56483, 46273, 36291, // -20
+    29154, 23254, 18705, 14949, 11916, // -15
+    9548, 7620, 6100, 4904, 3906, // -10
+    3121, 2501, 1991, 1586, 1277, // -5
+    1024, 820, 655, 526, 423, // 0
+    335, 272, 215, 172, 137, // 5
+    110, 87, 70, 56, 45, // 10
+    36, 29, 23, 18, 15, // 15
+];
+
+/// 被唤醒的线程最多获得的补偿，单位与 `time` 寄存器相同（约为 3 个时钟中断的间隔）
+const SLEEPER_CREDIT: usize = 300000;
+
+/// 采用 CFS（完全公平调度算法）的调度器
+///
+/// 每个线程的虚拟运行时间为实际运行时间（由 `time` 寄存器测量）× `NICE_0_WEIGHT` / 权重，
+/// 每次选择虚拟运行时间最小的线程，因此线程获得的处理器时间与权重成正比。
+///
+/// 新加入的线程从当前最小的虚拟运行时间开始；被唤醒的线程再减去休眠的时长作为补偿，
+/// 但最多补偿 [`SLEEPER_CREDIT`]，以免长时间休眠的线程唤醒后长期占用处理器
+pub struct CfsScheduler<ThreadType: Clone + Eq + Hash> {
+    /// 线程池中最小的虚拟运行时间，只增不减
+    min_vruntime: usize,
+    /// 按照虚拟运行时间排列的线程池，附带线程的权重
+    pool: RunQueue<usize, ThreadType, usize>,
+    /// 上一次 `get_next()` 返回的线程及其开始执行的时间，下一次调用时为它计时
+    running: Option<(ThreadType, usize)>,
+}
+
+/// `Default` 创建一个空的调度器
+impl<ThreadType: Clone + Eq + Hash> Default for CfsScheduler<ThreadType> {
+    fn default() -> Self {
+        Self {
+            min_vruntime: 0,
+            pool: RunQueue::default(),
+            running: None,
+        }
+    }
+}
+
+impl<ThreadType: Clone + Eq + Hash> Scheduler<ThreadType> for CfsScheduler<ThreadType> {
+    type Priority = Priority;
+
+    fn add_thread(&mut self, thread: ThreadType) {
+        self.pool
+            .push_back(thread, self.min_vruntime, weight_of(DEFAULT_PRIORITY));
+    }
+    fn wake_thread(&mut self, thread: ThreadType, slept: usize) {
+        let vruntime = self.min_vruntime.saturating_sub(slept.min(SLEEPER_CREDIT));
+        self.pool
+            .push_back(thread, vruntime, weight_of(DEFAULT_PRIORITY));
+    }
+    fn get_next(&mut self) -> Option<ThreadType> {
+        let now = time::read();
+        // 按照上一个线程实际执行的时间增加其虚拟运行时间
+        if let Some((thread, start)) = self.running.take() {
+            if let Some((vruntime, weight)) = self.pool.remove(&thread) {
+                let delta = (now - start) * NICE_0_WEIGHT / weight;
+                self.pool.push_back(thread, vruntime + delta, weight);
+            }
+        }
+        let (thread, vruntime) = self.pool.first()?;
+        let thread = thread.clone();
+        self.min_vruntime = self.min_vruntime.max(vruntime);
+        self.running = Some((thread.clone(), now));
+        Some(thread)
+    }
+    fn remove_thread(&mut self, thread: &ThreadType) {
+        // 确认线程在线程池中
+        assert!(self.pool.remove(thread).is_some());
+        if matches!(&self.running, Some((running, _)) if running == thread) {
+            self.running = None;
+        }
+    }
+    fn set_priority(&mut self, thread: ThreadType, priority: Priority) {
+        if let Some(weight) = self.pool.info_mut(&thread) {
+            *weight = weight_of(priority);
+        }
+    }
+    fn steal_thread(&mut self, running: Option<&ThreadType>) -> Option<ThreadType> {
+        // 取出虚拟运行时间最大者
+        let (thread, _, _) = self.pool.pop_last_except(running)?;
+        Some(thread)
+    }
+}
+
+/// 优先级对应的权重，优先级从 1 到 40，对应 nice 值从 19 到 -20
+fn weight_of(priority: Priority) -> usize {
+    NICE_TO_WEIGHT[40 - priority.max(1).min(40)]
+}
@@ -1,67 +1,105 @@
 //! 最高响应比优先算法的调度器 [`HrrnScheduler`]
-extern crate alloc;
 
-use super::Scheduler;
-use alloc::collections::LinkedList;
+use super::run_queue::RunQueue;
+use super::{Priority, Scheduler, DEFAULT_PRIORITY};
+use core::cmp::Reverse;
+use core::hash::Hash;
 
-/// 将线程和调度信息打包
-struct HrrnThread<ThreadType: Clone + Eq> {
-    /// 进入线程池时，[`current_time`] 中的时间
+/// 响应比的定点数精度
+const RATIO_UNIT: usize = 1 << 10;
+
+/// 线程的调度信息
+struct HrrnInfo {
+    /// 进入线程池时，`current_time` 中的时间
     birth_time: usize,
     /// 被分配时间片的次数
     service_count: usize,
-    /// 线程数据
-    pub thread: ThreadType,
+    /// 优先级
+    priority: Priority,
+}
+
+impl HrrnInfo {
+    /// 在 `current_time` 时的响应比：优先级 ×（等待时间 + 服务时间）/ 服务时间
+    ///
+    /// 还没有被服务过的线程响应比为无穷大
+    fn ratio(&self, current_time: usize) -> usize {
+        if self.service_count == 0 {
+            return usize::MAX;
+        }
+        (current_time - self.birth_time) * self.priority * RATIO_UNIT / self.service_count
+    }
 }
 
 /// 采用 HRRN（最高响应比优先算法）的调度器
-pub struct HrrnScheduler<ThreadType: Clone + Eq> {
+///
+/// 响应比为 优先级 ×（等待时间 + 服务时间）/ 服务时间，时间的单位为 `get_next()` 的调用次数。
+/// 线程按照响应比从高到低排列在 [`RunQueue`] 中；被调度的线程每次放回时按当时的响应比重新排列，
+/// 其他线程的响应比随等待时间增长，每经过与线程数相同次数的调度重新计算一次，
+/// 因此每次调度的均摊时间复杂度为 O(log n)。
+///
+/// 新加入（包括从休眠中唤醒）的线程响应比为无穷大，会被优先调度，
+/// 因此交互型线程（如 notebook）不会被计算密集的线程饿死
+pub struct HrrnScheduler<ThreadType: Clone + Eq + Hash> {
     /// 当前时间，单位为 `get_next()` 调用次数
     current_time: usize,
-    /// 带有调度信息的线程池
-    pool: LinkedList<HrrnThread<ThreadType>>,
+    /// 上一次重新计算响应比之后 `get_next()` 的调用次数
+    since_rekey: usize,
+    /// 按照响应比从高到低排列的线程池
+    pool: RunQueue<Reverse<usize>, ThreadType, HrrnInfo>,
 }
 
 /// `Default` 创建一个空的调度器
-impl<ThreadType: Clone + Eq> Default for HrrnScheduler<ThreadType> {
+impl<ThreadType: Clone + Eq + Hash> Default for HrrnScheduler<ThreadType> {
     fn default() -> Self {
         Self {
             current_time: 0,
-            pool: LinkedList::new(),
+            since_rekey: 0,
+            pool: RunQueue::default(),
         }
     }
 }
 
-impl<ThreadType: Clone + Eq> Scheduler<ThreadType> for HrrnScheduler<ThreadType> {
-    type Priority = ();
+impl<ThreadType: Clone + Eq + Hash> Scheduler<ThreadType> for HrrnScheduler<ThreadType> {
+    type Priority = Priority;
 
     fn add_thread(&mut self, thread: ThreadType) {
-        self.pool.push_back(HrrnThread {
+        let info = HrrnInfo {
             birth_time: self.current_time,
             service_count: 0,
-            thread,
-        })
+            priority: DEFAULT_PRIORITY,
+        };
+        self.pool
+            .push_back(thread, Reverse(info.ratio(self.current_time)), info);
     }
     fn get_next(&mut self) -> Option<ThreadType> {
         // 计时
         self.current_time += 1;
-
-        // 遍历线程池，返回响应比最高者
-        let current_time = self.current_time; // borrow-check
-        if let Some(best) = self.pool.iter_mut().max_by(|x, y| {
-            ((current_time - x.birth_time) * y.service_count)
-                .cmp(&((current_time - y.birth_time) * x.service_count))
-        }) {
-            best.service_count += 1;
-            Some(best.thread.clone())
-        } else {
-            None
+        self.since_rekey += 1;
+        if self.since_rekey >= self.pool.len() {
+            let current_time = self.current_time;
+            self.pool.rekey(|info| Reverse(info.ratio(current_time)));
+            self.since_rekey = 0;
         }
+        // 取出响应比最高者，为它计一个时间片后按新的响应比放回
+        let (thread, _, mut info) = self.pool.pop_first()?;
+        info.service_count += 1;
+        let ratio = info.ratio(self.current_time);
+        self.pool.push_back(thread.clone(), Reverse(ratio), info);
+        Some(thread)
     }
     fn remove_thread(&mut self, thread: &ThreadType) {
-        // 移除相应的线程并且确认恰移除一个线程
-        let mut removed = self.pool.drain_filter(|t| t.thread == *thread);
-        assert!(removed.next().is_some() && removed.next().is_none());
+        // 确认线程在线程池中
+        assert!(self.pool.remove(thread).is_some());
+    }
+    fn set_priority(&mut self, thread: ThreadType, priority: Priority) {
+        // 新的优先级在下一次重新计算响应比时生效
+        if let Some(info) = self.pool.info_mut(&thread) {
+            info.priority = priority.max(1);
+        }
+    }
+    fn steal_thread(&mut self, running: Option<&ThreadType>) -> Option<ThreadType> {
+        // 取出响应比最低者
+        let (thread, _, _) = self.pool.pop_last_except(running)?;
+        Some(thread)
     }
-    fn set_priority(&mut self, _thread: ThreadType, _priority: ()) {}
 }
@@ -0,0 +1,92 @@
+//! 多级反馈队列算法的调度器 [`MlfqScheduler`]
+
+use super::run_queue::RunQueue;
+use super::{Priority, Scheduler};
+use core::hash::Hash;
+
+/// 队列的级数，第 0 级优先级最高
+const LEVELS: usize = 4;
+
+/// 至少每隔多少次调度将所有线程提升到第 0 级，避免低级队列中的线程饥饿
+const BOOST_INTERVAL: usize = 100;
+
+/// 采用多级反馈队列算法的调度器
+///
+/// 新加入（包括从休眠中唤醒）的线程进入第 0 级队列；线程用完所在队列的时间片后降一级，
+/// 第 `n` 级队列的时间片为 `2^n`。总是执行最高一级非空队列的队首线程，因此交互型线程的响应更快。
+///
+/// 各级队列合并在一个以级数为键值的 [`RunQueue`] 中，附带线程在当前队列中已经执行的时间片数
+pub struct MlfqScheduler<ThreadType: Clone + Eq + Hash> {
+    /// 按照级数排列的线程池，同一级中按照加入的先后排列
+    pool: RunQueue<usize, ThreadType, usize>,
+    /// 上一次 `get_next()` 返回的线程，下一次调用时为它计时
+    running: Option<ThreadType>,
+    /// 上一次提升之后 `get_next()` 的调用次数
+    since_boost: usize,
+}
+
+/// `Default` 创建一个空的调度器
+impl<ThreadType: Clone + Eq + Hash> Default for MlfqScheduler<ThreadType> {
+    fn default() -> Self {
+        Self {
+            pool: RunQueue::default(),
+            running: None,
+            since_boost: 0,
+        }
+    }
+}
+
+impl<ThreadType: Clone + Eq + Hash> Scheduler<ThreadType> for MlfqScheduler<ThreadType> {
+    type Priority = Priority;
+
+    fn add_thread(&mut self, thread: ThreadType) {
+        self.pool.push_back(thread, 0, 0);
+    }
+    fn get_next(&mut self) -> Option<ThreadType> {
+        // 提升需要 O(n log n)，间隔至少为线程数，使每次调度的均摊时间复杂度为 O(log n)
+        self.since_boost += 1;
+        if self.since_boost >= BOOST_INTERVAL.max(self.pool.len()) {
+            self.pool.rekey(|used| {
+                *used = 0;
+                0
+            });
+            self.since_boost = 0;
+        }
+        // 上一个线程执行完了一个时间片
+        if let Some(running) = self.running.take() {
+            self.charge(running);
+        }
+        let (thread, _) = self.pool.first()?;
+        let thread = thread.clone();
+        self.running = Some(thread.clone());
+        Some(thread)
+    }
+    fn remove_thread(&mut self, thread: &ThreadType) {
+        // 确认线程在线程池中
+        assert!(self.pool.remove(thread).is_some());
+        if self.running.as_ref() == Some(thread) {
+            self.running = None;
+        }
+    }
+    /// 线程的优先级由调度器根据其行为调整，不能手动设置
+    fn set_priority(&mut self, _thread: ThreadType, _priority: Priority) {}
+    fn steal_thread(&mut self, running: Option<&ThreadType>) -> Option<ThreadType> {
+        // 取出最低一级队列的队尾
+        let (thread, _, _) = self.pool.pop_last_except(running)?;
+        Some(thread)
+    }
+}
+
+impl<ThreadType: Clone + Eq + Hash> MlfqScheduler<ThreadType> {
+    /// 为线程计一个时间片，用完所在队列的时间片后移到下一级队列的队尾（最低一级则回到本级队尾），
+    /// 否则留在本级队首
+    fn charge(&mut self, thread: ThreadType) {
+        if let Some((level, used)) = self.pool.remove(&thread) {
+            if used + 1 >= 1 << level {
+                self.pool.push_back(thread, (level + 1).min(LEVELS - 1), 0);
+            } else {
+                self.pool.push_front(thread, level, used + 1);
+            }
+        }
+    }
+}
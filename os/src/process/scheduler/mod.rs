@@ -1,27 +1,201 @@
+use crate::process::scheduler::cfs::CfsScheduler;
 use crate::process::scheduler::hrrn::HrrnScheduler;
+use crate::process::scheduler::mlfq::MlfqScheduler;
+use crate::process::scheduler::rr::RoundRobinScheduler;
+use crate::process::scheduler::stride::StrideScheduler;
+use core::hash::Hash;
 
+mod cfs;
 mod hrrn;
+mod mlfq;
+mod rr;
+mod run_queue;
+mod stride;
 
 /// 线程调度器
 ///
-/// `ThreadType` 应为 `Arc<Thread>`
+/// `ThreadType` 应为 `Arc<Thread>`，需要可以哈希以便在 O(log n) 的运行队列中查找
 ///
 /// ### 使用方法
 /// - 在每一个时间片结束后，调用 [`Scheduler::get_next()`] 来获取下一个时间片应当执行的线程。
 ///   这个线程可能是上一个时间片所执行的线程。
 /// - 当一个线程结束时，需要调用 [`Scheduler::remove_thread()`] 来将其移除。这个方法必须在
 ///   [`Scheduler::get_next()`] 之前调用。
-pub trait Scheduler<ThreadType: Clone + Eq>: Default {
+pub trait Scheduler<ThreadType: Clone + Eq + Hash>: Default {
     /// 优先级的类型
     type Priority;
     /// 向线程池中添加一个线程
     fn add_thread(&mut self, thread: ThreadType);
+    /// 将休眠了 `slept`（单位与 `time` 寄存器相同）之后被唤醒的线程加入线程池
+    ///
+    /// 默认与 [`Scheduler::add_thread()`] 相同
+    fn wake_thread(&mut self, thread: ThreadType, _slept: usize) {
+        self.add_thread(thread);
+    }
     /// 获取下一个时间段应当执行的线程
     fn get_next(&mut self) -> Option<ThreadType>;
     /// 移除一个线程
     fn remove_thread(&mut self, thread: &ThreadType);
     /// 设置线程的优先级
     fn set_priority(&mut self, thread: ThreadType, priority: Self::Priority);
+    /// 移除并返回一个除 `running` 以外的线程，用于将线程迁移到其他处理器核，没有时返回 `None`
+    ///
+    /// 应当尽量选择最近不会被调度的线程
+    fn steal_thread(&mut self, running: Option<&ThreadType>) -> Option<ThreadType>;
 }
 
-pub type SchedulerImpl<ThreadType: Clone + Eq> = HrrnScheduler<ThreadType>;
+/// 各个调度器共用的优先级，数值越大的线程获得越多的时间片
+pub type Priority = usize;
+
+/// 线程的默认优先级，即 nice 值为 0 时的优先级
+pub const DEFAULT_PRIORITY: Priority = 20;
+
+/// nice 值对应的优先级：nice 值从 -20 到 19，对应优先级从 40 到 1
+pub fn nice_to_priority(nice: isize) -> Priority {
+    (DEFAULT_PRIORITY as isize - nice) as Priority
+}
+
+/// 可以选择的调度算法
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SchedulerKind {
+    /// 最高响应比优先
+    Hrrn,
+    /// 时间片轮转
+    RoundRobin,
+    /// 步幅调度
+    Stride,
+    /// 多级反馈队列
+    Mlfq,
+    /// 完全公平调度
+    Cfs,
+}
+
+impl SchedulerKind {
+    /// 从内核命令行参数中的名字解析调度算法
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "hrrn" => Some(Self::Hrrn),
+            "rr" => Some(Self::RoundRobin),
+            "stride" => Some(Self::Stride),
+            "mlfq" => Some(Self::Mlfq),
+            "cfs" => Some(Self::Cfs),
+            _ => None,
+        }
+    }
+
+    /// 调度算法在内核命令行参数中的名字
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Hrrn => "hrrn",
+            Self::RoundRobin => "rr",
+            Self::Stride => "stride",
+            Self::Mlfq => "mlfq",
+            Self::Cfs => "cfs",
+        }
+    }
+}
+
+/// 默认的调度算法由 cargo feature 决定，都没有开启时使用 HRRN
+impl Default for SchedulerKind {
+    fn default() -> Self {
+        if cfg!(feature = "scheduler-rr") {
+            Self::RoundRobin
+        } else if cfg!(feature = "scheduler-stride") {
+            Self::Stride
+        } else if cfg!(feature = "scheduler-mlfq") {
+            Self::Mlfq
+        } else if cfg!(feature = "scheduler-cfs") {
+            Self::Cfs
+        } else {
+            Self::Hrrn
+        }
+    }
+}
+
+/// 内核使用的调度器，具体的调度算法可以在启动时选择
+pub enum SchedulerImpl<ThreadType: Clone + Eq + Hash> {
+    Hrrn(HrrnScheduler<ThreadType>),
+    RoundRobin(RoundRobinScheduler<ThreadType>),
+    Stride(StrideScheduler<ThreadType>),
+    Mlfq(MlfqScheduler<ThreadType>),
+    Cfs(CfsScheduler<ThreadType>),
+}
+
+impl<ThreadType: Clone + Eq + Hash> SchedulerImpl<ThreadType> {
+    /// 创建一个使用 `kind` 算法的空调度器
+    pub fn new(kind: SchedulerKind) -> Self {
+        match kind {
+            SchedulerKind::Hrrn => Self::Hrrn(Default::default()),
+            SchedulerKind::RoundRobin => Self::RoundRobin(Default::default()),
+            SchedulerKind::Stride => Self::Stride(Default::default()),
+            SchedulerKind::Mlfq => Self::Mlfq(Default::default()),
+            SchedulerKind::Cfs => Self::Cfs(Default::default()),
+        }
+    }
+}
+
+/// `Default` 创建一个使用默认算法的空调度器
+impl<ThreadType: Clone + Eq + Hash> Default for SchedulerImpl<ThreadType> {
+    fn default() -> Self {
+        Self::new(SchedulerKind::default())
+    }
+}
+
+impl<ThreadType: Clone + Eq + Hash> Scheduler<ThreadType> for SchedulerImpl<ThreadType> {
+    type Priority = Priority;
+
+    fn add_thread(&mut self, thread: ThreadType) {
+        match self {
+            Self::Hrrn(scheduler) => scheduler.add_thread(thread),
+            Self::RoundRobin(scheduler) => scheduler.add_thread(thread),
+            Self::Stride(scheduler) => scheduler.add_thread(thread),
+            Self::Mlfq(scheduler) => scheduler.add_thread(thread),
+            Self::Cfs(scheduler) => scheduler.add_thread(thread),
+        }
+    }
+    fn wake_thread(&mut self, thread: ThreadType, slept: usize) {
+        match self {
+            Self::Hrrn(scheduler) => scheduler.wake_thread(thread, slept),
+            Self::RoundRobin(scheduler) => scheduler.wake_thread(thread, slept),
+            Self::Stride(scheduler) => scheduler.wake_thread(thread, slept),
+            Self::Mlfq(scheduler) => scheduler.wake_thread(thread, slept),
+            Self::Cfs(scheduler) => scheduler.wake_thread(thread, slept),
+        }
+    }
+    fn get_next(&mut self) -> Option<ThreadType> {
+        match self {
+            Self::Hrrn(scheduler) => scheduler.get_next(),
+            Self::RoundRobin(scheduler) => scheduler.get_next(),
+            Self::Stride(scheduler) => scheduler.get_next(),
+            Self::Mlfq(scheduler) => scheduler.get_next(),
+            Self::Cfs(scheduler) => scheduler.get_next(),
+        }
+    }
+    fn remove_thread(&mut self, thread: &ThreadType) {
+        match self {
+            Self::Hrrn(scheduler) => scheduler.remove_thread(thread),
+            Self::RoundRobin(scheduler) => scheduler.remove_thread(thread),
+            Self::Stride(scheduler) => scheduler.remove_thread(thread),
+            Self::Mlfq(scheduler) => scheduler.remove_thread(thread),
+            Self::Cfs(scheduler) => scheduler.remove_thread(thread),
+        }
+    }
+    fn set_priority(&mut self, thread: ThreadType, priority: Priority) {
+        match self {
+            Self::Hrrn(scheduler) => scheduler.set_priority(thread, priority),
+            Self::RoundRobin(scheduler) => scheduler.set_priority(thread, priority),
+            Self::Stride(scheduler) => scheduler.set_priority(thread, priority),
+            Self::Mlfq(scheduler) => scheduler.set_priority(thread, priority),
+            Self::Cfs(scheduler) => scheduler.set_priority(thread, priority),
+        }
+    }
+    fn steal_thread(&mut self, running: Option<&ThreadType>) -> Option<ThreadType> {
+        match self {
+            Self::Hrrn(scheduler) => scheduler.steal_thread(running),
+            Self::RoundRobin(scheduler) => scheduler.steal_thread(running),
+            Self::Stride(scheduler) => scheduler.steal_thread(running),
+            Self::Mlfq(scheduler) => scheduler.steal_thread(running),
+            Self::Cfs(scheduler) => scheduler.steal_thread(running),
+        }
+    }
+}
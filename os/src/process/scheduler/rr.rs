@@ -0,0 +1,45 @@
+//! 时间片轮转算法的调度器 [`RoundRobinScheduler`]
+
+use super::run_queue::RunQueue;
+use super::{Priority, Scheduler};
+use core::hash::Hash;
+
+/// 采用时间片轮转算法的调度器，所有线程依次执行一个时间片
+pub struct RoundRobinScheduler<ThreadType: Clone + Eq + Hash> {
+    /// 线程池，所有线程的键值相同，按照加入的先后排列，队首为下一个执行的线程
+    pool: RunQueue<(), ThreadType, ()>,
+}
+
+/// `Default` 创建一个空的调度器
+impl<ThreadType: Clone + Eq + Hash> Default for RoundRobinScheduler<ThreadType> {
+    fn default() -> Self {
+        Self {
+            pool: RunQueue::default(),
+        }
+    }
+}
+
+impl<ThreadType: Clone + Eq + Hash> Scheduler<ThreadType> for RoundRobinScheduler<ThreadType> {
+    type Priority = Priority;
+
+    fn add_thread(&mut self, thread: ThreadType) {
+        self.pool.push_back(thread, (), ());
+    }
+    fn get_next(&mut self) -> Option<ThreadType> {
+        // 取出队首的线程，再放回队尾
+        let (thread, _, _) = self.pool.pop_first()?;
+        self.pool.push_back(thread.clone(), (), ());
+        Some(thread)
+    }
+    fn remove_thread(&mut self, thread: &ThreadType) {
+        // 确认线程在线程池中
+        assert!(self.pool.remove(thread).is_some());
+    }
+    /// 所有线程的时间片相同，不考虑优先级
+    fn set_priority(&mut self, _thread: ThreadType, _priority: Priority) {}
+    fn steal_thread(&mut self, running: Option<&ThreadType>) -> Option<ThreadType> {
+        // 取出离队首最远的线程
+        let (thread, _, _) = self.pool.pop_last_except(running)?;
+        Some(thread)
+    }
+}
@@ -0,0 +1,135 @@
+//! 按键值排序的运行队列 [`RunQueue`]
+extern crate alloc;
+
+use alloc::collections::BTreeMap;
+use core::hash::Hash;
+use hashbrown::HashMap;
+
+/// 线程在队列中的位置和附带的调度信息
+struct Entry<Key, Info> {
+    /// 排序的键值
+    key: Key,
+    /// 键值相同时的次序
+    sequence: isize,
+    /// 不参与排序的调度信息
+    info: Info,
+}
+
+/// 按照键值从小到大排列的运行队列，取出、加入和移除线程的时间复杂度均为 O(log n)
+///
+/// 键值相同的线程按照加入的先后排列，也可以用 [`RunQueue::push_front()`] 排在它们前面。
+/// 每个线程可以附带不参与排序的调度信息 `Info`
+pub struct RunQueue<Key: Ord + Copy, ThreadType: Clone + Eq + Hash, Info> {
+    /// 按照（键值，次序）排序的线程
+    tree: BTreeMap<(Key, isize), ThreadType>,
+    /// 线程在 `tree` 中的位置，用于移除线程和修改键值
+    entries: HashMap<ThreadType, Entry<Key, Info>>,
+    /// 下一个排在队尾的线程的次序
+    back_sequence: isize,
+    /// 下一个排在队首的线程的次序
+    front_sequence: isize,
+}
+
+/// `Default` 创建一个空的队列
+impl<Key: Ord + Copy, ThreadType: Clone + Eq + Hash, Info> Default
+    for RunQueue<Key, ThreadType, Info>
+{
+    fn default() -> Self {
+        Self {
+            tree: BTreeMap::new(),
+            entries: HashMap::new(),
+            back_sequence: 0,
+            front_sequence: -1,
+        }
+    }
+}
+
+impl<Key: Ord + Copy, ThreadType: Clone + Eq + Hash, Info> RunQueue<Key, ThreadType, Info> {
+    /// 加入线程，排在键值相同的线程之后
+    pub fn push_back(&mut self, thread: ThreadType, key: Key, info: Info) {
+        let sequence = self.back_sequence;
+        self.back_sequence += 1;
+        self.insert(thread, key, sequence, info);
+    }
+
+    /// 加入线程，排在键值相同的线程之前
+    pub fn push_front(&mut self, thread: ThreadType, key: Key, info: Info) {
+        let sequence = self.front_sequence;
+        self.front_sequence -= 1;
+        self.insert(thread, key, sequence, info);
+    }
+
+    /// 键值最小的线程及其键值
+    pub fn first(&self) -> Option<(&ThreadType, Key)> {
+        self.tree
+            .iter()
+            .next()
+            .map(|(&(key, _), thread)| (thread, key))
+    }
+
+    /// 取出键值最小的线程，返回线程、键值和调度信息
+    pub fn pop_first(&mut self) -> Option<(ThreadType, Key, Info)> {
+        let position = *self.tree.keys().next()?;
+        let thread = self.tree.remove(&position).unwrap();
+        let entry = self.entries.remove(&thread).unwrap();
+        Some((thread, entry.key, entry.info))
+    }
+
+    /// 取出除 `except` 以外键值最大的线程，返回线程、键值和调度信息
+    pub fn pop_last_except(
+        &mut self,
+        except: Option<&ThreadType>,
+    ) -> Option<(ThreadType, Key, Info)> {
+        let thread = self
+            .tree
+            .values()
+            .rev()
+            .find(|thread| Some(*thread) != except)?
+            .clone();
+        let (key, info) = self.remove(&thread).unwrap();
+        Some((thread, key, info))
+    }
+
+    /// 移除线程，返回其键值和调度信息；线程不在队列中时返回 `None`
+    pub fn remove(&mut self, thread: &ThreadType) -> Option<(Key, Info)> {
+        let entry = self.entries.remove(thread)?;
+        self.tree.remove(&(entry.key, entry.sequence));
+        Some((entry.key, entry.info))
+    }
+
+    /// 线程的调度信息
+    pub fn info_mut(&mut self, thread: &ThreadType) -> Option<&mut Info> {
+        self.entries.get_mut(thread).map(|entry| &mut entry.info)
+    }
+
+    /// 按照调度信息重新计算所有线程的键值，键值相同的线程保持原来的先后次序
+    ///
+    /// 时间复杂度为 O(n log n)，调用者应当让它的开销分摊到至少 n 次调度上
+    pub fn rekey(&mut self, mut key_of: impl FnMut(&mut Info) -> Key) {
+        self.tree.clear();
+        for (thread, entry) in self.entries.iter_mut() {
+            entry.key = key_of(&mut entry.info);
+            self.tree
+                .insert((entry.key, entry.sequence), thread.clone());
+        }
+    }
+
+    /// 队列中的线程数
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn insert(&mut self, thread: ThreadType, key: Key, sequence: isize, info: Info) {
+        // 同一个线程不能重复加入
+        assert!(self.entries.get(&thread).is_none());
+        self.tree.insert((key, sequence), thread.clone());
+        self.entries.insert(
+            thread,
+            Entry {
+                key,
+                sequence,
+                info,
+            },
+        );
+    }
+}
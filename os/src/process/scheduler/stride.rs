@@ -0,0 +1,61 @@
+//! 步幅调度算法的调度器 [`StrideScheduler`]
+
+use super::run_queue::RunQueue;
+use super::{Priority, Scheduler, DEFAULT_PRIORITY};
+use core::hash::Hash;
+
+/// 步幅的基准，优先级为 `p` 的线程每次被调度后，行程增加 `BIG_STRIDE / p`
+const BIG_STRIDE: usize = 1 << 20;
+
+/// 采用步幅调度算法的调度器，线程获得的时间片与优先级成正比
+pub struct StrideScheduler<ThreadType: Clone + Eq + Hash> {
+    /// 最近一次被调度的线程的行程，新加入的线程从这里开始，避免长期占用处理器
+    current_pass: usize,
+    /// 按照行程排列的线程池，附带每次被调度后行程的增量（与优先级成反比）
+    pool: RunQueue<usize, ThreadType, usize>,
+}
+
+/// `Default` 创建一个空的调度器
+impl<ThreadType: Clone + Eq + Hash> Default for StrideScheduler<ThreadType> {
+    fn default() -> Self {
+        Self {
+            current_pass: 0,
+            pool: RunQueue::default(),
+        }
+    }
+}
+
+impl<ThreadType: Clone + Eq + Hash> Scheduler<ThreadType> for StrideScheduler<ThreadType> {
+    type Priority = Priority;
+
+    fn add_thread(&mut self, thread: ThreadType) {
+        self.pool
+            .push_back(thread, self.current_pass, stride_of(DEFAULT_PRIORITY));
+    }
+    fn get_next(&mut self) -> Option<ThreadType> {
+        // 取出行程最小者，增加其行程后放回
+        let (thread, pass, stride) = self.pool.pop_first()?;
+        self.current_pass = pass;
+        self.pool.push_back(thread.clone(), pass + stride, stride);
+        Some(thread)
+    }
+    fn remove_thread(&mut self, thread: &ThreadType) {
+        // 确认线程在线程池中
+        assert!(self.pool.remove(thread).is_some());
+    }
+    fn set_priority(&mut self, thread: ThreadType, priority: Priority) {
+        if let Some(stride) = self.pool.info_mut(&thread) {
+            *stride = stride_of(priority);
+        }
+    }
+    fn steal_thread(&mut self, running: Option<&ThreadType>) -> Option<ThreadType> {
+        // 取出行程最大者
+        let (thread, _, _) = self.pool.pop_last_except(running)?;
+        Some(thread)
+    }
+}
+
+/// 优先级对应的步幅，优先级至少为 1
+fn stride_of(priority: Priority) -> usize {
+    BIG_STRIDE / priority.max(1)
+}
@@ -0,0 +1,130 @@
+//! 计数信号量 [`Semaphore`]
+//!
+//! 与互斥锁一样保存在进程的表中，用户程序通过 [`SemID`] 引用
+
+use core::mem::size_of;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use super::alloc::sync::Arc;
+use crate::kernel::SyscallResult;
+use crate::process::condvar::Condvar;
+use crate::process::PROCESSOR;
+use lib_redos::{SemID, EFAULT, EINTR, EINVAL};
+
+/// 通过阻塞线程实现的计数信号量
+#[derive(Default)]
+pub struct Semaphore {
+    /// 剩余的资源数
+    count: AtomicUsize,
+    /// 等待资源的线程
+    waiters: Condvar,
+}
+
+impl Semaphore {
+    /// 创建资源数为 `value` 的信号量
+    pub fn new(value: usize) -> Self {
+        Self {
+            count: AtomicUsize::new(value),
+            waiters: Condvar::default(),
+        }
+    }
+
+    /// 复制当前的资源数，用于 fork
+    pub fn fork(&self) -> Self {
+        Self::new(self.count.load(Ordering::Relaxed))
+    }
+
+    /// 取走一个资源，没有资源时休眠，被唤醒后重新尝试
+    pub fn wait(&self) -> SyscallResult {
+        loop {
+            let count = self.count.load(Ordering::Relaxed);
+            if count > 0 {
+                let res = self.count.compare_exchange(
+                    count,
+                    count - 1,
+                    Ordering::Acquire,
+                    Ordering::Relaxed,
+                );
+                if res.is_ok() {
+                    return SyscallResult::Proceed(0);
+                }
+                continue;
+            }
+            let waited = self
+                .waiters
+                .wait_while(|| self.count.load(Ordering::Relaxed) == 0);
+            if waited.is_err() {
+                return SyscallResult::Proceed(-EINTR);
+            }
+        }
+    }
+
+    /// 归还一个资源，唤醒一个等待的线程
+    pub fn post(&self) -> SyscallResult {
+        self.count.fetch_add(1, Ordering::Release);
+        self.waiters.notify_one();
+        SyscallResult::Proceed(0)
+    }
+}
+
+/// 创建资源数为 `value` 的信号量，将编号写入 `sem_id`
+pub(crate) fn sys_sem_create(sem_id: *mut SemID, value: usize) -> SyscallResult {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    if process
+        .prepare_user_access(sem_id as usize, size_of::<SemID>(), true)
+        .is_err()
+    {
+        return SyscallResult::Proceed(-EFAULT);
+    }
+    unsafe { *sem_id = process.create_semaphore(value) };
+    SyscallResult::Proceed(0)
+}
+
+/// 等待信号量，线程被要求终止时返回 `-EINTR`
+pub(crate) fn sys_sem_wait(sem_id: *const SemID) -> SyscallResult {
+    match find_semaphore(sem_id) {
+        Ok(semaphore) => semaphore.wait(),
+        Err(e) => SyscallResult::Proceed(-e),
+    }
+}
+
+/// 释放信号量
+pub(crate) fn sys_sem_post(sem_id: *const SemID) -> SyscallResult {
+    match find_semaphore(sem_id) {
+        Ok(semaphore) => semaphore.post(),
+        Err(e) => SyscallResult::Proceed(-e),
+    }
+}
+
+/// 销毁信号量，之后不能再通过这个编号使用它
+pub(crate) fn sys_sem_destroy(sem_id: *const SemID) -> SyscallResult {
+    let id = match read_sem_id(sem_id) {
+        Ok(id) => id,
+        Err(e) => return SyscallResult::Proceed(-e),
+    };
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    let removed = process.inner().sem_table.remove(&id);
+    match removed {
+        Some(_) => SyscallResult::Proceed(0),
+        None => SyscallResult::Proceed(-EINVAL),
+    }
+}
+
+/// 从用户内存中读取信号量编号，找到当前进程中对应的信号量
+///
+/// 返回信号量的引用，以便在休眠前释放进程的锁；出错时返回错误码
+fn find_semaphore(sem_id: *const SemID) -> Result<Arc<Semaphore>, isize> {
+    let id = read_sem_id(sem_id)?;
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    let semaphore = process.inner().sem_table.get(&id).cloned();
+    semaphore.ok_or(EINVAL)
+}
+
+/// 从用户内存中读取信号量编号
+fn read_sem_id(sem_id: *const SemID) -> Result<SemID, isize> {
+    let process = PROCESSOR.lock().current_thread().process.clone();
+    process
+        .prepare_user_access(sem_id as usize, size_of::<SemID>(), false)
+        .map_err(|_| EFAULT)?;
+    Ok(unsafe { *sem_id })
+}
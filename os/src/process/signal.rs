@@ -0,0 +1,282 @@
+//! 信号的发送和处理
+//!
+//! 信号的处理方式保存在进程中，待处理和屏蔽的信号保存在线程中。
+//! 每次从中断返回用户态之前（见 [`handle_signals`]）检查当前线程的待处理信号：
+//! 默认处理方式为终止、忽略或暂停；设置了处理函数时，在用户栈上保存现场并跳转到处理函数，
+//! 处理函数返回后通过 sigreturn 恢复现场。
+//!
+//! 被捕获的信号只会在线程回到用户态时处理，不会唤醒正在休眠的线程；
+//! 而终止、暂停和继续会在发送时立即生效。
+
+extern crate alloc;
+
+use super::process::Process;
+use super::processor::exit_current_thread;
+use super::thread::Thread;
+use super::PROCESSOR;
+use crate::interrupt::context::Context;
+use crate::KResult;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::mem::size_of;
+use lib_redos::{
+    SigAction, SigSet, NSIG, SIGCHLD, SIGCONT, SIGKILL, SIGNAL_EXIT_BASE, SIGSEGV, SIGSTOP,
+    SIGTSTP, SIG_DFL, SIG_IGN,
+};
+
+/// 不能被捕获、忽略或屏蔽的信号
+pub const UNBLOCKABLE: SigSet = bit(SIGKILL) | bit(SIGSTOP);
+
+/// 信号在 [`SigSet`] 中对应的位
+pub const fn bit(signal: usize) -> SigSet {
+    1 << signal
+}
+
+/// 信号编号是否有效
+pub fn is_valid(signal: usize) -> bool {
+    signal > 0 && signal < NSIG
+}
+
+/// 信号的默认处理方式
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum DefaultAction {
+    /// 终止进程
+    Terminate,
+    /// 忽略
+    Ignore,
+    /// 暂停进程
+    Stop,
+    /// 继续执行被暂停的进程（在发送时已经生效）
+    Continue,
+}
+
+fn default_action(signal: usize) -> DefaultAction {
+    match signal {
+        SIGCHLD => DefaultAction::Ignore,
+        SIGCONT => DefaultAction::Continue,
+        SIGSTOP | SIGTSTP => DefaultAction::Stop,
+        _ => DefaultAction::Terminate,
+    }
+}
+
+/// 执行处理函数之前保存在用户栈上的内容，sigreturn 时恢复
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SignalFrame {
+    /// 被信号打断时的现场
+    context: Context,
+    /// 被信号打断时屏蔽的信号
+    mask: SigSet,
+}
+
+/// 向进程发送信号
+///
+/// 向当前进程发送且当前线程没有屏蔽时，信号交给当前线程，使其在返回用户态之前立即处理；
+/// 否则交给进程中第一个没有屏蔽它的线程（都屏蔽时交给第一个线程）。
+/// 被忽略的信号直接丢弃；init 进程不会被默认处理方式终止或暂停。
+pub fn send_signal(process: &Arc<Process>, signal: usize) {
+    let (action, mut threads) = {
+        let inner = process.inner();
+        // 已经退出的进程不再处理信号
+        if inner.exit_code.is_some() {
+            return;
+        }
+        let threads: Vec<Arc<Thread>> = inner.threads.values().filter_map(Weak::upgrade).collect();
+        (inner.signal_actions[signal], threads)
+    };
+    threads.sort_by_key(|thread| thread.id);
+
+    // 继续和暂停互相抵消，继续执行不受处理方式的影响
+    match signal {
+        SIGCONT => {
+            for thread in threads.iter() {
+                thread.inner().pending &= !(bit(SIGSTOP) | bit(SIGTSTP));
+            }
+            process.inner().stopped = false;
+            process.continued.notify_all();
+        }
+        SIGSTOP | SIGTSTP => {
+            for thread in threads.iter() {
+                thread.inner().pending &= !bit(SIGCONT);
+            }
+        }
+        _ => {}
+    }
+
+    let default = match action.handler {
+        SIG_IGN => return,
+        SIG_DFL if process.is_init() => return,
+        SIG_DFL => Some(default_action(signal)),
+        _ => None,
+    };
+    if matches!(
+        default,
+        Some(DefaultAction::Ignore) | Some(DefaultAction::Continue)
+    ) {
+        return;
+    }
+
+    let current_thread = PROCESSOR.lock().current_thread();
+    let is_current = current_thread.process.pid == process.pid;
+    let target = if is_current && current_thread.inner().mask & bit(signal) == 0 {
+        Some(&current_thread)
+    } else {
+        threads
+            .iter()
+            .find(|thread| thread.inner().mask & bit(signal) == 0)
+            .or_else(|| threads.first())
+    };
+    let target = match target {
+        Some(thread) => thread,
+        None => return,
+    };
+    let blocked = target.inner().mask & bit(signal) != 0;
+    match default {
+        // 暂停立即生效，进程中的线程会在回到用户态之前休眠
+        Some(DefaultAction::Stop) if !blocked => process.inner().stopped = true,
+        // 终止其他进程不必等待其线程回到用户态；当前进程在返回用户态之前终止
+        Some(DefaultAction::Terminate) if !blocked && !is_current => terminate(process, signal),
+        _ => target.inner().pending |= bit(signal),
+    }
+}
+
+/// 向线程发送由它自己引起的信号（例如非法访问内存）
+///
+/// 信号即使被屏蔽也会处理；如果信号被忽略，则恢复为默认处理方式。
+/// `address` 为引起异常的地址，会作为处理函数的第二个参数
+pub fn force_signal(thread: &Thread, signal: usize, address: usize) {
+    {
+        let mut process_inner = thread.process.inner();
+        let action = &mut process_inner.signal_actions[signal];
+        if action.handler == SIG_IGN {
+            *action = SigAction::default();
+        }
+    }
+    let mut inner = thread.inner();
+    inner.pending |= bit(signal);
+    inner.mask &= !bit(signal);
+    inner.fault_address = address;
+}
+
+/// 在返回用户态之前处理当前线程的信号
+///
+/// 当前线程被终止时不再返回；进程被暂停时线程在这里休眠，继续执行后重新处理信号
+pub fn handle_signals(context: &mut Context) {
+    while let Some(process) = deliver_signals(context) {
+        // 线程被要求终止时不再等待，由调用者结束线程
+        let continued = process.continued.wait_while(|| process.inner().stopped);
+        if continued.is_err() {
+            return;
+        }
+    }
+}
+
+/// 处理当前线程的待处理信号，进程被暂停时返回这个进程
+///
+/// 每次最多进入一个处理函数，其余的信号等到下一次回到用户态时处理
+fn deliver_signals(context: &mut Context) -> Option<Arc<Process>> {
+    let thread = PROCESSOR.lock().current_thread();
+    let process = thread.process.clone();
+    if !process.is_user {
+        return None;
+    }
+    loop {
+        if process.inner().stopped {
+            return Some(process);
+        }
+        let signal = {
+            let mut inner = thread.inner();
+            let deliverable = inner.pending & !inner.mask;
+            if deliverable == 0 {
+                return None;
+            }
+            let signal = deliverable.trailing_zeros() as usize;
+            inner.pending &= !bit(signal);
+            signal
+        };
+        let action = process.inner().signal_actions[signal];
+        match action.handler {
+            SIG_IGN => {}
+            SIG_DFL => match default_action(signal) {
+                DefaultAction::Ignore | DefaultAction::Continue => {}
+                DefaultAction::Stop => process.inner().stopped = true,
+                DefaultAction::Terminate => {
+                    terminate(&process, signal);
+                    drop(thread);
+                    drop(process);
+                    exit_current_thread();
+                }
+            },
+            _ => {
+                // 无法在用户栈上保存现场时，只能终止进程
+                if push_frame(&thread, context, signal, &action).is_err() {
+                    terminate(&process, SIGSEGV);
+                    drop(thread);
+                    drop(process);
+                    exit_current_thread();
+                }
+                return None;
+            }
+        }
+    }
+}
+
+/// 在用户栈上保存现场，并让线程从处理函数开始执行
+///
+/// 处理函数的参数为信号编号和引起异常的地址（不是由异常引起时为 0），
+/// 执行期间屏蔽当前信号和 `action.mask` 中的信号，返回地址为 `action.restorer`
+fn push_frame(
+    thread: &Thread,
+    context: &mut Context,
+    signal: usize,
+    action: &SigAction,
+) -> KResult<()> {
+    let size = size_of::<SignalFrame>();
+    let sp = context
+        .sp()
+        .checked_sub(size)
+        .ok_or("user stack overflow")?
+        & !0xf;
+    thread.process.prepare_user_access(sp, size, true)?;
+    let (mask, address) = {
+        let mut inner = thread.inner();
+        let mask = inner.mask;
+        inner.mask |= (action.mask | bit(signal)) & !UNBLOCKABLE;
+        (mask, core::mem::take(&mut inner.fault_address))
+    };
+    unsafe {
+        *(sp as *mut SignalFrame) = SignalFrame {
+            context: *context,
+            mask,
+        };
+    }
+    context
+        .set_sp(sp)
+        .set_ra(action.restorer)
+        .set_arguments(&[signal, address]);
+    context.sepc = action.handler;
+    Ok(())
+}
+
+/// 从处理函数返回，恢复用户栈上保存的现场和屏蔽的信号，返回被打断时的 a0
+pub fn restore_frame(thread: &Thread, context: &mut Context) -> KResult<usize> {
+    let sp = context.sp();
+    thread
+        .process
+        .prepare_user_access(sp, size_of::<SignalFrame>(), false)?;
+    let frame = unsafe { (sp as *const SignalFrame).read_unaligned() };
+    // 不能使用用户栈上的 sstatus，否则用户程序可以借此进入内核态
+    let sstatus = context.sstatus;
+    *context = frame.context;
+    context.sstatus = sstatus;
+    thread.inner().mask = frame.mask & !UNBLOCKABLE;
+    Ok(context.x[10])
+}
+
+/// 以信号终止进程，返回值为 `SIGNAL_EXIT_BASE + signal`
+///
+/// 如果当前线程属于这个进程，需要由调用者终止
+fn terminate(process: &Process, signal: usize) {
+    debug_println!("process {} killed by signal {}", process.pid, signal);
+    process.exit_group(SIGNAL_EXIT_BASE + signal as isize);
+}
@@ -0,0 +1,46 @@
+# 线程在内核中的切换
+#
+# 只需要保存被调用者保存的寄存器（ra、sp 和 s0 至 s11），其他寄存器已经由调用 __switch 的 Rust 代码保存
+
+.altmacro
+# 寄存器宽度对应的字节数
+.set    REG_SIZE, 8
+
+# 宏：将 s{n} 寄存器存到 a0 指向的 SwitchContext 中
+.macro SAVE_S n
+    sd s\n, (\n + 2) * REG_SIZE(a0)
+.endm
+
+# 宏：从 a1 指向的 SwitchContext 中取出 s{n} 寄存器
+.macro LOAD_S n
+    ld s\n, (\n + 2) * REG_SIZE(a1)
+.endm
+
+    .section .text
+    .globl __switch
+# __switch(current: *mut SwitchContext, next: *const SwitchContext)
+# 将当前的寄存器保存到 current，从 next 中恢复寄存器，然后返回到 next 保存的 ra
+__switch:
+    sd      ra, 0 * REG_SIZE(a0)
+    sd      sp, 1 * REG_SIZE(a0)
+    .set    n, 0
+    .rept   12
+        SAVE_S  %n
+        .set    n, n + 1
+    .endr
+
+    ld      ra, 0 * REG_SIZE(a1)
+    ld      sp, 1 * REG_SIZE(a1)
+    .set    n, 0
+    .rept   12
+        LOAD_S  %n
+        .set    n, n + 1
+    .endr
+    ret
+
+    .globl __trap_return
+# 新线程第一次被切换到时从这里开始执行
+# 此时 sp 指向内核栈顶的 Context，交给 __restore 恢复
+__trap_return:
+    mv      a0, sp
+    j       __restore
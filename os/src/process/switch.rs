@@ -0,0 +1,70 @@
+//! 线程在内核中的切换 [`SwitchContext`]
+//!
+//! 每个处理器核在启动栈上执行调度循环（见 [`run`](super::processor::run)），
+//! 线程让出处理器核时切换回调度循环，由调度循环选出下一个线程再切换过去。
+//! 线程切换时停在内核栈中的任意位置，因此可以在系统调用的中途休眠，被唤醒后从原处继续执行
+
+use crate::interrupt::context::Context;
+use crate::smp::{hart_id, MAX_HARTS};
+
+global_asm!(include_str!("./switch.asm"));
+
+extern "C" {
+    /// `switch.asm` 中切换寄存器的函数
+    fn __switch(current: *mut SwitchContext, next: *const SwitchContext);
+    /// `switch.asm` 中新线程的入口，从内核栈顶的 Context 返回
+    fn __trap_return();
+}
+
+/// 线程在内核中切换时保存的寄存器
+///
+/// 只包括被调用者保存的寄存器，其余的寄存器由调用 `__switch` 的代码负责保存
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SwitchContext {
+    /// 切换回来时的返回地址
+    ra: usize,
+    /// 内核栈指针
+    sp: usize,
+    /// s0 至 s11
+    s: [usize; 12],
+}
+
+/// 每个处理器核的调度循环切换出去时保存的寄存器
+static mut SCHEDULER_CONTEXTS: [SwitchContext; MAX_HARTS] = [SwitchContext::EMPTY; MAX_HARTS];
+
+impl SwitchContext {
+    /// 全部为 0 的寄存器
+    const EMPTY: Self = Self {
+        ra: 0,
+        sp: 0,
+        s: [0; 12],
+    };
+
+    /// 新线程的寄存器：第一次被切换到时，从内核栈顶的 `context` 进入线程
+    pub fn new(context: *mut Context) -> Self {
+        Self {
+            ra: __trap_return as usize,
+            sp: context as usize,
+            ..Self::EMPTY
+        }
+    }
+}
+
+/// 从当前处理器核的调度循环切换到线程，线程让出处理器核后返回
+///
+/// # Safety
+///
+/// `next` 必须是一个没有在执行的线程的寄存器
+pub unsafe fn switch_to(next: *const SwitchContext) {
+    __switch(&mut SCHEDULER_CONTEXTS[hart_id()], next);
+}
+
+/// 当前线程保存寄存器到 `current`，切换回当前处理器核的调度循环，再次被调度时返回
+///
+/// # Safety
+///
+/// `current` 必须是当前线程的寄存器，且切换期间不能持有任何锁
+pub unsafe fn switch_to_scheduler(current: *mut SwitchContext) {
+    __switch(current, &SCHEDULER_CONTEXTS[hart_id()]);
+}
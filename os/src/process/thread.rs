@@ -4,18 +4,21 @@ use super::*;
 use crate::fs::{INodeExt, ROOT_INODE};
 use crate::interrupt::context::Context;
 use crate::kernel::SyscallResult;
-use crate::kernel::SyscallResult::{Park, Proceed};
+use crate::kernel::SyscallResult::Proceed;
 use crate::memory::addr::VirtualAddress;
 use crate::memory::mapping::Flags;
 use crate::memory::range::Range;
 use crate::process::condvar::Condvar;
-use crate::process::kernel_stack::KERNEL_STACK;
+use crate::process::kernel_stack::KernelStack;
 use crate::process::process::Process;
+use crate::process::switch::{switch_to, SwitchContext};
 use crate::process::thread::ThreadState::{Dead, Runnable};
 use crate::KResult;
-use alloc::sync::Arc;
+use alloc::sync::{Arc, Weak};
 use core::ffi::c_void;
 use core::hash::{Hash, Hasher};
+use core::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
+use lib_redos::{SigSet, EINTR};
 use spin::Mutex;
 use xmas_elf::ElfFile;
 
@@ -23,7 +26,7 @@ use xmas_elf::ElfFile;
 pub type ThreadID = isize;
 
 /// 线程计数，用于设置线程 ID
-static mut THREAD_COUNTER: ThreadID = 0;
+static THREAD_COUNTER: AtomicIsize = AtomicIsize::new(0);
 
 /// 线程的信息
 pub struct Thread {
@@ -31,6 +34,10 @@ pub struct Thread {
     pub id: ThreadID,
     /// 线程的栈
     pub stack: Range<VirtualAddress>,
+    /// 线程的内核栈，栈顶保存着线程的 Context
+    pub kernel_stack: KernelStack,
+    /// 线程的寄存器是否还在某个处理器核上（正在执行，或正在切换出去）
+    running: AtomicBool,
     /// 所属的进程
     pub process: Arc<Process>,
     /// 用 `Mutex` 包装一些可变的变量
@@ -48,32 +55,59 @@ pub enum ThreadState {
 
 /// 线程中需要可变的部分
 pub struct ThreadInner {
-    /// 线程执行上下文
-    ///
-    /// 当且仅当线程被暂停执行时，`context` 为 `Some`
-    pub context: Option<Context>,
+    /// 线程在内核中切换出去时保存的寄存器
+    pub switch_context: SwitchContext,
     pub state: ThreadState,
+    /// 线程已经被要求终止，会在下一次离开内核之前结束
+    ///
+    /// 休眠的线程被要求终止时会被唤醒，从阻塞的地方返回
+    pub killed: bool,
+    /// 已经发送给线程、尚未处理的信号
+    pub pending: SigSet,
+    /// 线程屏蔽的信号，被屏蔽的信号会一直等待，直到解除屏蔽
+    pub mask: SigSet,
+    /// 最近一次异常的地址，传给对应信号的处理函数
+    pub fault_address: usize,
+    /// nice 值，从 -20 到 19，越小优先级越高
+    pub nice: isize,
+    /// 最近一次开始休眠时 `time` 寄存器的值，唤醒时据此计算休眠的时长
+    pub sleep_time: usize,
+    /// 线程所在的调度器属于哪个处理器核
+    pub hart: usize,
 }
 
 impl Thread {
-    /// 准备执行一个线程
+    /// 从当前处理器核的调度循环切换到这个线程，线程让出处理器核后返回
     ///
-    /// 激活对应进程的页表，并返回其 Context
-    pub fn prepare(&self) -> *mut Context {
+    /// 激活对应进程的页表，线程从上一次切换出去的地方继续执行
+    pub fn run(&self) {
+        // 线程可能在另一个处理器核上进入休眠之后、切换出去之前就被唤醒，此时等待那个处理器核保存寄存器
+        while self
+            .running
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::sync::atomic::spin_loop_hint();
+        }
         // 激活页表
         self.process.inner().memory_set.activate();
-        // 取出 Context
-        let parked_frame = self.inner().context.take().unwrap();
-        // 将 Context 放至内核栈顶
-        unsafe { KERNEL_STACK.push_context(parked_frame) }
+        self.kernel_stack.prepare();
+        let switch_context = &self.inner().switch_context as *const SwitchContext;
+        unsafe { switch_to(switch_context) };
+        // 线程已经切换出去
+        self.running.store(false, Ordering::Release);
     }
 
-    /// 发生时钟中断后暂停线程，保存状态
-    pub fn park(&self, context: Context) {
-        // 检查目前线程内的 context 应当为 None
-        assert!(self.inner().context.is_none());
-        // 将 Context 保存到线程中
-        self.inner().context.replace(context);
+    /// 线程是否正在某个处理器核上执行（包括正在切换出去）
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire)
+    }
+
+    /// 内核栈顶的 Context
+    ///
+    /// 只能在线程开始执行之前修改，或者由线程自己在中断处理中修改
+    pub fn context(&self) -> *mut Context {
+        self.kernel_stack.context()
     }
 
     /// 当前进程创建一个新的线程
@@ -84,7 +118,16 @@ impl Thread {
             entry_point,
             Some(&[args as usize]),
         )?;
-        t.as_ref().inner().context.as_mut().unwrap().set_ra(exit_fn);
+        let (mask, nice) = {
+            let inner = current_thread.inner();
+            (inner.mask, inner.nice)
+        };
+        unsafe { (*t.context()).set_ra(exit_fn) };
+        let mut inner = t.inner();
+        // 新线程继承创建者屏蔽的信号和 nice 值
+        inner.mask = mask;
+        inner.nice = nice;
+        drop(inner);
         Ok(t)
     }
 
@@ -100,18 +143,55 @@ impl Thread {
         // 构建线程的 Context
         let context = Context::new(stack.end.into(), entry_point, arguments, process.is_user);
 
-        // 打包成线程
+        Self::with_context(process, stack, context)
+    }
+
+    /// 在 fork 出的子进程中复制当前线程
+    ///
+    /// 子线程使用相同的栈地址（已在子进程的内存空间中复制），从 `context` 处继续执行，
+    /// 而 fork 在子线程中的返回值为 0。子线程屏蔽的信号和 nice 值与当前线程相同，但没有待处理的信号
+    pub fn fork(&self, process: Arc<Process>, context: &Context) -> KResult<Arc<Thread>> {
+        let mut context = *context;
+        context.x[10] = 0;
+        let thread = Self::with_context(process, self.stack, context)?;
+        let (mask, nice) = {
+            let inner = self.inner();
+            (inner.mask, inner.nice)
+        };
+        let mut inner = thread.inner();
+        inner.mask = mask;
+        inner.nice = nice;
+        drop(inner);
+        Ok(thread)
+    }
+
+    /// 用给定的栈和 Context 打包成线程，并登记到所属进程中
+    ///
+    /// 为线程分配内核栈，`context` 放在内核栈顶
+    fn with_context(
+        process: Arc<Process>,
+        stack: Range<VirtualAddress>,
+        context: Context,
+    ) -> KResult<Arc<Thread>> {
+        let kernel_stack = KernelStack::new(context)?;
+        let switch_context = SwitchContext::new(kernel_stack.context());
         let thread = Arc::new(Thread {
-            id: unsafe {
-                THREAD_COUNTER += 1;
-                THREAD_COUNTER
-            },
+            id: THREAD_COUNTER.fetch_add(1, Ordering::SeqCst) + 1,
             stack,
+            kernel_stack,
+            running: AtomicBool::new(false),
             process,
             join_handle: Condvar::default(),
             inner: Mutex::new(ThreadInner {
-                context: Some(context),
+                switch_context,
                 state: Runnable,
+                killed: false,
+                pending: 0,
+                mask: 0,
+                fault_address: 0,
+                nice: 0,
+                sleep_time: 0,
+                hart: 0,
             }),
         });
 
@@ -128,10 +208,6 @@ impl Thread {
     pub fn inner(&self) -> spin::MutexGuard<ThreadInner> {
         self.inner.lock()
     }
-
-    pub fn wait(&self) {
-        self.join_handle.wait();
-    }
 }
 
 impl Drop for Thread {
@@ -172,27 +248,37 @@ impl core::fmt::Debug for Thread {
             .debug_struct("Thread")
             .field("thread_id", &self.id)
             .field("stack", &self.stack)
-            .field("context", &self.inner().context)
+            .field("kernel_stack", &self.kernel_stack)
             .finish()
     }
 }
 
+/// 等待线程结束，线程不存在时返回 -1
 pub fn sys_join(tid: ThreadID) -> SyscallResult {
     let current_thread = PROCESSOR.lock().current_thread();
-    let guard = current_thread.process.inner.lock();
-    match guard.threads.get(&tid) {
-        Some(t) => unsafe {
-            (*t.as_ptr()).wait();
-            Park(0)
-        },
-        None => Proceed(-1),
+    let thread = current_thread
+        .process
+        .inner()
+        .threads
+        .get(&tid)
+        .and_then(Weak::upgrade);
+    let thread = match thread {
+        Some(thread) => thread,
+        None => return Proceed(-1),
+    };
+    match thread
+        .join_handle
+        .wait_while(|| thread.inner().state != Dead)
+    {
+        Ok(()) => Proceed(0),
+        Err(_) => Proceed(-EINTR),
     }
 }
 
 /// 内核线程需要调用这个函数来退出
 fn kernel_thread_exit() {
     // 当前线程标记为结束
-    PROCESSOR.lock().current_thread().as_ref().inner().state = Dead;
+    PROCESSOR.lock().current_thread().as_ref().inner().killed = true;
     // 制造一个中断来交给操作系统处理
     unsafe { llvm_asm!("ebreak" :::: "volatile") };
 }
@@ -206,13 +292,7 @@ pub fn create_kernel_thread(
     // 创建线程
     let thread = Thread::new(process, entry_point, arguments).unwrap();
     // 设置线程的返回地址为 kernel_thread_exit
-    thread
-        .as_ref()
-        .inner()
-        .context
-        .as_mut()
-        .unwrap()
-        .set_ra(kernel_thread_exit as usize);
+    unsafe { (*thread.context()).set_ra(kernel_thread_exit as usize) };
     thread
 }
 
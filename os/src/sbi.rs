@@ -11,6 +11,15 @@ const SBI_REMOTE_SFENCE_VMA: usize = 6;
 const SBI_REMOTE_SFENCE_VMA_ASID: usize = 7;
 const SBI_SHUTDOWN: usize = 8;
 
+// SBI v0.2 之后的扩展，通过 a7 指定扩展编号，a6 指定函数编号
+
+/// IPI 扩展
+const EXTENSION_IPI: usize = 0x735049;
+/// RFENCE 扩展
+const EXTENSION_RFENCE: usize = 0x52464e43;
+/// HSM（处理器核状态管理）扩展
+const EXTENSION_HSM: usize = 0x48534d;
+
 /// 向控制台输出一个字符
 ///
 /// 需要注意我们不能直接使用 Rust 中的 char 类型
@@ -36,6 +45,24 @@ pub fn set_timer(time: usize) {
     sbi_call(SBI_SET_TIMER, time, 0, 0);
 }
 
+/// 启动编号为 `hart_id` 的处理器核，它会在 S 态从物理地址 `start_address` 开始执行，
+/// `a0` 为处理器核编号，`a1` 为 `opaque`
+///
+/// 成功时返回 0，处理器核不存在或已经启动时返回负的错误码
+pub fn hart_start(hart_id: usize, start_address: usize, opaque: usize) -> isize {
+    sbi_call_extension(EXTENSION_HSM, 0, [hart_id, start_address, opaque, 0])
+}
+
+/// 向 `hart_mask` 中的处理器核发送处理器间中断（软件中断）
+pub fn send_ipi(hart_mask: usize) {
+    sbi_call_extension(EXTENSION_IPI, 0, [hart_mask, 0, 0, 0]);
+}
+
+/// 令 `hart_mask` 中的处理器核刷新 `[start, start + size)` 范围的 TLB，`size` 为 `usize::MAX` 时刷新全部
+pub fn remote_sfence_vma(hart_mask: usize, start: usize, size: usize) {
+    sbi_call_extension(EXTENSION_RFENCE, 1, [hart_mask, 0, start, size]);
+}
+
 /// SBI 调用
 #[inline(always)]
 fn sbi_call(which: usize, arg0: usize, arg1: usize, arg2: usize) -> usize {
@@ -49,3 +76,19 @@ fn sbi_call(which: usize, arg0: usize, arg1: usize, arg2: usize) -> usize {
     }
     ret
 }
+
+/// SBI v0.2 的扩展调用，返回错误码
+#[inline(always)]
+fn sbi_call_extension(extension: usize, function: usize, args: [usize; 4]) -> isize {
+    let error: isize;
+    let _value: usize;
+    unsafe {
+        llvm_asm!("ecall"
+            : "={x10}" (error), "={x11}" (_value)
+            : "{x10}" (args[0]), "{x11}" (args[1]), "{x12}" (args[2]), "{x13}" (args[3]),
+              "{x16}" (function), "{x17}" (extension)
+            : "memory"
+            : "volatile");
+    }
+    error
+}
@@ -0,0 +1,130 @@
+//! 多处理器核的启动和处理器核之间的通信
+//!
+//! 由 SBI 选出的启动处理器核完成初始化后，通过 SBI 的 HSM 扩展启动其他处理器核。
+//! 每个处理器核有各自的启动栈、内核栈、[`Processor`](crate::process::processor::Processor)
+//! 和时钟中断，在内核中 `tp` 寄存器始终保存当前处理器核的编号（见 `entry.asm` 和 `interrupt.asm`）。
+//!
+//! 处理器核之间通过处理器间中断（IPI）通知对方重新调度，通过 SBI 的 RFENCE 扩展刷新其他处理器核的 TLB
+
+use crate::interrupt;
+use crate::memory::KERNEL_MAP_OFFSET;
+use crate::process::processor;
+use crate::sbi;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// 最多支持的处理器核数，需要与 `entry.asm` 中启动栈的数量一致
+pub const MAX_HARTS: usize = 8;
+
+/// 已经启动的处理器核，每一位对应一个处理器核
+static ONLINE_HARTS: AtomicUsize = AtomicUsize::new(0);
+
+/// 正在执行空闲线程的处理器核，每一位对应一个处理器核
+static IDLE_HARTS: AtomicUsize = AtomicUsize::new(0);
+
+/// 每个处理器核 `satp` 中的根页表页号，0 表示仍在使用启动页表
+static ACTIVE_ROOTS: [AtomicUsize; MAX_HARTS] = {
+    const BOOT: AtomicUsize = AtomicUsize::new(0);
+    [BOOT; MAX_HARTS]
+};
+
+/// 当前处理器核的编号
+#[inline(always)]
+pub fn hart_id() -> usize {
+    let hart_id: usize;
+    unsafe { llvm_asm!("mv $0, tp" : "=r"(hart_id) ::: "volatile") };
+    hart_id
+}
+
+/// 启动其他处理器核，由启动处理器核在完成初始化、加入初始线程之后调用
+///
+/// 依次尝试启动编号小于 [`MAX_HARTS`] 的处理器核，不存在的处理器核会被 SBI 拒绝
+pub fn init() {
+    extern "C" {
+        /// `entry.asm` 中其他处理器核的入口
+        fn _start_secondary();
+    }
+    ONLINE_HARTS.fetch_or(1 << hart_id(), Ordering::SeqCst);
+    let start_address = _start_secondary as usize - KERNEL_MAP_OFFSET;
+    for hart in (0..MAX_HARTS).filter(|&hart| hart != hart_id()) {
+        sbi::hart_start(hart, start_address, 0);
+    }
+}
+
+/// 其他处理器核的 Rust 入口
+///
+/// 初始化中断之后，从调度器中选取线程开始执行（没有线程时执行空闲线程）
+#[no_mangle]
+pub extern "C" fn secondary_main(hart_id: usize) -> ! {
+    interrupt::init_secondary();
+    // 允许内核读写用户态内存
+    unsafe { riscv::register::sstatus::set_sum() };
+    ONLINE_HARTS.fetch_or(1 << hart_id, Ordering::SeqCst);
+    println!("hart {} started", hart_id);
+
+    // 进入调度循环，启动第一个线程
+    processor::run()
+}
+
+/// 处理器核是否已经启动
+pub fn is_online(hart_id: usize) -> bool {
+    ONLINE_HARTS.load(Ordering::SeqCst) & (1 << hart_id) != 0
+}
+
+/// 记录处理器核是否正在执行空闲线程
+pub fn set_idle(hart_id: usize, idle: bool) {
+    if idle {
+        IDLE_HARTS.fetch_or(1 << hart_id, Ordering::SeqCst);
+    } else {
+        IDLE_HARTS.fetch_and(!(1 << hart_id), Ordering::SeqCst);
+    }
+}
+
+/// 除 `except` 以外任意一个空闲的处理器核
+pub fn idle_hart(except: usize) -> Option<usize> {
+    let idle = IDLE_HARTS.load(Ordering::SeqCst) & !(1 << except);
+    if idle == 0 {
+        None
+    } else {
+        Some(idle.trailing_zeros() as usize)
+    }
+}
+
+/// 向处理器核发送处理器间中断，令它重新调度
+pub fn send_ipi(hart_id: usize) {
+    sbi::send_ipi(1 << hart_id);
+}
+
+/// 刷新当前处理器核的 TLB，并通知其他已经启动的处理器核刷新（TLB shootdown）
+///
+/// 在移除所有页表共享的页表项（如内核栈区域）之后调用，否则其他处理器核可能仍在使用旧的页表项
+pub fn flush_tlb() {
+    flush_local_tlb();
+    let others = ONLINE_HARTS.load(Ordering::SeqCst) & !(1 << hart_id());
+    if others != 0 {
+        sbi::remote_sfence_vma(others, 0, usize::MAX);
+    }
+}
+
+/// 刷新当前处理器核的 TLB
+pub fn flush_local_tlb() {
+    unsafe { llvm_asm!("sfence.vma" :::: "volatile") };
+}
+
+/// 记录当前处理器核即将写入 `satp` 的根页表页号，必须在写入 `satp` 之前调用
+pub fn set_active_root(root_ppn: usize) {
+    ACTIVE_ROOTS[hart_id()].store(root_ppn, Ordering::SeqCst);
+}
+
+/// 刷新当前处理器核的 TLB，并通知正在使用根页表 `root_ppn` 的其他处理器核刷新
+///
+/// 在移除某个页表中的页表项或降低权限之后调用。没有使用这个页表的处理器核在切换到它时会刷新 TLB，
+/// 不需要通知
+pub fn flush_tlb_of(root_ppn: usize) {
+    flush_local_tlb();
+    let others = (0..MAX_HARTS)
+        .filter(|&hart| hart != hart_id() && ACTIVE_ROOTS[hart].load(Ordering::SeqCst) == root_ppn)
+        .fold(0, |mask, hart| mask | 1 << hart);
+    if others != 0 {
+        sbi::remote_sfence_vma(others, 0, usize::MAX);
+    }
+}
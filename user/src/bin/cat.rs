@@ -0,0 +1,32 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use lib_redos::O_RDONLY;
+use user_lib::redos::{close, open};
+use user_lib::{args, sys_read, sys_write, STDOUT};
+
+#[no_mangle]
+pub fn main() -> usize {
+    let mut status = 0;
+    for path in args().iter().skip(1) {
+        let fd = open(path, O_RDONLY);
+        if fd < 0 {
+            println!("cat: {}: error {}", path, -fd);
+            status = 1;
+            continue;
+        }
+        let mut buffer = [0u8; 512];
+        loop {
+            let size = sys_read(fd as usize, &mut buffer);
+            if size <= 0 {
+                break;
+            }
+            sys_write(STDOUT, &buffer[..size as usize]);
+        }
+        close(fd as usize);
+    }
+    status
+}
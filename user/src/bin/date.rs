@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use lib_redos::CLOCK_MONOTONIC;
+use user_lib::redos::{clock_gettime, gettimeofday, nanosleep};
+
+/// 每天的秒数
+const SECS_PER_DAY: i64 = 86400;
+
+/// 从 1970-01-01 起的天数换算为公历日期
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// 打印当前的 UTC 时间，并用单调时钟测量一次 250 毫秒的休眠
+#[no_mangle]
+pub fn main() -> usize {
+    let now = match gettimeofday() {
+        Ok(now) => now,
+        Err(e) => {
+            println!("date: error {}", -e);
+            return 1;
+        }
+    };
+    let (year, month, day) = civil_from_days(now.tv_sec.div_euclid(SECS_PER_DAY));
+    let secs = now.tv_sec.rem_euclid(SECS_PER_DAY);
+    println!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:06} UTC",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        now.tv_usec
+    );
+
+    let start = clock_gettime(CLOCK_MONOTONIC).unwrap();
+    nanosleep(250_000_000);
+    let end = clock_gettime(CLOCK_MONOTONIC).unwrap();
+    println!(
+        "nanosleep(250 ms) took {} us",
+        (end.as_nanos() - start.as_nanos()) / 1000
+    );
+    0
+}
@@ -0,0 +1,19 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::args;
+
+#[no_mangle]
+pub fn main() -> usize {
+    for (i, arg) in args().iter().enumerate().skip(1) {
+        if i > 1 {
+            print!(" ");
+        }
+        print!("{}", arg);
+    }
+    println!("");
+    0
+}
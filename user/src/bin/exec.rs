@@ -0,0 +1,19 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::redos::{exec, fork};
+
+#[no_mangle]
+pub fn main() -> usize {
+    println!("exec test!");
+    let ret = exec("not_exist", &[], &[]);
+    println!("exec not_exist: {}", ret);
+    if fork() == 0 {
+        exec("echo", &["echo", "hello", "from", "exec"], &[]);
+        println!("unreachable");
+    }
+    0
+}
@@ -0,0 +1,29 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::ffi::c_void;
+use user_lib::redos::{create_thread, sleep};
+
+#[no_mangle]
+pub fn main() -> usize {
+    println!("exit_group test!");
+    let mut id = 0;
+    create_thread(&mut id, spin_fn, core::ptr::null());
+    create_thread(&mut id, sleep_fn, core::ptr::null());
+    sleep(1);
+    // main 返回后，两个线程都应当被终止
+    println!("main thread exit");
+    0
+}
+
+fn spin_fn(_: *const c_void) {
+    loop {}
+}
+
+fn sleep_fn(_: *const c_void) {
+    sleep(100);
+    println!("unreachable");
+}
@@ -0,0 +1,76 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use lib_redos::{
+    MAP_ANONYMOUS, MAP_PRIVATE, PROT_EXEC, PROT_READ, PROT_WRITE, SIGILL, SIGNAL_EXIT_BASE, SIGSEGV,
+};
+use user_lib::redos::signal::on_fault;
+use user_lib::redos::{fork, mmap, mprotect, waitpid};
+
+const PAGE_SIZE: usize = 4096;
+
+/// 把引起异常的页面改为可写，返回后重新执行的写入就会成功
+extern "C" fn make_writable(signal: usize, address: usize) {
+    println!("caught signal {} at {:#x}", signal, address);
+    mprotect(
+        address & !(PAGE_SIZE - 1),
+        PAGE_SIZE,
+        PROT_READ | PROT_WRITE,
+    );
+}
+
+/// 分配一个全零的页面
+fn map_page(prot: usize) -> usize {
+    mmap(0, PAGE_SIZE, prot, MAP_PRIVATE | MAP_ANONYMOUS, 0, 0) as usize
+}
+
+/// 在子进程中执行 `f`，返回子进程的返回值
+fn run_child(f: fn()) -> isize {
+    let pid = fork();
+    if pid == 0 {
+        f();
+        user_lib::sys_exit(0);
+    }
+    let mut status = 0;
+    waitpid(pid, &mut status);
+    status
+}
+
+#[no_mangle]
+pub fn main() -> usize {
+    println!("fault test!");
+
+    // 有处理函数时可以从异常中恢复
+    on_fault(SIGSEGV, make_writable);
+    let page = map_page(PROT_READ) as *mut usize;
+    unsafe {
+        page.write_volatile(42);
+        println!("recovered, value = {}", page.read_volatile());
+    }
+
+    // 没有处理函数时进程被终止，返回值为 128 + 信号编号
+    let status = run_child(|| {
+        user_lib::redos::signal::default(SIGSEGV);
+        unsafe { (0 as *mut usize).write_volatile(1) };
+    });
+    println!(
+        "null write: {}, expected {}",
+        status,
+        SIGNAL_EXIT_BASE + SIGSEGV as isize
+    );
+    let status = run_child(|| {
+        // 全零的指令是非法指令
+        let code: extern "C" fn() =
+            unsafe { core::mem::transmute(map_page(PROT_READ | PROT_EXEC)) };
+        code();
+    });
+    println!(
+        "illegal instruction: {}, expected {}",
+        status,
+        SIGNAL_EXIT_BASE + SIGILL as isize
+    );
+    0
+}
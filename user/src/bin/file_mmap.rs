@@ -0,0 +1,74 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use lib_redos::{
+    MAP_PRIVATE, MAP_SHARED, O_CREAT, O_RDWR, O_TRUNC, PROT_READ, PROT_WRITE, SEEK_SET,
+};
+use user_lib::redos::{close, lseek, mmap, msync, munmap, open, unlink};
+use user_lib::{sys_read, sys_write};
+
+const PAGE_SIZE: usize = 4096;
+const PATH: &str = "file_mmap.txt";
+
+/// 将文件映射到内存，并从 read 系统调用读出映射中的修改
+#[no_mangle]
+pub fn main() -> usize {
+    println!("file mmap test!");
+    let fd = open(PATH, O_RDWR | O_CREAT | O_TRUNC);
+    if fd < 0 {
+        println!("open failed: {}", fd);
+        return 1;
+    }
+    let fd = fd as usize;
+    sys_write(fd, b"hello, page cache!");
+
+    // 共享映射和 read / write 使用同一份页面缓存
+    let address = mmap(0, PAGE_SIZE, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    if address < 0 {
+        println!("mmap failed: {}", address);
+        return 1;
+    }
+    let shared = unsafe { core::slice::from_raw_parts_mut(address as *mut u8, PAGE_SIZE) };
+    println!("mapped: {}", core::str::from_utf8(&shared[..18]).unwrap());
+    shared[..5].copy_from_slice(b"HELLO");
+
+    let mut buffer = [0u8; 18];
+    lseek(fd, 0, SEEK_SET);
+    sys_read(fd, &mut buffer);
+    println!(
+        "read after store: {}",
+        core::str::from_utf8(&buffer).unwrap()
+    );
+
+    // 私有映射的修改只对当前进程可见
+    let address = mmap(0, PAGE_SIZE, PROT_READ | PROT_WRITE, MAP_PRIVATE, fd, 0);
+    if address < 0 {
+        println!("mmap failed: {}", address);
+        return 1;
+    }
+    let private = unsafe { core::slice::from_raw_parts_mut(address as *mut u8, PAGE_SIZE) };
+    private[..5].copy_from_slice(b"howdy");
+    println!("private: {}", core::str::from_utf8(&private[..18]).unwrap());
+    println!("shared: {}", core::str::from_utf8(&shared[..18]).unwrap());
+
+    msync(shared.as_ptr() as usize, PAGE_SIZE);
+    munmap(shared.as_ptr() as usize, PAGE_SIZE);
+    munmap(private.as_ptr() as usize, PAGE_SIZE);
+    close(fd);
+
+    // 重新打开文件，共享映射的修改已经写回
+    let fd = open(PATH, O_RDWR);
+    if fd < 0 {
+        println!("reopen failed: {}", fd);
+        return 1;
+    }
+    let mut buffer = [0u8; 18];
+    sys_read(fd as usize, &mut buffer);
+    println!("file content: {}", core::str::from_utf8(&buffer).unwrap());
+    close(fd as usize);
+    unlink(PATH);
+    0
+}
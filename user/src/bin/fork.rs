@@ -0,0 +1,27 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::redos::fork;
+
+static mut A: i32 = 0;
+
+#[no_mangle]
+pub fn main() -> usize {
+    println!("fork test!");
+    let pid = fork();
+    if pid == 0 {
+        unsafe {
+            A += 1;
+            println!("child: A = {}", A);
+        }
+    } else {
+        unsafe {
+            A += 100;
+            println!("parent: child pid = {}, A = {}", pid, A);
+        }
+    }
+    0
+}
@@ -0,0 +1,23 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+#[no_mangle]
+pub fn main() -> usize {
+    println!("heap grow test!");
+    // 总共分配 8M，超过堆每次扩展的大小
+    let mut blocks = Vec::new();
+    for i in 0..8 {
+        let mut block = Vec::<u8>::with_capacity(0x10_0000);
+        block.resize(0x10_0000, i as u8);
+        blocks.push(block);
+    }
+    let sum: usize = blocks.iter().map(|block| block[0] as usize).sum();
+    println!("allocated {} blocks, sum = {}", blocks.len(), sum);
+    0
+}
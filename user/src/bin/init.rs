@@ -0,0 +1,26 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::redos::{exec, fork, waitpid};
+
+/// 启动 notebook，并回收所有子进程（包括交给 init 的孤儿进程）
+#[no_mangle]
+pub fn main() -> usize {
+    if fork() == 0 {
+        let ret = exec("notebook", &["notebook"], &[]);
+        println!("[init] failed to exec notebook: {}", ret);
+        return 1;
+    }
+    loop {
+        let mut status = 0;
+        let pid = waitpid(-1, &mut status);
+        if pid < 0 {
+            println!("[init] no more children");
+            return 0;
+        }
+        println!("[init] process {} exited with code {}", pid, status);
+    }
+}
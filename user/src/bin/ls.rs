@@ -0,0 +1,62 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use lib_redos::{Dirent, Stat, O_RDONLY, S_IFDIR, S_IFMT};
+use user_lib::args;
+use user_lib::redos::{close, getdents, open, stat};
+
+/// 列出目录中的文件，不带参数时列出当前目录
+#[no_mangle]
+pub fn main() -> usize {
+    let args = args();
+    let paths = if args.len() > 1 {
+        &args[1..]
+    } else {
+        &["."][..]
+    };
+    let mut status = 0;
+    for path in paths {
+        if list(path) < 0 {
+            status = 1;
+        }
+    }
+    status
+}
+
+fn list(path: &str) -> isize {
+    let mut info = Stat::default();
+    let ret = stat(path, &mut info);
+    if ret < 0 {
+        println!("ls: {}: error {}", path, -ret);
+        return ret;
+    }
+    if !info.is_dir() {
+        println!("{:>8} {}", info.size, path);
+        return 0;
+    }
+    let fd = open(path, O_RDONLY);
+    if fd < 0 {
+        println!("ls: {}: error {}", path, -fd);
+        return fd;
+    }
+    let mut dirents = [Dirent::default(); 8];
+    loop {
+        let count = getdents(fd as usize, &mut dirents);
+        if count <= 0 {
+            break;
+        }
+        for dirent in dirents[..count as usize].iter() {
+            let suffix = if dirent.mode & S_IFMT == S_IFDIR {
+                "/"
+            } else {
+                ""
+            };
+            println!("{}{}", dirent.name(), suffix);
+        }
+    }
+    close(fd as usize);
+    0
+}
@@ -0,0 +1,21 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::args;
+use user_lib::redos::mkdir;
+
+#[no_mangle]
+pub fn main() -> usize {
+    let mut status = 0;
+    for path in args().iter().skip(1) {
+        let ret = mkdir(path);
+        if ret < 0 {
+            println!("mkdir: {}: error {}", path, -ret);
+            status = 1;
+        }
+    }
+    status
+}
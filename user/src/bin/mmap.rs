@@ -0,0 +1,50 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use lib_redos::{MAP_ANONYMOUS, MAP_PRIVATE, PROT_READ, PROT_WRITE};
+use user_lib::redos::{mmap, mprotect, munmap};
+
+const PAGE_SIZE: usize = 4096;
+
+#[no_mangle]
+pub fn main() -> usize {
+    println!("mmap test!");
+    let address = mmap(
+        0,
+        4 * PAGE_SIZE,
+        PROT_READ | PROT_WRITE,
+        MAP_PRIVATE | MAP_ANONYMOUS,
+        0,
+        0,
+    );
+    if address < 0 {
+        println!("mmap failed: {}", address);
+        return 1;
+    }
+    let address = address as usize;
+    let buffer = unsafe { core::slice::from_raw_parts_mut(address as *mut u8, 4 * PAGE_SIZE) };
+    for (i, byte) in buffer.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    let sum: usize = buffer.iter().map(|&b| b as usize).sum();
+    println!("mapped 4 pages at {:#x}, sum = {}", address, sum);
+
+    // 移除中间的两页，剩下的两页仍可访问
+    munmap(address + PAGE_SIZE, 2 * PAGE_SIZE);
+    println!(
+        "after munmap: {} {}",
+        buffer[PAGE_SIZE - 1],
+        buffer[3 * PAGE_SIZE]
+    );
+
+    // 第一页改为只读，读取仍然正常，写入会导致进程被杀死
+    mprotect(address, PAGE_SIZE, PROT_READ);
+    println!("read-only page: {}", buffer[1]);
+    println!("writing to read-only page, should be killed");
+    buffer[1] = 0;
+    println!("unreachable");
+    1
+}
@@ -0,0 +1,23 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::args;
+use user_lib::redos::rename;
+
+#[no_mangle]
+pub fn main() -> usize {
+    let args = args();
+    if args.len() != 3 {
+        println!("usage: mv <source> <target>");
+        return 1;
+    }
+    let ret = rename(args[1], args[2]);
+    if ret < 0 {
+        println!("mv: error {}", -ret);
+        return 1;
+    }
+    0
+}
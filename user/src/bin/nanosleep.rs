@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::ffi::c_void;
+use lib_redos::ThreadID;
+use user_lib::redos::{create_thread, join, nanosleep};
+
+/// 每毫秒的纳秒数
+const NANOS_PER_MILLI: u64 = 1_000_000;
+
+#[no_mangle]
+pub fn main() -> usize {
+    let mut t: ThreadID = 0;
+    create_thread(&mut t, thread_fn, core::ptr::null());
+    for i in 0..6 {
+        println!("main: tick {} (every 500 ms)", i);
+        nanosleep(500 * NANOS_PER_MILLI);
+    }
+    join(t);
+    println!("main exit");
+    0
+}
+
+fn thread_fn(_: *const c_void) {
+    for i in 0..10 {
+        println!("thread: tick {} (every 300 ms)", i);
+        nanosleep(300 * NANOS_PER_MILLI);
+    }
+    println!("thread exit");
+}
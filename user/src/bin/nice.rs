@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::args;
+use user_lib::redos::{exec, setpriority};
+
+/// 用法：nice <n> <program> [args...]，以 nice 值 n 运行程序
+#[no_mangle]
+pub fn main() -> usize {
+    let args = args();
+    if args.len() < 3 {
+        eprintln!("usage: nice <n> <program> [args...]");
+        return 1;
+    }
+    let nice = match args[1].parse::<isize>() {
+        Ok(nice) => nice,
+        Err(_) => {
+            eprintln!("nice: invalid value {}", args[1]);
+            return 1;
+        }
+    };
+    let ret = setpriority(0, nice);
+    if ret < 0 {
+        eprintln!("nice: cannot set priority: {}", ret);
+        return 1;
+    }
+    let ret = exec(args[2], &args[2..], &[]);
+    eprintln!("nice: cannot exec {}: {}", args[2], ret);
+    1
+}
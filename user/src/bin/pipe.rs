@@ -0,0 +1,46 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::redos::{close, fork, pipe, waitpid};
+use user_lib::{sys_read, sys_write};
+
+const MESSAGE: &[u8] = b"hello through the pipe";
+
+#[no_mangle]
+pub fn main() -> usize {
+    println!("pipe test!");
+    let mut fds = [0usize; 2];
+    if pipe(&mut fds) < 0 {
+        println!("pipe failed");
+        return 1;
+    }
+    let pid = fork();
+    if pid == 0 {
+        // 子进程只写，写完后关闭写端
+        close(fds[0]);
+        for _ in 0..4 {
+            sys_write(fds[1], MESSAGE);
+        }
+        close(fds[1]);
+        return 0;
+    }
+    // 父进程只读，必须关闭自己的写端才能读到文件结束
+    close(fds[1]);
+    let mut buffer = [0u8; 16];
+    let mut total = 0;
+    loop {
+        let size = sys_read(fds[0], &mut buffer);
+        if size <= 0 {
+            break;
+        }
+        total += size as usize;
+    }
+    close(fds[0]);
+    let mut status = 0;
+    waitpid(pid, &mut status);
+    println!("read {} bytes, expected {}", total, MESSAGE.len() * 4);
+    0
+}
@@ -0,0 +1,77 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::ffi::c_void;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use lazy_static::*;
+use lib_redos::ThreadID;
+use user_lib::redos::semaphore::Semaphore;
+use user_lib::redos::{create_thread, join};
+
+/// 缓冲区的容量
+const CAPACITY: usize = 4;
+/// 每个生产者放入的数据个数
+const ITEMS: usize = 20;
+/// 生产者的个数
+const PRODUCERS: usize = 2;
+
+lazy_static! {
+    /// 缓冲区中的空位
+    static ref EMPTY: Semaphore = Semaphore::new(CAPACITY);
+    /// 缓冲区中的数据
+    static ref FULL: Semaphore = Semaphore::new(0);
+    /// 保护写入位置的二元信号量
+    static ref LOCK: Semaphore = Semaphore::new(1);
+}
+
+static BUFFER: [AtomicUsize; CAPACITY] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+/// 下一个写入的位置，由 `LOCK` 保护
+static TAIL: AtomicUsize = AtomicUsize::new(0);
+
+/// 多个生产者通过有界缓冲区向一个消费者传递数据，缓冲区满或空时在信号量上休眠
+#[no_mangle]
+pub fn main() -> usize {
+    let mut producers: [ThreadID; PRODUCERS] = [0; PRODUCERS];
+    for (i, producer) in producers.iter_mut().enumerate() {
+        create_thread(producer, produce, i as *const c_void);
+    }
+
+    let mut sum = 0;
+    for head in 0..ITEMS * PRODUCERS {
+        FULL.wait();
+        sum += BUFFER[head % CAPACITY].load(Ordering::Acquire);
+        EMPTY.post();
+    }
+    for producer in producers.iter() {
+        join(*producer);
+    }
+
+    // 每个生产者放入 1..=ITEMS
+    let expected = PRODUCERS * ITEMS * (ITEMS + 1) / 2;
+    println!("consumed sum {}, expected {}", sum, expected);
+    if sum == expected {
+        0
+    } else {
+        1
+    }
+}
+
+fn produce(id: *const c_void) {
+    for item in 1..=ITEMS {
+        EMPTY.wait();
+        LOCK.wait();
+        let tail = TAIL.fetch_add(1, Ordering::Relaxed);
+        BUFFER[tail % CAPACITY].store(item, Ordering::Release);
+        LOCK.post();
+        FULL.post();
+    }
+    println!("producer {} done", id as usize);
+}
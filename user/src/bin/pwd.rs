@@ -0,0 +1,22 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::redos::getcwd;
+
+#[no_mangle]
+pub fn main() -> usize {
+    let mut buffer = [0u8; 256];
+    let len = getcwd(&mut buffer);
+    if len < 0 {
+        println!("pwd: error {}", -len);
+        return 1;
+    }
+    println!(
+        "{}",
+        core::str::from_utf8(&buffer[..len as usize]).unwrap_or("?")
+    );
+    0
+}
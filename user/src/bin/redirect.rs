@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use lib_redos::{O_CLOEXEC, O_CREAT, O_TRUNC, O_WRONLY};
+use user_lib::redos::{dup2, exec, fork, open, waitpid};
+use user_lib::{args, STDOUT};
+
+/// 用法：redirect <file> <program> [args...]，将程序的标准输出重定向到文件
+#[no_mangle]
+pub fn main() -> usize {
+    let args = args();
+    if args.len() < 3 {
+        eprintln!("usage: redirect <file> <program> [args...]");
+        return 1;
+    }
+    let pid = fork();
+    if pid == 0 {
+        // 原来的文件描述符设置了 O_CLOEXEC，exec 之后只剩下标准输出指向该文件
+        let fd = open(args[1], O_WRONLY | O_CREAT | O_TRUNC | O_CLOEXEC);
+        if fd < 0 {
+            eprintln!("redirect: cannot open {}: {}", args[1], fd);
+            return 1;
+        }
+        dup2(fd as usize, STDOUT, 0);
+        let ret = exec(args[2], &args[2..], &[]);
+        eprintln!("redirect: cannot exec {}: {}", args[2], ret);
+        return 1;
+    }
+    let mut status = 0;
+    waitpid(pid, &mut status);
+    status as usize
+}
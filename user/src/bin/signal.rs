@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use lib_redos::{SIGCONT, SIGNAL_EXIT_BASE, SIGSTOP, SIGTERM, SIGUSR1, SIG_BLOCK, SIG_SETMASK};
+use user_lib::redos::signal::{kill, signal, sigprocmask};
+use user_lib::redos::{fork, sleep, waitpid};
+
+/// 子进程是否已经处理了 `SIGUSR1`
+static RECEIVED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_usr1(signal: usize) {
+    println!("child caught signal {}", signal);
+    RECEIVED.store(true, Ordering::SeqCst);
+}
+
+#[no_mangle]
+pub fn main() -> usize {
+    println!("signal test!");
+    let pid = fork();
+    if pid == 0 {
+        signal(SIGUSR1, on_usr1);
+        // 屏蔽期间收到的信号会一直等待，解除屏蔽后才处理
+        let old_mask = sigprocmask(SIG_BLOCK, 1 << SIGUSR1).unwrap();
+        sleep(2);
+        println!(
+            "child unblocks SIGUSR1, received: {}",
+            RECEIVED.load(Ordering::SeqCst)
+        );
+        sigprocmask(SIG_SETMASK, old_mask).unwrap();
+        println!("child received: {}", RECEIVED.load(Ordering::SeqCst));
+        // 等待被父进程终止
+        loop {}
+    }
+    sleep(1);
+    kill(pid, SIGUSR1);
+    sleep(2);
+    kill(pid, SIGSTOP);
+    kill(pid, SIGCONT);
+    kill(pid, SIGTERM);
+    let mut status = 0;
+    waitpid(pid, &mut status);
+    println!(
+        "child exited with {}, expected {}",
+        status,
+        SIGNAL_EXIT_BASE + SIGTERM as isize
+    );
+    0
+}
@@ -1,2 +1,2 @@
-/// 每个用户进程所用的堆大小（1M）
-pub const USER_HEAP_SIZE: usize = 0x10_0000;
+/// 用户堆每次通过 brk 扩展的最小大小（1M）
+pub const USER_HEAP_GROW_SIZE: usize = 0x10_0000;
@@ -17,11 +17,27 @@ impl Write for Stdout {
     }
 }
 
+/// 输出到标准错误输出，不受标准输出重定向的影响
+struct Stderr;
+
+impl Write for Stderr {
+    /// 打印一个字符串
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        sys_write(STDERR, s.as_bytes());
+        Ok(())
+    }
+}
+
 /// 打印由 [`core::format_args!`] 格式化后的数据
 pub fn print(args: fmt::Arguments) {
     Stdout.write_fmt(args).unwrap();
 }
 
+/// 将 [`core::format_args!`] 格式化后的数据打印到标准错误输出
+pub fn eprint(args: fmt::Arguments) {
+    Stderr.write_fmt(args).unwrap();
+}
+
 /// 实现类似于标准库中的 `print!` 宏
 #[macro_export]
 macro_rules! print {
@@ -38,6 +54,22 @@ macro_rules! println {
     }
 }
 
+/// 实现类似于标准库中的 `eprint!` 宏
+#[macro_export]
+macro_rules! eprint {
+    ($fmt: literal $(, $($arg: tt)+)?) => {
+        $crate::console::eprint(format_args!($fmt $(, $($arg)+)?));
+    }
+}
+
+/// 实现类似于标准库中的 `eprintln!` 宏
+#[macro_export]
+macro_rules! eprintln {
+    ($fmt: literal $(, $($arg: tt)+)?) => {
+        $crate::console::eprint(format_args!(concat!($fmt, "\n") $(, $($arg)+)?));
+    }
+}
+
 /// 从控制台读取一个字符（阻塞）
 pub fn getchar() -> u8 {
     let mut c = [0u8; 1];
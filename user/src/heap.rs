@@ -0,0 +1,44 @@
+//! 通过 brk 系统调用按需扩展的用户堆 [`GrowableHeap`]
+
+use crate::config::USER_HEAP_GROW_SIZE;
+use crate::sys_brk;
+use buddy_system_allocator::LockedHeap;
+use core::alloc::{GlobalAlloc, Layout};
+use core::cmp::max;
+use core::ptr::{null_mut, NonNull};
+
+/// 在 `buddy_system_allocator` 的堆之上，分配失败时通过 brk 向内核申请更多空间
+pub struct GrowableHeap(LockedHeap);
+
+impl GrowableHeap {
+    /// 创建一个空的堆，第一次分配时再申请空间
+    pub const fn empty() -> Self {
+        Self(LockedHeap::empty())
+    }
+}
+
+unsafe impl GlobalAlloc for GrowableHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut heap = self.0.lock();
+        if let Ok(pointer) = heap.alloc(layout) {
+            return pointer.as_ptr();
+        }
+        // buddy 算法按 2 的幂对齐切分新加入的空间，扩展两倍的大小才能保证放下这次分配
+        let block_size = max(layout.size(), layout.align()).next_power_of_two();
+        let grow_size = max(block_size * 2, USER_HEAP_GROW_SIZE);
+        let start = sys_brk(0);
+        let end = start + grow_size;
+        if sys_brk(end) != end {
+            return null_mut();
+        }
+        heap.add_to_heap(start, end);
+        heap.alloc(layout)
+            .map_or(null_mut(), |pointer| pointer.as_ptr())
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        self.0
+            .lock()
+            .dealloc(NonNull::new_unchecked(pointer), layout)
+    }
+}
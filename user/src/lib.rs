@@ -1,10 +1,11 @@
 //! 为各种用户程序提供依赖
 //!
-//! - 动态内存分配（允许使用 alloc，但总大小固定）
+//! - 动态内存分配（允许使用 alloc，堆空间通过 brk 按需扩展）
 //! - 错误处理（打印信息并退出程序）
 
 #![no_std]
 #![feature(llvm_asm)]
+#![feature(global_asm)]
 #![feature(lang_items)]
 #![feature(panic_info_message)]
 #![feature(linkage)]
@@ -12,12 +13,13 @@
 use core::alloc::Layout;
 use core::panic::PanicInfo;
 
-use buddy_system_allocator::LockedHeap;
+use alloc::vec::Vec;
 
-use config::USER_HEAP_SIZE;
+use heap::GrowableHeap;
 pub use redos::syscall::*;
 
 pub mod config;
+pub mod heap;
 
 #[macro_use]
 pub mod console;
@@ -25,12 +27,9 @@ pub mod redos;
 
 extern crate alloc;
 
-/// 大小为 [`USER_HEAP_SIZE`] 的堆空间
-static mut HEAP_SPACE: [u8; USER_HEAP_SIZE] = [0; USER_HEAP_SIZE];
-
-/// 使用 `buddy_system_allocator` 中的堆
+/// 使用 `buddy_system_allocator` 中的堆，空间不足时通过 brk 扩展
 #[global_allocator]
-static HEAP: LockedHeap = LockedHeap::empty();
+static HEAP: GrowableHeap = GrowableHeap::empty();
 
 /// 打印 panic 信息并退出用户程序
 #[panic_handler]
@@ -48,14 +47,40 @@ fn panic(info: &PanicInfo) -> ! {
     sys_exit(-1);
 }
 
+/// exec 时传入的参数个数
+static mut ARGC: usize = 0;
+
+/// exec 时传入的参数，以空指针结尾
+static mut ARGV: *const *const u8 = core::ptr::null();
+
 /// 程序入口
 #[no_mangle]
-pub extern "C" fn _start(_args: isize, _argv: *const u8) -> ! {
+pub extern "C" fn _start(argc: usize, argv: *const *const u8) -> ! {
+    unsafe {
+        ARGC = argc;
+        ARGV = argv;
+    }
+    // main 返回时结束整个进程，包括尚未退出的其他线程
+    sys_exit_group(main())
+}
+
+/// 获取程序的命令行参数
+///
+/// 由内核直接创建的进程没有参数
+pub fn args() -> Vec<&'static str> {
+    let mut args = Vec::new();
     unsafe {
-        HEAP.lock()
-            .init(HEAP_SPACE.as_ptr() as usize, USER_HEAP_SIZE);
+        for i in 0..ARGC {
+            let pointer = *ARGV.add(i);
+            let mut len = 0;
+            while *pointer.add(len) != 0 {
+                len += 1;
+            }
+            let bytes = core::slice::from_raw_parts(pointer, len);
+            args.push(core::str::from_utf8(bytes).unwrap_or(""));
+        }
     }
-    sys_exit(main())
+    args
 }
 
 /// 默认的 main 函数
@@ -1,7 +1,10 @@
+use alloc::vec::Vec;
 use core::ffi::c_void;
-use lib_redos::ThreadID;
+use lib_redos::{Dirent, ProcessID, Stat, ThreadID, TimeSpec, TimeVal};
 
 pub mod mutex;
+pub mod semaphore;
+pub mod signal;
 pub mod syscall;
 
 pub fn create_thread(thread_id: &mut ThreadID, f: fn(*const c_void), args: *const c_void) -> isize {
@@ -18,6 +21,266 @@ pub fn sleep(sec: u64) {
     crate::syscall(lib_redos::SYS_SLEEP, sec as usize, 0, 0, 0);
 }
 
+/// 休眠 `nanos` 纳秒，线程被终止时提前返回负的错误码
+pub fn nanosleep(nanos: u64) -> isize {
+    crate::syscall(lib_redos::SYS_NANOSLEEP, nanos as usize, 0, 0, 0)
+}
+
+/// 读取时钟 `clock`（`CLOCK_REALTIME` 或 `CLOCK_MONOTONIC`）的时间，出错时返回负的错误码
+pub fn clock_gettime(clock: usize) -> Result<TimeSpec, isize> {
+    let mut time = TimeSpec::default();
+    let ret = crate::syscall(
+        lib_redos::SYS_CLOCK_GETTIME,
+        clock,
+        &mut time as *mut TimeSpec as usize,
+        0,
+        0,
+    );
+    if ret < 0 {
+        Err(ret)
+    } else {
+        Ok(time)
+    }
+}
+
+/// 读取墙上时间，精度为微秒，出错时返回负的错误码
+pub fn gettimeofday() -> Result<TimeVal, isize> {
+    let mut time = TimeVal::default();
+    let ret = crate::syscall(
+        lib_redos::SYS_GETTIMEOFDAY,
+        &mut time as *mut TimeVal as usize,
+        0,
+        0,
+        0,
+    );
+    if ret < 0 {
+        Err(ret)
+    } else {
+        Ok(time)
+    }
+}
+
 pub fn join(thread_id: ThreadID) {
     crate::syscall(lib_redos::SYS_JOIN, thread_id as usize, 0, 0, 0);
 }
+
+/// 复制当前进程，父进程中返回子进程 ID，子进程中返回 0，内存不足时返回 `-ENOMEM`
+pub fn fork() -> ProcessID {
+    crate::syscall(lib_redos::SYS_FORK, 0, 0, 0, 0)
+}
+
+/// 等待子进程退出并回收，`pid` 为 -1 时等待任意子进程
+///
+/// 返回子进程 ID，并将其返回值写入 `status`；出错时返回负的错误码
+pub fn waitpid(pid: ProcessID, status: &mut isize) -> ProcessID {
+    crate::syscall(
+        lib_redos::SYS_WAITPID,
+        pid as usize,
+        status as *mut isize as usize,
+        0,
+        0,
+    )
+}
+
+/// 让出处理器
+pub fn sched_yield() {
+    crate::syscall(lib_redos::SYS_SCHED_YIELD, 0, 0, 0, 0);
+}
+
+/// 设置进程的 nice 值，`pid` 为 0 时表示当前进程
+///
+/// nice 值越小优先级越高，超出 `NICE_MIN..=NICE_MAX` 的值会被截断
+pub fn setpriority(pid: ProcessID, nice: isize) -> isize {
+    crate::syscall(
+        lib_redos::SYS_SETPRIORITY,
+        lib_redos::PRIO_PROCESS,
+        pid as usize,
+        nice as usize,
+        0,
+    )
+}
+
+/// 获取进程的 nice 值，`pid` 为 0 时表示当前进程，出错时返回负的错误码
+pub fn getpriority(pid: ProcessID) -> Result<isize, isize> {
+    let ret = crate::syscall(
+        lib_redos::SYS_GETPRIORITY,
+        lib_redos::PRIO_PROCESS,
+        pid as usize,
+        0,
+        0,
+    );
+    if ret < 0 {
+        Err(ret)
+    } else {
+        Ok(20 - ret)
+    }
+}
+
+/// 用文件系统中的程序替换当前进程
+///
+/// 成功时不会返回，失败时返回负的错误码
+pub fn exec(path: &str, argv: &[&str], envp: &[&str]) -> isize {
+    let path = c_string(path);
+    let argv: Vec<Vec<u8>> = argv.iter().map(|s| c_string(s)).collect();
+    let envp: Vec<Vec<u8>> = envp.iter().map(|s| c_string(s)).collect();
+    let argv_pointers = c_string_array(&argv);
+    let envp_pointers = c_string_array(&envp);
+    crate::syscall(
+        lib_redos::SYS_EXEC,
+        path.as_ptr() as usize,
+        argv_pointers.as_ptr() as usize,
+        envp_pointers.as_ptr() as usize,
+        0,
+    )
+}
+
+/// 打开文件，返回文件描述符，出错时返回负的错误码
+pub fn open(path: &str, flags: usize) -> isize {
+    let path = c_string(path);
+    crate::syscall(lib_redos::SYS_OPEN, path.as_ptr() as usize, flags, 0, 0)
+}
+
+/// 创建一个管道，`fds[0]` 为读端，`fds[1]` 为写端
+pub fn pipe(fds: &mut [usize; 2]) -> isize {
+    crate::syscall(lib_redos::SYS_PIPE, fds.as_mut_ptr() as usize, 0, 0, 0)
+}
+
+/// 关闭文件描述符
+pub fn close(fd: usize) -> isize {
+    crate::syscall(lib_redos::SYS_CLOSE, fd, 0, 0, 0)
+}
+
+/// 复制文件描述符，返回最小的未被占用的编号
+pub fn dup(fd: usize) -> isize {
+    crate::syscall(lib_redos::SYS_DUP, fd, 0, 0, 0)
+}
+
+/// 令 `new_fd` 指向 `old_fd` 所指向的文件，`flags` 可以为 `O_CLOEXEC`
+pub fn dup2(old_fd: usize, new_fd: usize, flags: usize) -> isize {
+    crate::syscall(lib_redos::SYS_DUP2, old_fd, new_fd, flags, 0)
+}
+
+/// 移动文件的读写位置，返回新的读写位置
+pub fn lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    crate::syscall(lib_redos::SYS_LSEEK, fd, offset as usize, whence, 0)
+}
+
+/// 创建目录
+pub fn mkdir(path: &str) -> isize {
+    let path = c_string(path);
+    crate::syscall(lib_redos::SYS_MKDIR, path.as_ptr() as usize, 0, 0, 0)
+}
+
+/// 删除文件或空目录
+pub fn unlink(path: &str) -> isize {
+    let path = c_string(path);
+    crate::syscall(lib_redos::SYS_UNLINK, path.as_ptr() as usize, 0, 0, 0)
+}
+
+/// 移动或重命名文件
+pub fn rename(old_path: &str, new_path: &str) -> isize {
+    let old_path = c_string(old_path);
+    let new_path = c_string(new_path);
+    crate::syscall(
+        lib_redos::SYS_RENAME,
+        old_path.as_ptr() as usize,
+        new_path.as_ptr() as usize,
+        0,
+        0,
+    )
+}
+
+/// 改变当前进程的工作目录
+pub fn chdir(path: &str) -> isize {
+    let path = c_string(path);
+    crate::syscall(lib_redos::SYS_CHDIR, path.as_ptr() as usize, 0, 0, 0)
+}
+
+/// 将工作目录的绝对路径以 `\0` 结尾写入 `buffer`，返回路径的长度
+pub fn getcwd(buffer: &mut [u8]) -> isize {
+    crate::syscall(
+        lib_redos::SYS_GETCWD,
+        buffer.as_mut_ptr() as usize,
+        buffer.len(),
+        0,
+        0,
+    )
+}
+
+/// 获取路径为 `path` 的文件的信息
+pub fn stat(path: &str, stat: &mut Stat) -> isize {
+    let path = c_string(path);
+    crate::syscall(
+        lib_redos::SYS_STAT,
+        path.as_ptr() as usize,
+        stat as *mut Stat as usize,
+        0,
+        0,
+    )
+}
+
+/// 获取文件描述符对应的文件的信息
+pub fn fstat(fd: usize, stat: &mut Stat) -> isize {
+    crate::syscall(lib_redos::SYS_FSTAT, fd, stat as *mut Stat as usize, 0, 0)
+}
+
+/// 从目录中读取目录项，返回读取的项数，读完时返回 0
+pub fn getdents(fd: usize, dirents: &mut [Dirent]) -> isize {
+    crate::syscall(
+        lib_redos::SYS_GETDENTS,
+        fd,
+        dirents.as_mut_ptr() as usize,
+        dirents.len(),
+        0,
+    )
+}
+
+/// 建立一段内存映射，`address` 为 0 时由内核选择地址
+///
+/// 返回映射的起始地址，出错时返回负的错误码。不使用 `MAP_ANONYMOUS` 时映射文件 `fd`，
+/// `offset` 必须按页对齐；匿名映射只能是 `MAP_PRIVATE`
+pub fn mmap(
+    address: usize,
+    length: usize,
+    prot: usize,
+    flags: usize,
+    fd: usize,
+    offset: usize,
+) -> isize {
+    crate::syscall6(
+        lib_redos::SYS_MMAP,
+        [address, length, prot, flags, fd, offset],
+    )
+}
+
+/// 移除一段内存映射
+pub fn munmap(address: usize, length: usize) -> isize {
+    crate::syscall(lib_redos::SYS_MUNMAP, address, length, 0, 0)
+}
+
+/// 将一段共享文件映射的修改写回文件
+pub fn msync(address: usize, length: usize) -> isize {
+    crate::syscall(lib_redos::SYS_MSYNC, address, length, 0, 0)
+}
+
+/// 修改一段内存映射的权限
+pub fn mprotect(address: usize, length: usize, prot: usize) -> isize {
+    crate::syscall(lib_redos::SYS_MPROTECT, address, length, prot, 0)
+}
+
+/// 生成以 `\0` 结尾的字符串
+fn c_string(s: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(s.len() + 1);
+    bytes.extend_from_slice(s.as_bytes());
+    bytes.push(0);
+    bytes
+}
+
+/// 生成以空指针结尾的字符串指针数组
+fn c_string_array(strings: &[Vec<u8>]) -> Vec<*const u8> {
+    strings
+        .iter()
+        .map(|s| s.as_ptr())
+        .chain(core::iter::once(core::ptr::null()))
+        .collect()
+}
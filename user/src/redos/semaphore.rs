@@ -0,0 +1,62 @@
+//! 计数信号量 [`Semaphore`]，由内核维护资源数和等待的线程
+
+use crate::syscall;
+use lib_redos::SemID;
+
+/// 计数信号量，同一进程中的线程共享
+///
+/// 资源数为 0 时 [`Semaphore::wait`] 在内核中休眠，不会占用处理器
+pub struct Semaphore {
+    sem_id: SemID,
+}
+
+impl Semaphore {
+    /// 创建资源数为 `value` 的信号量
+    pub fn new(value: usize) -> Semaphore {
+        let mut sem_id: SemID = 0;
+        let ret = syscall(
+            lib_redos::SYS_SEM_CREATE,
+            &mut sem_id as *mut SemID as usize,
+            value,
+            0,
+            0,
+        );
+        assert_eq!(ret, 0, "failed to create semaphore");
+        Semaphore { sem_id }
+    }
+
+    /// 取走一个资源，没有资源时阻塞；出错时返回负的错误码
+    pub fn wait(&self) -> isize {
+        syscall(
+            lib_redos::SYS_SEM_WAIT,
+            &self.sem_id as *const SemID as usize,
+            0,
+            0,
+            0,
+        )
+    }
+
+    /// 归还一个资源，唤醒一个等待的线程；出错时返回负的错误码
+    pub fn post(&self) -> isize {
+        syscall(
+            lib_redos::SYS_SEM_POST,
+            &self.sem_id as *const SemID as usize,
+            0,
+            0,
+            0,
+        )
+    }
+}
+
+impl Drop for Semaphore {
+    fn drop(&mut self) {
+        let res = syscall(
+            lib_redos::SYS_SEM_DESTROY,
+            &self.sem_id as *const SemID as usize,
+            0,
+            0,
+            0,
+        );
+        debug_assert_eq!(res, 0);
+    }
+}
@@ -0,0 +1,93 @@
+//! 信号
+
+use lib_redos::{ProcessID, SigAction, SigSet, SIG_DFL, SIG_IGN};
+
+// 信号处理函数的返回地址：处理函数返回时栈指针指向内核保存的现场，
+// 这里不能修改栈指针，直接调用 `SYS_SIGRETURN`（139 号）
+global_asm!(
+    "
+    .section .text
+    .globl __sigreturn
+__sigreturn:
+    li a7, 139
+    ecall
+"
+);
+
+extern "C" {
+    fn __sigreturn();
+}
+
+/// 信号处理函数，参数为信号编号
+pub type SignalHandler = extern "C" fn(usize);
+
+/// 异常信号（如 `SIGSEGV`）的处理函数，参数为信号编号和引起异常的地址
+///
+/// 处理函数返回后会重新执行引起异常的指令，因此需要先消除异常的原因（例如用 mprotect 修改权限）
+pub type FaultHandler = extern "C" fn(usize, usize);
+
+/// 向进程发送信号
+pub fn kill(pid: ProcessID, signal: usize) -> isize {
+    crate::syscall(lib_redos::SYS_KILL, pid as usize, signal, 0, 0)
+}
+
+/// 设置信号的处理方式，`action.restorer` 会被自动设置，原来的处理方式写入 `old_action`
+pub fn sigaction(signal: usize, action: &SigAction, old_action: Option<&mut SigAction>) -> isize {
+    let action = SigAction {
+        restorer: __sigreturn as usize,
+        ..*action
+    };
+    let old_action = old_action.map_or(core::ptr::null_mut(), |a| a as *mut SigAction);
+    crate::syscall(
+        lib_redos::SYS_SIGACTION,
+        signal,
+        &action as *const SigAction as usize,
+        old_action as usize,
+        0,
+    )
+}
+
+/// 设置信号的处理函数
+pub fn signal(signal: usize, handler: SignalHandler) -> isize {
+    set_handler(signal, handler as usize)
+}
+
+/// 设置异常信号的处理函数
+pub fn on_fault(signal: usize, handler: FaultHandler) -> isize {
+    set_handler(signal, handler as usize)
+}
+
+/// 忽略信号
+pub fn ignore(signal: usize) -> isize {
+    set_handler(signal, SIG_IGN)
+}
+
+/// 恢复信号的默认处理方式
+pub fn default(signal: usize) -> isize {
+    set_handler(signal, SIG_DFL)
+}
+
+/// 修改当前线程屏蔽的信号，返回原来屏蔽的信号，出错时返回负的错误码
+pub fn sigprocmask(how: usize, set: SigSet) -> Result<SigSet, isize> {
+    let mut old_set: SigSet = 0;
+    let ret = crate::syscall(
+        lib_redos::SYS_SIGPROCMASK,
+        how,
+        &set as *const SigSet as usize,
+        &mut old_set as *mut SigSet as usize,
+        0,
+    );
+    if ret < 0 {
+        Err(ret)
+    } else {
+        Ok(old_set)
+    }
+}
+
+fn set_handler(signal: usize, handler: usize) -> isize {
+    let action = SigAction {
+        handler,
+        ..SigAction::default()
+    };
+    sigaction(signal, &action, None)
+}
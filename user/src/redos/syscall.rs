@@ -4,6 +4,7 @@ use lib_redos;
 
 pub const STDIN: usize = 0;
 pub const STDOUT: usize = 1;
+pub const STDERR: usize = 2;
 
 /// 将参数放在对应寄存器中，并执行 `ecall`
 #[inline(always)]
@@ -20,26 +21,28 @@ pub(crate) fn syscall(id: usize, arg0: usize, arg1: usize, arg2: usize, arg3: us
     ret
 }
 
-/// 读取字符
-pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
-    loop {
-        let ret = syscall(
-            lib_redos::SYS_READ,
-            fd,
-            buffer as *const [u8] as *const u8 as usize,
-            buffer.len(),
-            0,
-        );
-        if ret > 0 {
-            return ret;
-        }
+/// 与 [`syscall`] 相同，但使用 6 个参数
+#[inline(always)]
+pub(crate) fn syscall6(id: usize, args: [usize; 6]) -> isize {
+    // 返回值
+    let mut ret = 0;
+    unsafe {
+        llvm_asm!("ecall"
+            : "={x10}" (ret)
+            : "{x10}" (args[0]), "{x11}" (args[1]), "{x12}" (args[2]), "{x13}" (args[3]),
+              "{x14}" (args[4]), "{x15}" (args[5]), "{x17}" (id)
+            : "memory"
+            : "volatile");
     }
+    ret
 }
 
-/// 打印字符串
-pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
+/// 读取字符，暂无数据时阻塞
+///
+/// 返回读取的字节数，读到文件末尾时返回 0，出错时返回负的错误码
+pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
     syscall(
-        lib_redos::SYS_WRITE,
+        lib_redos::SYS_READ,
         fd,
         buffer as *const [u8] as *const u8 as usize,
         buffer.len(),
@@ -47,13 +50,48 @@ pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
     )
 }
 
+/// 打印字符串，管道缓冲区已满时阻塞，直到全部写入或出错
+///
+/// 返回写入的字节数，没有写入任何数据就出错时返回负的错误码
+pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
+    let mut written = 0;
+    while written < buffer.len() {
+        let ret = syscall(
+            lib_redos::SYS_WRITE,
+            fd,
+            buffer[written..].as_ptr() as usize,
+            buffer.len() - written,
+            0,
+        );
+        if ret <= 0 {
+            if written == 0 {
+                return ret;
+            }
+            break;
+        }
+        written += ret as usize;
+    }
+    written as isize
+}
+
 /// 退出并返回数值
 pub fn sys_exit(code: isize) -> ! {
     syscall(lib_redos::SYS_EXIT, code as usize, 0, 0, 0);
     unreachable!()
 }
 
+/// 结束整个进程并返回数值
+pub fn sys_exit_group(code: isize) -> ! {
+    syscall(lib_redos::SYS_EXIT_GROUP, code as usize, 0, 0, 0);
+    unreachable!()
+}
+
 fn sys_exit0() -> ! {
     syscall(lib_redos::SYS_EXIT, 0, 0, 0, 0);
     unreachable!()
 }
+
+/// 设置 program break，`brk` 为 0 时返回当前值
+pub fn sys_brk(brk: usize) -> usize {
+    syscall(lib_redos::SYS_BRK, brk, 0, 0, 0) as usize
+}